@@ -0,0 +1,204 @@
+//! Measures how long the processes list takes to compute its updates, with synthetic process
+//! tables of different sizes. Run it with `cargo bench`.
+
+#[allow(dead_code)]
+#[path = "../src/process_index.rs"]
+mod process_index;
+
+use process_index::{compute_subtree_totals, ProcessEntry, ProcessIndex, ViewMode};
+use sysinfo::Pid;
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+const SIZES: &[usize] = &[1_000, 5_000, 10_000];
+const ITERATIONS: u32 = 50;
+
+struct FakeProcess {
+    pid: Pid,
+    parent: Option<Pid>,
+    start_time: u64,
+    cpu_usage: f32,
+    memory: u64,
+    thread: bool,
+    application: u32,
+}
+
+impl ProcessEntry for FakeProcess {
+    fn pid(&self) -> Pid {
+        self.pid
+    }
+
+    fn parent(&self) -> Option<Pid> {
+        self.parent
+    }
+
+    fn start_time(&self) -> u64 {
+        self.start_time
+    }
+
+    fn cpu_usage(&self) -> f32 {
+        self.cpu_usage
+    }
+
+    fn memory(&self) -> u64 {
+        self.memory
+    }
+
+    fn is_userland_thread(&self) -> bool {
+        self.thread
+    }
+
+    fn group_name(&self) -> String {
+        format!("application-{}", self.application)
+    }
+}
+
+/// A linear congruential generator, so the tables are the same from one run to the other.
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        self.0 >> 33
+    }
+
+    fn below(&mut self, max: u64) -> u64 {
+        self.next() % max.max(1)
+    }
+}
+
+struct Table {
+    entries: HashMap<Pid, FakeProcess>,
+    pids: Vec<Pid>,
+    next_pid: u32,
+    time: u64,
+    random: Random,
+}
+
+impl Table {
+    fn new(size: usize) -> Self {
+        let mut table = Self {
+            entries: HashMap::with_capacity(size),
+            pids: Vec::with_capacity(size),
+            next_pid: 1,
+            time: 0,
+            random: Random(42),
+        };
+        for _ in 0..size {
+            table.spawn();
+        }
+        table
+    }
+
+    /// Adds a process, under one of the existing ones. About one in five is a thread.
+    fn spawn(&mut self) {
+        let pid = Pid::from_u32(self.next_pid);
+        self.next_pid += 1;
+        self.time += 1;
+        let parent = if self.pids.is_empty() {
+            None
+        } else {
+            Some(self.pids[self.random.below(self.pids.len() as u64) as usize])
+        };
+        let process = FakeProcess {
+            pid,
+            parent,
+            start_time: self.time,
+            cpu_usage: self.random.below(1000) as f32 / 10.,
+            memory: self.random.below(1 << 30),
+            thread: parent.is_some() && self.random.below(5) == 0,
+            application: self.random.below(200) as u32,
+        };
+        self.entries.insert(pid, process);
+        self.pids.push(pid);
+    }
+
+    /// Changes the CPU usage of `count` processes.
+    fn change_usage(&mut self, count: usize) {
+        for _ in 0..count {
+            let pid = self.pids[self.random.below(self.pids.len() as u64) as usize];
+            if let Some(process) = self.entries.get_mut(&pid) {
+                process.cpu_usage = self.random.below(1000) as f32 / 10.;
+            }
+        }
+    }
+
+    /// Ends `count` processes and starts as many new ones.
+    fn churn(&mut self, count: usize) {
+        for _ in 0..count {
+            let pos = self.random.below(self.pids.len() as u64) as usize;
+            let pid = self.pids.swap_remove(pos);
+            self.entries.remove(&pid);
+        }
+        for _ in 0..count {
+            self.spawn();
+        }
+    }
+}
+
+/// Applies the changes of an update, like the processes list does. The rows are the PIDs.
+fn update(index: &mut ProcessIndex<u32>, table: &Table, view_mode: ViewMode) -> usize {
+    // Only the CPU usage of the processes changes between updates.
+    let changes = index.diff(&table.entries, view_mode, |p| p.cpu_usage.to_bits().into());
+    let nb_changes = changes.updated.len() + changes.removed.len() + changes.added.len();
+    for name in changes.groups.keys() {
+        if index.group(name).is_none() {
+            index.insert_group(name.clone(), 0);
+        }
+    }
+    for (p, parent) in changes.added {
+        index.insert(p, parent, p.pid.as_u32());
+    }
+    nb_changes
+}
+
+fn measure(mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    println!(
+        "{:>8} {:>24} {:>12} {:>12} {:>12} {:>12}",
+        "size", "view mode", "first", "usage", "churn", "totals"
+    );
+    for size in SIZES.iter().copied() {
+        for view_mode in ViewMode::ALL.iter().copied() {
+            let mut table = Table::new(size);
+
+            let first = measure(|| {
+                let mut index = ProcessIndex::new();
+                std::hint::black_box(update(&mut index, &table, view_mode));
+            });
+
+            let mut index = ProcessIndex::new();
+            update(&mut index, &table, view_mode);
+            let usage = measure(|| {
+                table.change_usage(size / 20);
+                std::hint::black_box(update(&mut index, &table, view_mode));
+            });
+            let churn = measure(|| {
+                table.churn(size / 100);
+                std::hint::black_box(update(&mut index, &table, view_mode));
+            });
+            let totals = measure(|| {
+                std::hint::black_box(compute_subtree_totals(
+                    &table.entries,
+                    view_mode == ViewMode::Tree,
+                ));
+            });
+
+            println!(
+                "{size:>8} {:>24} {first:>12.2?} {usage:>12.2?} {churn:>12.2?} {totals:>12.2?}",
+                view_mode.title(),
+            );
+        }
+    }
+}
@@ -0,0 +1,305 @@
+use gtk::glib;
+use gtk::prelude::*;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate};
+
+use crate::signals::show_results;
+use crate::utils::show_error_dialog;
+
+use std::cell::RefCell;
+use std::io;
+use std::rc::Rc;
+
+// Number of CPU checkboxes per line in the dialog.
+const CPUS_PER_LINE: usize = 8;
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn explain_error(pid: Pid, error: io::Error) -> String {
+    match error.raw_os_error() {
+        Some(libc::EPERM) => format!(
+            "You are not allowed to change the CPU affinity of process {pid}. Changing the CPU \
+             affinity of a process owned by another user requires administrator privileges."
+        ),
+        Some(libc::ESRCH) => format!("Process {pid} doesn't exist anymore"),
+        Some(libc::EINVAL) => {
+            format!("None of the selected CPUs can be used by process {pid}")
+        }
+        _ => format!("Failed to change the CPU affinity of process {pid}: {error}"),
+    }
+}
+
+/// Returns the CPUs the given process (or thread) is allowed to run on.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn cpu_affinity(pid: Pid) -> Result<Vec<usize>, String> {
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        if libc::sched_getaffinity(
+            pid.as_u32() as _,
+            std::mem::size_of::<libc::cpu_set_t>(),
+            &mut set,
+        ) != 0
+        {
+            return Err(format!(
+                "Failed to get the CPU affinity of process {pid}: {}",
+                io::Error::last_os_error()
+            ));
+        }
+        Ok((0..libc::CPU_SETSIZE as usize)
+            .filter(|cpu| libc::CPU_ISSET(*cpu, &set))
+            .collect())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn cpu_affinity(_pid: Pid) -> Result<Vec<usize>, String> {
+    Err("CPU affinity is not supported on this platform".to_owned())
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn sched_set_affinity(pid: Pid, cpus: &[usize]) -> io::Result<()> {
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        for cpu in cpus {
+            libc::CPU_SET(*cpu, &mut set);
+        }
+        if libc::sched_setaffinity(
+            pid.as_u32() as _,
+            std::mem::size_of::<libc::cpu_set_t>(),
+            &set,
+        ) != 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Restricts the given process (or thread) to the given CPUs.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn set_cpu_affinity(pid: Pid, cpus: &[usize]) -> Result<(), String> {
+    sched_set_affinity(pid, cpus).map_err(|error| explain_error(pid, error))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn set_cpu_affinity(_pid: Pid, _cpus: &[usize]) -> Result<(), String> {
+    Err("CPU affinity is not supported on this platform".to_owned())
+}
+
+/// Restricts the given thread to the given CPUs. Threads which ended in the meantime are
+/// ignored.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn set_thread_affinity(tid: Pid, cpus: &[usize]) -> Result<(), String> {
+    match sched_set_affinity(tid, cpus) {
+        Err(error) if error.raw_os_error() != Some(libc::ESRCH) => Err(explain_error(tid, error)),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn set_thread_affinity(tid: Pid, cpus: &[usize]) -> Result<(), String> {
+    set_cpu_affinity(tid, cpus)
+}
+
+/// Returns the ids of the online CPUs. They aren't always contiguous: CPUs can be taken offline.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn online_cpus(sys: &sysinfo::System) -> Vec<usize> {
+    std::fs::read_to_string("/sys/devices/system/cpu/online")
+        .ok()
+        .and_then(|online| parse_cpu_list(&online))
+        .unwrap_or_else(|| (0..sys.cpus().len()).collect())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn online_cpus(sys: &sysinfo::System) -> Vec<usize> {
+    (0..sys.cpus().len()).collect()
+}
+
+/// Parses a list of CPUs like `0-3,8,10-11`, the format used by the kernel.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn parse_cpu_list(list: &str) -> Option<Vec<usize>> {
+    let mut cpus = Vec::new();
+    for range in list.trim().split(',') {
+        let (first, last) = range.split_once('-').unwrap_or((range, range));
+        cpus.extend(first.parse::<usize>().ok()?..=last.parse().ok()?);
+    }
+    Some(cpus)
+}
+
+/// Shows a dialog to choose on which CPUs the processes `pids` can run. The CPUs checked by
+/// default are the ones of the first process.
+pub fn show_affinity_dialog<T: IsA<gtk::Window>>(
+    window: &T,
+    sys: &Rc<RefCell<sysinfo::System>>,
+    pids: Vec<Pid>,
+) {
+    let Some(first) = pids.first().copied() else {
+        return;
+    };
+    let current = match cpu_affinity(first) {
+        Ok(current) => current,
+        Err(error) => {
+            show_error_dialog(false, &error);
+            return;
+        }
+    };
+    let cpus = online_cpus(&sys.borrow());
+
+    let title = if pids.len() == 1 {
+        format!("CPU affinity of process {first}")
+    } else {
+        format!("CPU affinity of {} processes", pids.len())
+    };
+    let dialog = gtk::Dialog::with_buttons(
+        Some(&title),
+        Some(window),
+        gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT,
+        &[
+            ("Apply", gtk::ResponseType::Apply),
+            ("Cancel", gtk::ResponseType::Cancel),
+        ],
+    );
+    let layout = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(6)
+        .css_classes(vec!["button-with-margin".to_owned()])
+        .build();
+    layout.append(&gtk::Label::new(Some("Allowed CPUs:")));
+
+    let grid = gtk::Grid::builder()
+        .column_spacing(4)
+        .row_spacing(4)
+        .build();
+    // The CPU ids are kept with their checkbox, they don't always match its position.
+    let check_buttons = Rc::new(
+        cpus.into_iter()
+            .enumerate()
+            .map(|(pos, cpu)| {
+                let check = gtk::CheckButton::builder()
+                    .label(format!("CPU {cpu}"))
+                    .active(current.contains(&cpu))
+                    .build();
+                grid.attach(
+                    &check,
+                    (pos % CPUS_PER_LINE) as _,
+                    (pos / CPUS_PER_LINE) as _,
+                    1,
+                    1,
+                );
+                (cpu, check)
+            })
+            .collect::<Vec<_>>(),
+    );
+    layout.append(&grid);
+
+    let select_all = gtk::Button::with_label("Select all");
+    let select_none = gtk::Button::with_label("Select none");
+    let selection_buttons = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+    selection_buttons.append(&select_all);
+    selection_buttons.append(&select_none);
+    layout.append(&selection_buttons);
+    for (button, active) in [(&select_all, true), (&select_none, false)] {
+        button.connect_clicked(glib::clone!(
+            #[strong]
+            check_buttons,
+            move |_| {
+                for (_, check) in check_buttons.iter() {
+                    check.set_active(active);
+                }
+            }
+        ));
+    }
+
+    let all_threads = gtk::CheckButton::builder()
+        .label("Apply to all threads")
+        .active(true)
+        .build();
+    layout.append(&all_threads);
+
+    // A process needs at least one CPU to run on.
+    let update_sensitivity = glib::clone!(
+        #[weak]
+        dialog,
+        #[weak]
+        grid,
+        move |_: &gtk::CheckButton| {
+            let mut child = grid.first_child();
+            let mut any_active = false;
+            while let Some(c) = child {
+                any_active |= c
+                    .downcast_ref::<gtk::CheckButton>()
+                    .is_some_and(|c| c.is_active());
+                child = c.next_sibling();
+            }
+            dialog.set_response_sensitive(gtk::ResponseType::Apply, any_active);
+        }
+    );
+    for (_, check) in check_buttons.iter() {
+        check.connect_toggled(update_sensitivity.clone());
+    }
+    if let Some((_, check)) = check_buttons.first() {
+        update_sensitivity(check);
+    }
+
+    dialog.connect_response(glib::clone!(
+        #[weak]
+        sys,
+        #[weak]
+        all_threads,
+        #[strong]
+        check_buttons,
+        move |dialog, response| {
+            dialog.close();
+            if response != gtk::ResponseType::Apply {
+                return;
+            }
+            let cpus = check_buttons
+                .iter()
+                .filter(|(_, check)| check.is_active())
+                .map(|(cpu, _)| *cpu)
+                .collect::<Vec<_>>();
+            let mut sys = sys.borrow_mut();
+            // The threads are needed to change their affinity too.
+            sys.refresh_processes_specifics(
+                ProcessesToUpdate::Some(&pids),
+                true,
+                ProcessRefreshKind::nothing().with_tasks(),
+            );
+            let results = pids
+                .iter()
+                .map(|pid| {
+                    let mut result = set_cpu_affinity(*pid, &cpus);
+                    // The affinity is per thread, so changing it for the process only impacts
+                    // its main thread.
+                    if result.is_ok() && all_threads.is_active() {
+                        if let Some(tasks) = sys.process(*pid).and_then(|p| p.tasks()) {
+                            for task in tasks {
+                                if let Err(error) = set_thread_affinity(*task, &cpus) {
+                                    result = Err(error);
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                    (*pid, result)
+                })
+                .collect::<Vec<_>>();
+            show_results("Changed CPU affinity", &results);
+        }
+    ));
+
+    dialog.content_area().append(&layout);
+    dialog.show();
+}
+
+#[cfg(all(test, any(target_os = "linux", target_os = "android")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpu_list() {
+        assert_eq!(parse_cpu_list("0-3\n"), Some(vec![0, 1, 2, 3]));
+        assert_eq!(parse_cpu_list("0,2-3,6"), Some(vec![0, 2, 3, 6]));
+        assert_eq!(parse_cpu_list("5"), Some(vec![5]));
+        assert_eq!(parse_cpu_list(""), None);
+        assert_eq!(parse_cpu_list("0-three"), None);
+    }
+}
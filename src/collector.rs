@@ -0,0 +1,486 @@
+// Collects the information displayed by the application in a single thread, which owns the
+// `sysinfo` values. Each kind of information is refreshed at its own rate and published to the
+// UI as an immutable snapshot.
+
+use sysinfo::{
+    Components, CpuRefreshKind, MacAddr, MemoryRefreshKind, Networks, Pid, Process,
+    ProcessRefreshKind, ProcessStatus, ProcessesToUpdate, RefreshKind, System, ThreadKind, Uid,
+    MINIMUM_CPU_UPDATE_INTERVAL,
+};
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::memory_maps::{memory_rollup, MemoryUsage};
+use crate::priority::{io_priority, nice_value, IoPriority};
+use crate::process_index::ProcessEntry;
+use crate::settings::Settings;
+
+/// A kind of information refreshed by the collector, each at its own rate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Subsystem {
+    Processes,
+    System,
+    Networks,
+}
+
+impl Subsystem {
+    const ALL: [Subsystem; 3] = [Subsystem::Processes, Subsystem::System, Subsystem::Networks];
+}
+
+/// What is refreshed about the processes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProcessRefresh {
+    pub kind: ProcessRefreshKind,
+    /// Whether the nice values and I/O priorities are read. They aren't provided by `sysinfo`
+    /// and need a syscall per process.
+    pub priorities: bool,
+    /// Whether the proportional and unique set sizes and the swapped memory are read. Reading
+    /// them makes the kernel walk the pages of each process.
+    pub memory_details: bool,
+}
+
+/// What the collector refreshes, depending on what is displayed. Nothing is refreshed until
+/// it is set with [`Collector::set_refreshes`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Refreshes {
+    /// `None` if no process information is displayed.
+    pub processes: Option<ProcessRefresh>,
+    pub system: bool,
+    pub networks: bool,
+}
+
+impl Refreshes {
+    fn is_enabled(&self, subsystem: Subsystem) -> bool {
+        match subsystem {
+            Subsystem::Processes => self.processes.is_some(),
+            Subsystem::System => self.system,
+            Subsystem::Networks => self.networks,
+        }
+    }
+
+    /// Returns `true` if `subsystem` is enabled in `self` and wasn't refreshed the same way in
+    /// `old`.
+    fn changed(&self, old: &Refreshes, subsystem: Subsystem) -> bool {
+        self.is_enabled(subsystem)
+            && match subsystem {
+                Subsystem::Processes => self.processes != old.processes,
+                Subsystem::System => !old.system,
+                Subsystem::Networks => !old.networks,
+            }
+    }
+}
+
+/// The information about a process at the time of a snapshot.
+#[derive(Clone, Debug)]
+pub struct ProcessInfo {
+    pub pid: Pid,
+    pub parent: Option<Pid>,
+    pub name: OsString,
+    pub cmd: Vec<OsString>,
+    pub exe: Option<PathBuf>,
+    pub user_id: Option<Uid>,
+    pub status: ProcessStatus,
+    pub thread_kind: Option<ThreadKind>,
+    pub start_time: u64,
+    pub run_time: u64,
+    pub cpu_usage: f32,
+    pub memory: u64,
+    pub virtual_memory: u64,
+    /// Bytes read and written since the previous refresh.
+    pub disk_usage: u64,
+    /// Number of threads, including the main one. `None` if they aren't known.
+    pub threads: Option<usize>,
+    /// `None` if it couldn't be read or if the priorities weren't refreshed.
+    pub nice: Option<i32>,
+    pub io_priority: Option<IoPriority>,
+    /// `None` if it couldn't be read or if the memory details weren't refreshed.
+    pub memory_details: Option<MemoryUsage>,
+}
+
+impl ProcessInfo {
+    fn new(p: &Process, refresh: &ProcessRefresh) -> Self {
+        let priorities = refresh.priorities;
+        let disk_usage = p.disk_usage();
+        Self {
+            pid: p.pid(),
+            parent: p.parent(),
+            name: p.name().to_owned(),
+            cmd: p.cmd().to_vec(),
+            exe: p.exe().map(Path::to_path_buf),
+            user_id: p.user_id().cloned(),
+            status: p.status(),
+            thread_kind: p.thread_kind(),
+            start_time: p.start_time(),
+            run_time: p.run_time(),
+            cpu_usage: p.cpu_usage(),
+            memory: p.memory(),
+            virtual_memory: p.virtual_memory(),
+            disk_usage: disk_usage.written_bytes + disk_usage.read_bytes,
+            threads: p.tasks().map(|tasks| tasks.len() + 1),
+            nice: priorities.then(|| nice_value(p.pid())).flatten(),
+            io_priority: priorities.then(|| io_priority(p.pid())).flatten(),
+            memory_details: refresh
+                .memory_details
+                .then(|| memory_rollup(p.pid()))
+                .flatten(),
+        }
+    }
+
+    /// The name of the application: the name of its executable if it is known.
+    pub fn application_name(&self) -> &OsStr {
+        self.exe
+            .as_deref()
+            .and_then(Path::file_name)
+            .unwrap_or(&self.name)
+    }
+}
+
+impl ProcessEntry for ProcessInfo {
+    fn pid(&self) -> Pid {
+        self.pid
+    }
+
+    fn parent(&self) -> Option<Pid> {
+        self.parent
+    }
+
+    fn start_time(&self) -> u64 {
+        self.start_time
+    }
+
+    fn cpu_usage(&self) -> f32 {
+        self.cpu_usage
+    }
+
+    fn memory(&self) -> u64 {
+        self.memory
+    }
+
+    fn is_userland_thread(&self) -> bool {
+        self.thread_kind == Some(ThreadKind::Userland)
+    }
+
+    fn group_name(&self) -> String {
+        self.application_name().to_string_lossy().into_owned()
+    }
+}
+
+pub type ProcessTable = HashMap<Pid, ProcessInfo>;
+
+#[derive(Clone, Debug)]
+pub struct ComponentInfo {
+    pub label: String,
+    pub temperature: Option<f32>,
+}
+
+/// The usage of the CPUs and of the memory, and the temperature of the components.
+#[derive(Clone, Debug)]
+pub struct SystemInfo {
+    pub global_cpu_usage: f32,
+    pub cpu_usages: Vec<f32>,
+    pub total_memory: u64,
+    pub used_memory: u64,
+    pub total_swap: u64,
+    pub used_swap: u64,
+    pub components: Vec<ComponentInfo>,
+}
+
+/// The information about a network interface. The values which aren't `total_*` are the ones
+/// since the previous refresh.
+#[derive(Clone, Debug)]
+pub struct NetworkInfo {
+    pub mac_address: MacAddr,
+    pub received: u64,
+    pub total_received: u64,
+    pub transmitted: u64,
+    pub total_transmitted: u64,
+    pub packets_received: u64,
+    pub total_packets_received: u64,
+    pub packets_transmitted: u64,
+    pub total_packets_transmitted: u64,
+    pub errors_on_received: u64,
+    pub total_errors_on_received: u64,
+    pub errors_on_transmitted: u64,
+    pub total_errors_on_transmitted: u64,
+}
+
+/// The network interfaces, by name.
+pub type NetworkTable = HashMap<String, NetworkInfo>;
+
+pub enum Snapshot {
+    Processes(ProcessTable),
+    System(SystemInfo),
+    Networks(NetworkTable),
+}
+
+/// The snapshots collected when the collector starts, to build the UI with.
+pub struct InitialSnapshots {
+    pub processes: ProcessTable,
+    pub system: SystemInfo,
+    pub networks: NetworkTable,
+}
+
+enum Command {
+    SetRate(Subsystem, Duration),
+    SetRefreshes(Refreshes),
+    Stop,
+}
+
+struct Schedule {
+    rate: Duration,
+    next: Instant,
+}
+
+/// Everything owned by the collector thread.
+struct State {
+    sys: System,
+    components: Components,
+    networks: Networks,
+    refreshes: Refreshes,
+    /// Indexed by [`Subsystem`].
+    schedules: [Schedule; 3],
+}
+
+impl State {
+    fn new(settings: &Settings) -> Self {
+        let sys = System::new_with_specifics(RefreshKind::everything());
+        // The CPU usage is computed between two refreshes, so the first one can't happen
+        // right away.
+        let next = Instant::now() + MINIMUM_CPU_UPDATE_INTERVAL;
+        let schedule = |rate: u32| Schedule {
+            rate: Duration::from_millis(rate.into()),
+            next,
+        };
+        Self {
+            sys,
+            components: Components::new_with_refreshed_list(),
+            networks: Networks::new_with_refreshed_list(),
+            refreshes: Refreshes::default(),
+            schedules: [
+                schedule(settings.refresh_processes_rate),
+                schedule(settings.refresh_system_rate),
+                schedule(settings.refresh_network_rate),
+            ],
+        }
+    }
+
+    fn schedule(&mut self, subsystem: Subsystem) -> &mut Schedule {
+        &mut self.schedules[subsystem as usize]
+    }
+
+    fn process_table(&self, refresh: &ProcessRefresh) -> ProcessTable {
+        self.sys
+            .processes()
+            .iter()
+            .map(|(pid, p)| (*pid, ProcessInfo::new(p, refresh)))
+            .collect()
+    }
+
+    fn system_info(&self) -> SystemInfo {
+        SystemInfo {
+            global_cpu_usage: self.sys.global_cpu_usage(),
+            cpu_usages: self.sys.cpus().iter().map(|cpu| cpu.cpu_usage()).collect(),
+            total_memory: self.sys.total_memory(),
+            used_memory: self.sys.used_memory(),
+            total_swap: self.sys.total_swap(),
+            used_swap: self.sys.used_swap(),
+            components: self
+                .components
+                .iter()
+                .map(|component| ComponentInfo {
+                    label: component.label().to_owned(),
+                    temperature: component.temperature(),
+                })
+                .collect(),
+        }
+    }
+
+    fn network_table(&self) -> NetworkTable {
+        self.networks
+            .iter()
+            .map(|(name, data)| {
+                let info = NetworkInfo {
+                    mac_address: data.mac_address(),
+                    received: data.received(),
+                    total_received: data.total_received(),
+                    transmitted: data.transmitted(),
+                    total_transmitted: data.total_transmitted(),
+                    packets_received: data.packets_received(),
+                    total_packets_received: data.total_packets_received(),
+                    packets_transmitted: data.packets_transmitted(),
+                    total_packets_transmitted: data.total_packets_transmitted(),
+                    errors_on_received: data.errors_on_received(),
+                    total_errors_on_received: data.total_errors_on_received(),
+                    errors_on_transmitted: data.errors_on_transmitted(),
+                    total_errors_on_transmitted: data.total_errors_on_transmitted(),
+                };
+                (name.clone(), info)
+            })
+            .collect()
+    }
+
+    /// Refreshes the information of `subsystem`, returns `None` if it isn't needed.
+    fn collect(&mut self, subsystem: Subsystem) -> Option<Snapshot> {
+        match subsystem {
+            Subsystem::Processes => {
+                let refresh = self.refreshes.processes?;
+                self.sys
+                    .refresh_processes_specifics(ProcessesToUpdate::All, true, refresh.kind);
+                Some(Snapshot::Processes(self.process_table(&refresh)))
+            }
+            Subsystem::System if self.refreshes.system => {
+                self.sys.refresh_specifics(
+                    RefreshKind::nothing()
+                        .with_cpu(CpuRefreshKind::nothing().with_cpu_usage())
+                        .with_memory(MemoryRefreshKind::everything()),
+                );
+                self.components.refresh(true);
+                Some(Snapshot::System(self.system_info()))
+            }
+            Subsystem::Networks if self.refreshes.networks => {
+                self.networks.refresh(true);
+                Some(Snapshot::Networks(self.network_table()))
+            }
+            _ => None,
+        }
+    }
+
+    fn set_refreshes(&mut self, refreshes: Refreshes) {
+        let old = std::mem::replace(&mut self.refreshes, refreshes);
+        // What is newly displayed is refreshed as soon as possible.
+        let soon = Instant::now() + MINIMUM_CPU_UPDATE_INTERVAL;
+        for subsystem in Subsystem::ALL {
+            if refreshes.changed(&old, subsystem) {
+                let schedule = self.schedule(subsystem);
+                schedule.next = schedule.next.min(soon);
+            }
+        }
+    }
+
+    fn set_rate(&mut self, subsystem: Subsystem, rate: Duration) {
+        let schedule = self.schedule(subsystem);
+        // A longer rate is only used after the next refresh.
+        schedule.next = schedule.next.min(Instant::now() + rate);
+        schedule.rate = rate;
+    }
+
+    fn run(
+        mut self,
+        commands: mpsc::Receiver<Command>,
+        snapshots: async_channel::Sender<Snapshot>,
+    ) {
+        loop {
+            let now = Instant::now();
+            for subsystem in Subsystem::ALL {
+                let schedule = self.schedule(subsystem);
+                if schedule.next > now {
+                    continue;
+                }
+                schedule.next = now + schedule.rate;
+                let Some(snapshot) = self.collect(subsystem) else {
+                    continue;
+                };
+                // The UI is gone, nothing needs to be collected anymore.
+                if snapshots.send_blocking(snapshot).is_err() {
+                    return;
+                }
+            }
+
+            let next = Subsystem::ALL
+                .iter()
+                .filter(|subsystem| self.refreshes.is_enabled(**subsystem))
+                .map(|subsystem| self.schedules[*subsystem as usize].next)
+                .min();
+            let command = match next {
+                Some(next) => commands.recv_timeout(next.saturating_duration_since(Instant::now())),
+                // Nothing is displayed, so there is nothing to do until that changes.
+                None => commands
+                    .recv()
+                    .map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+            };
+            match command {
+                Ok(Command::SetRate(subsystem, rate)) => self.set_rate(subsystem, rate),
+                Ok(Command::SetRefreshes(refreshes)) => self.set_refreshes(refreshes),
+                Ok(Command::Stop) | Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+            }
+        }
+    }
+}
+
+/// The handle of the collector thread. Once started, the collector refreshes what is set with
+/// [`Collector::set_refreshes`] and sends the snapshots to the receiver returned by
+/// [`Collector::start`], until it is stopped or the receiver is dropped.
+#[derive(Clone)]
+pub struct Collector {
+    commands: mpsc::Sender<Command>,
+    /// The last refreshes sent to the thread.
+    refreshes: Rc<Cell<Refreshes>>,
+    thread: Rc<RefCell<Option<JoinHandle<()>>>>,
+}
+
+impl Collector {
+    /// Starts the collector with the refresh rates of `settings`.
+    pub fn start(
+        settings: &Settings,
+    ) -> (Self, InitialSnapshots, async_channel::Receiver<Snapshot>) {
+        let state = State::new(settings);
+        // The memory details are costly for all the processes, they wait for the first refresh.
+        let initial_refresh = ProcessRefresh {
+            kind: ProcessRefreshKind::everything(),
+            priorities: true,
+            memory_details: false,
+        };
+        let initial = InitialSnapshots {
+            processes: state.process_table(&initial_refresh),
+            system: state.system_info(),
+            networks: state.network_table(),
+        };
+        let (commands, command_receiver) = mpsc::channel();
+        let (sender, receiver) = async_channel::unbounded();
+        let thread = thread::spawn(move || state.run(command_receiver, sender));
+        let collector = Self {
+            commands,
+            refreshes: Rc::new(Cell::new(Refreshes::default())),
+            thread: Rc::new(RefCell::new(Some(thread))),
+        };
+        (collector, initial, receiver)
+    }
+
+    /// Sets the refresh rate of `subsystem`, in milliseconds.
+    pub fn set_rate(&self, subsystem: Subsystem, rate: u32) {
+        self.send(Command::SetRate(
+            subsystem,
+            Duration::from_millis(rate.into()),
+        ));
+    }
+
+    /// Does nothing if `refreshes` didn't change since the last call.
+    pub fn set_refreshes(&self, refreshes: Refreshes) {
+        if self.refreshes.replace(refreshes) != refreshes {
+            self.send(Command::SetRefreshes(refreshes));
+        }
+    }
+
+    /// Stops the collector and waits for its thread to end.
+    pub fn stop(&self) {
+        self.send(Command::Stop);
+        if let Some(thread) = self.thread.borrow_mut().take() {
+            // If the thread panicked, its panic message was already printed.
+            let _ = thread.join();
+        }
+    }
+
+    fn send(&self, command: Command) {
+        // The thread only ends once the collector is stopped or the UI doesn't receive the
+        // snapshots anymore, in which case there is nothing left to do.
+        let _ = self.commands.send(command);
+    }
+}
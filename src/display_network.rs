@@ -1,54 +1,109 @@
+use crate::collector::{NetworkInfo, NetworkTable};
+use crate::filter::Matcher;
 use crate::network_dialog::{self, NetworkDialog};
+use crate::settings::Settings;
 
-use crate::utils::{format_number, format_number_full};
-use gtk::glib;
+use crate::utils::{
+    compare_ignore_case, create_search_options_button, create_sorter, create_text_column,
+    format_number, format_number_full, sort_again,
+};
 use gtk::prelude::*;
-use sysinfo::Networks;
+use gtk::subclass::prelude::*;
+use gtk::{gio, glib};
 
 use std::cell::RefCell;
 use std::collections::HashSet;
 use std::rc::Rc;
-use std::sync::{Arc, Mutex};
 
-fn append_column(
-    title: &str,
-    v: &mut Vec<gtk::TreeViewColumn>,
-    tree: &gtk::TreeView,
-    max_width: Option<i32>,
-) {
-    let id = v.len() as i32;
-    let renderer = gtk::CellRendererText::new();
+mod imp {
+    use super::*;
+
+    #[derive(Default, glib::Properties)]
+    #[properties(wrapper_type = super::NetworkRow)]
+    pub struct NetworkRow {
+        #[property(get, construct_only)]
+        name: RefCell<String>,
+        #[property(get, set)]
+        received: RefCell<u64>,
+        #[property(get, set)]
+        transmitted: RefCell<u64>,
+        #[property(get, set)]
+        packets_received: RefCell<u64>,
+        #[property(get, set)]
+        packets_transmitted: RefCell<u64>,
+        #[property(get, set)]
+        errors_on_received: RefCell<u64>,
+        #[property(get, set)]
+        errors_on_transmitted: RefCell<u64>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for NetworkRow {
+        const NAME: &'static str = "NetworkRow";
+        type Type = super::NetworkRow;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for NetworkRow {}
+}
 
-    if title != "name" {
-        renderer.set_xalign(1.0);
+glib::wrapper! {
+    /// A row of the networks list.
+    pub struct NetworkRow(ObjectSubclass<imp::NetworkRow>);
+}
+
+impl NetworkRow {
+    fn new(interface_name: &str, data: &NetworkInfo) -> Self {
+        let row: Self = glib::Object::builder()
+            .property("name", interface_name)
+            .build();
+        row.update(data);
+        row
     }
 
-    let column = gtk::TreeViewColumn::builder()
-        .title(title)
-        .resizable(true)
-        .clickable(true)
-        .min_width(10)
-        .build();
-    if let Some(max_width) = max_width {
-        column.set_max_width(max_width);
-        column.set_expand(true);
+    fn update(&self, data: &NetworkInfo) {
+        self.set_received(data.received);
+        self.set_transmitted(data.transmitted);
+        self.set_packets_received(data.packets_received);
+        self.set_packets_transmitted(data.packets_transmitted);
+        self.set_errors_on_received(data.errors_on_received);
+        self.set_errors_on_transmitted(data.errors_on_transmitted);
     }
-    column.pack_start(&renderer, true);
-    column.add_attribute(&renderer, "text", id);
-    tree.append_column(&column);
-    v.push(column);
+}
+
+fn append_column(
+    column_view: &gtk::ColumnView,
+    title: &str,
+    property: &str,
+    value: fn(&NetworkRow) -> u64,
+    format: fn(u64) -> String,
+) {
+    let column = create_text_column(title, property, 1., move |row: &NetworkRow| {
+        format(value(row))
+    });
+    column.set_sorter(Some(&create_sorter(
+        move |a: &NetworkRow, b: &NetworkRow| value(a).cmp(&value(b)),
+    )));
+    column_view.append_column(&column);
 }
 
 pub struct Network {
-    list_store: gtk::ListStore,
+    list_store: gio::ListStore,
+    sort_model: gtk::SortListModel,
     pub search_bar: gtk::SearchBar,
     dialogs: Rc<RefCell<Vec<NetworkDialog>>>,
+    /// The last snapshot, used to create the dialogs.
+    networks: Rc<RefCell<NetworkTable>>,
 }
 
 impl Network {
-    pub fn new(stack: &gtk::Stack, networks: &Arc<Mutex<Networks>>) -> Self {
-        let tree = gtk::TreeView::builder().headers_visible(true).build();
-        let scroll = gtk::ScrolledWindow::builder().child(&tree).build();
+    pub fn new(
+        stack: &gtk::Stack,
+        initial_networks: NetworkTable,
+        settings: &Rc<RefCell<Settings>>,
+    ) -> Self {
+        let column_view = gtk::ColumnView::new(None::<gtk::SelectionModel>);
+        let scroll = gtk::ScrolledWindow::builder().child(&column_view).build();
         let info_button = gtk::Button::builder()
             .label("More information")
             .hexpand(true)
@@ -59,12 +114,17 @@ impl Network {
 
         // We put the filter entry at the right bottom.
         let filter_entry = gtk::SearchEntry::new();
+        let filter_row = gtk::Box::new(gtk::Orientation::Horizontal, 2);
+        filter_row.append(&filter_entry);
         let search_bar = gtk::SearchBar::builder()
             .halign(gtk::Align::End)
             .valign(gtk::Align::End)
-            .child(&filter_entry)
+            .child(&filter_row)
             .show_close_button(true)
             .build();
+        search_bar.connect_entry(&filter_entry);
+        // `None` when there is nothing to filter or when the regular expression is invalid.
+        let matcher: Rc<RefCell<Option<Matcher>>> = Rc::new(RefCell::new(None));
 
         let overlay = gtk::Overlay::builder()
             .child(&scroll)
@@ -73,111 +133,160 @@ impl Network {
             .build();
         overlay.add_overlay(&search_bar);
 
-        let mut columns: Vec<gtk::TreeViewColumn> = Vec::new();
-
-        let list_store = gtk::ListStore::new(&[
-            // The first four columns of the model are going to be visible in the view.
-            glib::Type::STRING, // name
-            glib::Type::STRING, // received data
-            glib::Type::STRING, // transmitted data
-            glib::Type::STRING, // received packets
-            glib::Type::STRING, // transmitted packets
-            glib::Type::STRING, // errors on received
-            glib::Type::STRING, // errors on transmitted
-            // These two will serve as keys when sorting by interface name and other numerical
-            // things.
-            glib::Type::STRING, // name_lowercase
-            glib::Type::U64,    // received data
-            glib::Type::U64,    // transmitted data
-            glib::Type::U64,    // received packets
-            glib::Type::U64,    // transmitted packets
-            glib::Type::U64,    // errors on received
-            glib::Type::U64,    // errors on transmitted
-        ]);
+        let list_store = gio::ListStore::new::<NetworkRow>();
 
         // The filter model
-        let filter_model = gtk::TreeModelFilter::new(&list_store, None);
-        filter_model.set_visible_func(glib::clone!(
+        let filter = gtk::CustomFilter::new(glib::clone!(
             #[strong]
             filter_entry,
-            move |model, iter| {
+            #[strong]
+            matcher,
+            move |item| {
                 if !WidgetExt::is_visible(&filter_entry) {
                     return true;
                 }
-                let text = filter_entry.text();
-                if text.is_empty() {
+                let Some(ref matcher) = *matcher.borrow() else {
                     return true;
-                }
-                let text: &str = text.as_ref();
-                // TODO: Maybe add an option to make searches case sensitive?
-                let name = model
-                    .get_value(iter, 0)
-                    .get::<String>()
-                    .map(|s| s.to_lowercase())
-                    .ok()
-                    .unwrap_or_default();
-                name.contains(text)
+                };
+                item.downcast_ref::<NetworkRow>()
+                    .is_some_and(|row| matcher.is_match(&row.name()))
             }
         ));
-
-        // For the filtering to be taken into account, we need to add it directly into the
-        // "global" model.
-        let sort_model = gtk::TreeModelSort::with_model(&filter_model);
-        tree.set_model(Some(&sort_model));
-        tree.set_search_entry(Some(&filter_entry));
-
-        append_column("name", &mut columns, &tree, Some(200));
-        append_column("received data", &mut columns, &tree, None);
-        append_column("transmitted data", &mut columns, &tree, None);
-        append_column("received packets", &mut columns, &tree, None);
-        append_column("transmitted packets", &mut columns, &tree, None);
-        append_column("errors on received", &mut columns, &tree, None);
-        append_column("errors on transmitted", &mut columns, &tree, None);
-
-        let columns_len = columns.len();
-        for (pos, column) in columns.iter().enumerate() {
-            column.set_sort_column_id(pos as i32 + columns_len as i32);
-        }
+        let filter_model =
+            gtk::FilterListModel::new(Some(list_store.clone()), Some(filter.clone()));
+        let sort_model = gtk::SortListModel::new(Some(filter_model), column_view.sorter());
+        let selection = gtk::SingleSelection::builder()
+            .model(&sort_model)
+            .autoselect(false)
+            .can_unselect(true)
+            .build();
+        column_view.set_model(Some(&selection));
+
+        let name_column = create_text_column("name", "name", 0., NetworkRow::name);
+        name_column.set_sorter(Some(&create_sorter(|a: &NetworkRow, b: &NetworkRow| {
+            compare_ignore_case(&a.name(), &b.name())
+        })));
+        name_column.set_expand(true);
+        column_view.append_column(&name_column);
+        append_column(
+            &column_view,
+            "received data",
+            "received",
+            NetworkRow::received,
+            format_number,
+        );
+        append_column(
+            &column_view,
+            "transmitted data",
+            "transmitted",
+            NetworkRow::transmitted,
+            format_number,
+        );
+        append_column(
+            &column_view,
+            "received packets",
+            "packets-received",
+            NetworkRow::packets_received,
+            |v| format_number_full(v, false),
+        );
+        append_column(
+            &column_view,
+            "transmitted packets",
+            "packets-transmitted",
+            NetworkRow::packets_transmitted,
+            |v| format_number_full(v, false),
+        );
+        append_column(
+            &column_view,
+            "errors on received",
+            "errors-on-received",
+            NetworkRow::errors_on_received,
+            |v| format_number_full(v, false),
+        );
+        append_column(
+            &column_view,
+            "errors on transmitted",
+            "errors-on-transmitted",
+            NetworkRow::errors_on_transmitted,
+            |v| format_number_full(v, false),
+        );
 
         // Sort by network name by default.
-        sort_model.set_sort_column_id(
-            gtk::SortColumn::Index(columns_len as _),
-            gtk::SortType::Ascending,
-        );
+        column_view.sort_by_column(Some(&name_column), gtk::SortType::Ascending);
 
         let vertical_layout = gtk::Box::new(gtk::Orientation::Vertical, 0);
 
         vertical_layout.append(&overlay);
         vertical_layout.append(&info_button);
 
-        filter_entry.connect_search_changed(move |_| {
-            filter_model.refilter();
-        });
+        let update_filter = Rc::new(glib::clone!(
+            #[weak]
+            filter_entry,
+            #[strong]
+            settings,
+            move || {
+                let text = filter_entry.text();
+                let new_matcher = if text.is_empty() {
+                    Ok(None)
+                } else {
+                    Matcher::new(&text, settings.borrow().network_search).map(Some)
+                };
+                match new_matcher {
+                    Ok(new_matcher) => {
+                        *matcher.borrow_mut() = new_matcher;
+                        filter_entry.remove_css_class("error");
+                        filter_entry.set_tooltip_text(None);
+                    }
+                    Err(_) => {
+                        *matcher.borrow_mut() = None;
+                        filter_entry.add_css_class("error");
+                        filter_entry.set_tooltip_text(Some("Invalid regular expression"));
+                    }
+                }
+                filter.changed(gtk::FilterChange::Different);
+            }
+        ));
+        filter_entry.connect_search_changed(glib::clone!(
+            #[strong]
+            update_filter,
+            move |_| update_filter()
+        ));
+        filter_row.append(&create_search_options_button(
+            settings.borrow().network_search,
+            None,
+            glib::clone!(
+                #[strong]
+                settings,
+                move |options, _| {
+                    {
+                        let mut settings = settings.borrow_mut();
+                        settings.network_search = options;
+                        settings.save();
+                    }
+                    update_filter();
+                }
+            ),
+        ));
 
         stack.add_titled(&vertical_layout, Some("Networks"), "Networks");
 
-        tree.connect_cursor_changed(glib::clone!(
+        selection.connect_selected_item_notify(glib::clone!(
             #[weak]
             current_network,
             #[weak]
             info_button,
-            move |tree_view| {
-                let selection = tree_view.selection();
-                let (name, ret) = if let Some((model, iter)) = selection.selected() {
-                    if let Ok(x) = model.get_value(&iter, 0).get::<String>() {
-                        (Some(x), true)
-                    } else {
-                        (None, false)
-                    }
-                } else {
-                    (None, false)
-                };
+            move |selection| {
+                let name = selection
+                    .selected_item()
+                    .and_downcast::<NetworkRow>()
+                    .map(|row| row.name());
+                info_button.set_sensitive(name.is_some());
                 *current_network.borrow_mut() = name;
-                info_button.set_sensitive(ret);
             }
         ));
 
         let dialogs = Rc::new(RefCell::new(Vec::new()));
+        let networks = Rc::new(RefCell::new(NetworkTable::new()));
 
         info_button.connect_clicked(glib::clone!(
             #[weak]
@@ -190,101 +299,59 @@ impl Network {
                     create_network_dialog(
                         &mut dialogs.borrow_mut(),
                         interface_name,
-                        &networks
-                            .lock()
-                            .expect("failed to lock for new network dialog"),
+                        &networks.borrow(),
                     );
                 }
             }
         ));
 
-        tree.connect_row_activated(glib::clone!(
+        column_view.connect_activate(glib::clone!(
             #[weak]
             networks,
             #[weak]
             dialogs,
-            move |tree_view, path, _| {
-                let model = tree_view.model().expect("couldn't get model");
-                let iter = model.iter(path).expect("couldn't get iter");
-                let interface_name = model
-                    .get_value(&iter, 0)
-                    .get::<String>()
-                    .expect("Model::get failed");
-                create_network_dialog(
-                    &mut dialogs.borrow_mut(),
-                    &interface_name,
-                    &networks
-                        .lock()
-                        .expect("failed to lock for new network dialog (from tree)"),
-                );
+            move |column_view, position| {
+                let Some(row) = column_view
+                    .model()
+                    .and_then(|model| model.item(position))
+                    .and_downcast::<NetworkRow>()
+                else {
+                    return;
+                };
+                create_network_dialog(&mut dialogs.borrow_mut(), &row.name(), &networks.borrow());
             }
         ));
 
-        Network {
+        let mut network = Network {
             list_store,
+            sort_model,
             search_bar,
             dialogs,
-        }
+            networks,
+        };
+        network.update_networks(initial_networks);
+        network
     }
 
-    pub fn update_networks(&mut self, networks: &Networks) {
-        // first part, deactivate sorting
-        let sorted = TreeSortableExtManual::sort_column_id(&self.list_store);
-        self.list_store.set_unsorted();
-
+    pub fn update_networks(&mut self, networks: NetworkTable) {
         let mut seen: HashSet<String> = HashSet::new();
 
-        if let Some(iter) = self.list_store.iter_first() {
-            let mut valid = true;
-            while valid {
-                let name = match self.list_store.get_value(&iter, 0).get::<glib::GString>() {
-                    Ok(n) => n,
-                    _ => {
-                        valid = self.list_store.iter_next(&iter);
-                        continue;
-                    }
-                };
-                if let Some((_, data)) = networks
-                    .iter()
-                    .find(|(interface_name, _)| interface_name.as_str() == name)
-                {
-                    self.list_store.set(
-                        &iter,
-                        &[
-                            (1, &format_number(data.received())),
-                            (2, &format_number(data.transmitted())),
-                            (3, &format_number_full(data.packets_received(), false)),
-                            (4, &format_number_full(data.packets_transmitted(), false)),
-                            (5, &format_number_full(data.errors_on_received(), false)),
-                            (6, &format_number_full(data.errors_on_transmitted(), false)),
-                            (8, &data.received()),
-                            (9, &data.transmitted()),
-                            (10, &data.packets_received()),
-                            (11, &data.packets_transmitted()),
-                            (12, &data.errors_on_received()),
-                            (13, &data.errors_on_transmitted()),
-                        ],
-                    );
-                    valid = self.list_store.iter_next(&iter);
-                    seen.insert(name.to_string());
-                } else {
-                    valid = self.list_store.remove(&iter);
-                }
+        let mut pos = 0;
+        while let Some(row) = self.list_store.item(pos).and_downcast::<NetworkRow>() {
+            let name = row.name();
+            if let Some(data) = networks.get(&name) {
+                row.update(data);
+                seen.insert(name);
+                pos += 1;
+            } else {
+                self.list_store.remove(pos);
             }
         }
 
         for (interface_name, data) in networks.iter() {
             if !seen.contains(interface_name.as_str()) {
-                create_and_fill_model(
-                    &self.list_store,
-                    interface_name,
-                    data.received(),
-                    data.transmitted(),
-                    data.packets_received(),
-                    data.packets_transmitted(),
-                    data.errors_on_received(),
-                    data.errors_on_transmitted(),
-                );
+                self.list_store
+                    .append(&NetworkRow::new(interface_name, data));
             }
             if let Some(dialog) = self
                 .dialogs
@@ -296,52 +363,22 @@ impl Network {
             }
         }
 
-        // we re-enable the sorting
-        if let Some((col, order)) = sorted {
-            self.list_store.set_sort_column_id(col, order);
-        }
+        sort_again(&self.sort_model);
 
         self.dialogs.borrow_mut().retain(|x| !x.need_remove());
+        *self.networks.borrow_mut() = networks;
     }
-}
 
-#[allow(clippy::too_many_arguments)]
-fn create_and_fill_model(
-    list_store: &gtk::ListStore,
-    interface_name: &str,
-    in_usage: u64,
-    out_usage: u64,
-    incoming_packets: u64,
-    outgoing_packets: u64,
-    incoming_errors: u64,
-    outgoing_errors: u64,
-) {
-    list_store.insert_with_values(
-        None,
-        &[
-            (0, &interface_name),
-            (1, &format_number(in_usage)),
-            (2, &format_number(out_usage)),
-            (3, &format_number_full(incoming_packets, false)),
-            (4, &format_number_full(outgoing_packets, false)),
-            (5, &format_number_full(incoming_errors, false)),
-            (6, &format_number_full(outgoing_errors, false)),
-            // sort part
-            (7, &interface_name.to_lowercase()),
-            (8, &in_usage),
-            (9, &out_usage),
-            (10, &incoming_packets),
-            (11, &outgoing_packets),
-            (12, &incoming_errors),
-            (13, &outgoing_errors),
-        ],
-    );
+    /// Returns `true` if the dialog of a network interface is open.
+    pub fn has_dialogs(&self) -> bool {
+        self.dialogs.borrow().iter().any(|x| !x.need_remove())
+    }
 }
 
 fn create_network_dialog(
     dialogs: &mut Vec<NetworkDialog>,
     interface_name: &str,
-    networks: &Networks,
+    networks: &NetworkTable,
 ) {
     for dialog in dialogs.iter() {
         if dialog.name == interface_name {
@@ -349,10 +386,7 @@ fn create_network_dialog(
             return;
         }
     }
-    if let Some((_, data)) = networks
-        .iter()
-        .find(|(name, _)| name.as_str() == interface_name)
-    {
+    if let Some(data) = networks.get(interface_name) {
         dialogs.push(network_dialog::create_network_dialog(data, interface_name));
     } else {
         eprintln!("couldn't find {interface_name}...");
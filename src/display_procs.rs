@@ -1,34 +1,539 @@
-use gtk::glib;
 use gtk::prelude::*;
+use gtk::{gio, glib};
 
-use sysinfo::{Pid, Process};
+use serde_derive::{Deserialize, Serialize};
+use sysinfo::{Pid, ProcessRefreshKind, ProcessStatus, ThreadKind, UpdateKind};
 
-use crate::utils::format_number;
+use crate::collector::{ProcessInfo, ProcessRefresh};
+use crate::filter::{FilterRow, NumberField, Query, TextField};
+use crate::memory_maps::MemoryUsage;
+use crate::priority::IoPriority;
+use crate::process_index::{ProcessIndex, ViewMode};
+use crate::process_row::{ProcessRow, UNKNOWN};
+use crate::settings::{ProcessColumnSettings, Settings};
+use crate::signals::create_signals_menu;
+use crate::utils::{
+    compare_ignore_case, create_search_options_button, create_sorter, format_number,
+    format_start_time,
+};
 
-use std::cell::Cell;
-use std::collections::HashMap;
-use std::ffi::{OsStr, OsString};
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::rc::Rc;
 
+const FILTER_HELP: &str = "Words match the process name or PID (see the search options). \
+    Filter on fields with \
+    `cpu>10`, `mem>500M`, `user:root`, `name~^python` (regular expression), \
+    `cmd:\"--port 80\"` or `pid:1234`, and combine them with AND, OR, NOT and parentheses.\n\
+    Fields: name, cmd, exe, user, state, pid, ppid, cpu, mem, vmem, disk, threads, nice.";
+
+/// The process states which can be shown or hidden in the processes list. Every
+/// [`ProcessStatus`] belongs to one of them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, glib::Enum)]
+#[serde(rename_all = "snake_case")]
+#[enum_type(name = "StateKind")]
+pub enum StateKind {
+    Running,
+    Sleeping,
+    DiskSleep,
+    Stopped,
+    Zombie,
+    #[default]
+    Other,
+}
+
+impl StateKind {
+    pub const ALL: &'static [StateKind] = &[
+        StateKind::Running,
+        StateKind::Sleeping,
+        StateKind::DiskSleep,
+        StateKind::Stopped,
+        StateKind::Zombie,
+        StateKind::Other,
+    ];
+
+    pub fn of(status: ProcessStatus) -> Self {
+        match status {
+            ProcessStatus::Run => Self::Running,
+            ProcessStatus::Sleep | ProcessStatus::Idle => Self::Sleeping,
+            ProcessStatus::UninterruptibleDiskSleep => Self::DiskSleep,
+            ProcessStatus::Stop | ProcessStatus::Tracing => Self::Stopped,
+            ProcessStatus::Zombie => Self::Zombie,
+            _ => Self::Other,
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::Running => "Running",
+            Self::Sleeping => "Sleeping",
+            Self::DiskSleep => "Disk sleep",
+            Self::Stopped => "Stopped",
+            Self::Zombie => "Zombie",
+            Self::Other => "Other",
+        }
+    }
+
+    /// The color of the state column, `None` for the usual text color. Sleeping is by far the
+    /// most common state, so only the other ones stand out.
+    fn color(self) -> Option<&'static str> {
+        match self {
+            Self::Running => Some("#2ec27e"),
+            Self::Sleeping | Self::Other => None,
+            Self::DiskSleep => Some("#e66100"),
+            Self::Stopped => Some("#9141ac"),
+            Self::Zombie => Some("#e01b24"),
+        }
+    }
+}
+
+/// The columns which can be displayed in the processes list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcColumn {
+    Pid,
+    Name,
+    Cpu,
+    Memory,
+    DiskUsage,
+    SubtreeCpu,
+    SubtreeMemory,
+    User,
+    State,
+    Threads,
+    StartTime,
+    VirtualMemory,
+    Nice,
+    ParentPid,
+    Command,
+    IoPriority,
+    Instances,
+    Pss,
+    Uss,
+    Swap,
+}
+
+impl ProcColumn {
+    /// All the columns, in the same order as the enum variants.
+    pub const ALL: &'static [ProcColumn] = &[
+        ProcColumn::Pid,
+        ProcColumn::Name,
+        ProcColumn::Cpu,
+        ProcColumn::Memory,
+        ProcColumn::DiskUsage,
+        ProcColumn::SubtreeCpu,
+        ProcColumn::SubtreeMemory,
+        ProcColumn::User,
+        ProcColumn::State,
+        ProcColumn::Threads,
+        ProcColumn::StartTime,
+        ProcColumn::VirtualMemory,
+        ProcColumn::Nice,
+        ProcColumn::ParentPid,
+        ProcColumn::Command,
+        ProcColumn::IoPriority,
+        ProcColumn::Instances,
+        ProcColumn::Pss,
+        ProcColumn::Uss,
+        ProcColumn::Swap,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::Pid => "pid",
+            Self::Name => "process name",
+            Self::Cpu => "cpu usage",
+            Self::Memory => "memory usage",
+            #[cfg(not(windows))]
+            Self::DiskUsage => "disk I/O usage",
+            #[cfg(windows)]
+            Self::DiskUsage => "I/O usage",
+            Self::SubtreeCpu => "subtree cpu usage",
+            Self::SubtreeMemory => "subtree memory usage",
+            Self::User => "user",
+            Self::State => "state",
+            Self::Threads => "threads",
+            Self::StartTime => "start time",
+            Self::VirtualMemory => "virtual memory",
+            Self::Nice => "nice",
+            Self::ParentPid => "parent pid",
+            Self::Command => "command line",
+            Self::IoPriority => "I/O priority",
+            Self::Instances => "instances",
+            Self::Pss => "proportional memory (PSS)",
+            Self::Uss => "unique memory (USS)",
+            Self::Swap => "swapped memory",
+        }
+    }
+
+    /// The property of [`ProcessRow`] displayed in the column.
+    fn property(self) -> &'static str {
+        match self {
+            Self::Pid => "pid",
+            Self::Name => "name",
+            Self::Cpu => "cpu",
+            Self::Memory => "memory",
+            Self::DiskUsage => "disk-usage",
+            Self::SubtreeCpu => "subtree-cpu",
+            Self::SubtreeMemory => "subtree-memory",
+            Self::User => "user",
+            Self::State => "state",
+            Self::Threads => "threads",
+            Self::StartTime => "start-time",
+            Self::VirtualMemory => "virtual-memory",
+            Self::Nice => "nice",
+            Self::ParentPid => "parent-pid",
+            Self::Command => "command",
+            Self::IoPriority => "io-priority",
+            Self::Instances => "instances",
+            Self::Pss => "pss",
+            Self::Uss => "uss",
+            Self::Swap => "swap",
+        }
+    }
+
+    /// The text displayed in the column for `row`. Unknown values, and the ones which don't
+    /// apply to applications, are left empty.
+    fn text(self, row: &ProcessRow) -> String {
+        let known = |value: i64| {
+            if value == UNKNOWN {
+                String::new()
+            } else {
+                value.to_string()
+            }
+        };
+        let usage = |value: u64| {
+            if value > 0 {
+                format_number(value)
+            } else {
+                String::new()
+            }
+        };
+        let known_size = |value: i64| {
+            if value == UNKNOWN {
+                String::new()
+            } else {
+                format_number(value as u64)
+            }
+        };
+        match self {
+            Self::Pid if row.is_group() => String::new(),
+            Self::Pid => row.pid().to_string(),
+            Self::Name => row.name(),
+            Self::Cpu => format!("{:.1}", row.cpu()),
+            Self::Memory => format_number(row.memory()),
+            Self::DiskUsage => usage(row.disk_usage()),
+            Self::SubtreeCpu => format!("{:.1}", row.subtree_cpu()),
+            Self::SubtreeMemory => format_number(row.subtree_memory()),
+            Self::User => row.user(),
+            // The state is displayed with markup, to color it.
+            Self::State => {
+                let state = glib::markup_escape_text(&row.state());
+                match row.state_kind().color() {
+                    Some(color) => format!("<span foreground=\"{color}\">{state}</span>"),
+                    None => state.into(),
+                }
+            }
+            Self::Threads => known(row.threads()),
+            Self::StartTime if row.is_group() => String::new(),
+            Self::StartTime => format_start_time(row.start_time()),
+            Self::VirtualMemory => format_number(row.virtual_memory()),
+            Self::Nice => known(row.nice()),
+            Self::ParentPid => known(row.parent_pid()),
+            Self::Command => row.command(),
+            Self::IoPriority => row
+                .io_priority()
+                .map(|io| io.to_string())
+                .unwrap_or_default(),
+            Self::Instances if row.is_group() => row.instances().to_string(),
+            Self::Instances => String::new(),
+            Self::Pss => known_size(row.pss()),
+            Self::Uss => known_size(row.uss()),
+            Self::Swap => known_size(row.swap()),
+        }
+    }
+
+    /// Compares two rows on the value of the column. Numbers are compared numerically and
+    /// names without taking their case into account.
+    fn compare(self, a: &ProcessRow, b: &ProcessRow) -> Ordering {
+        match self {
+            Self::Pid => a.pid().cmp(&b.pid()),
+            Self::Name => compare_ignore_case(&a.name(), &b.name()),
+            Self::Cpu => a.cpu().total_cmp(&b.cpu()),
+            Self::Memory => a.memory().cmp(&b.memory()),
+            Self::DiskUsage => a.disk_usage().cmp(&b.disk_usage()),
+            Self::SubtreeCpu => a.subtree_cpu().total_cmp(&b.subtree_cpu()),
+            Self::SubtreeMemory => a.subtree_memory().cmp(&b.subtree_memory()),
+            Self::User => a.user().cmp(&b.user()),
+            Self::State => a.state().cmp(&b.state()),
+            Self::Threads => a.threads().cmp(&b.threads()),
+            Self::StartTime => a.start_time().cmp(&b.start_time()),
+            Self::VirtualMemory => a.virtual_memory().cmp(&b.virtual_memory()),
+            Self::Nice => a.nice().cmp(&b.nice()),
+            Self::ParentPid => a.parent_pid().cmp(&b.parent_pid()),
+            Self::Command => a.command().cmp(&b.command()),
+            Self::IoPriority => a
+                .io_priority()
+                .map(IoPriority::sort_key)
+                .cmp(&b.io_priority().map(IoPriority::sort_key)),
+            Self::Instances => a.instances().cmp(&b.instances()),
+            Self::Pss => a.pss().cmp(&b.pss()),
+            Self::Uss => a.uss().cmp(&b.uss()),
+            Self::Swap => a.swap().cmp(&b.swap()),
+        }
+    }
+
+    /// Subtree totals are only meaningful in the tree mode and the instances count in the grouped
+    /// mode, so their columns are hidden otherwise.
+    fn is_available_in(self, view_mode: ViewMode) -> bool {
+        match self {
+            Self::SubtreeCpu | Self::SubtreeMemory => view_mode == ViewMode::Tree,
+            Self::Instances => view_mode == ViewMode::Grouped,
+            _ => true,
+        }
+    }
+
+    fn is_text(self) -> bool {
+        matches!(self, Self::Name | Self::User | Self::State | Self::Command)
+    }
+
+    /// Adds the information needed by the column to `refresh`. The other values are either
+    /// always retrieved by sysinfo or not retrieved by it at all.
+    fn refresh(self, refresh: ProcessRefresh) -> ProcessRefresh {
+        let kind = refresh.kind;
+        let kind = match self {
+            Self::Cpu | Self::SubtreeCpu => kind.with_cpu(),
+            Self::Memory | Self::SubtreeMemory | Self::VirtualMemory => kind.with_memory(),
+            Self::DiskUsage => kind.with_disk_usage(),
+            Self::User => kind.with_user(UpdateKind::OnlyIfNotSet),
+            Self::Command => kind.with_cmd(UpdateKind::OnlyIfNotSet),
+            _ => kind,
+        };
+        ProcessRefresh {
+            kind,
+            priorities: refresh.priorities || matches!(self, Self::Nice | Self::IoPriority),
+            memory_details: refresh.memory_details || self.is_memory_detail(),
+        }
+    }
+
+    /// The memory details are costly to read and the filter can't use them, so they are only read
+    /// for their columns.
+    fn is_memory_detail(self) -> bool {
+        matches!(self, Self::Pss | Self::Uss | Self::Swap)
+    }
+}
+
+/// Called with a cell of the processes view, the position of its row and the coordinates of a
+/// right click on it.
+type ContextMenuCallback = Rc<dyn Fn(&gtk::Widget, u32, f64, f64)>;
+
+/// Information about a process which isn't provided by [`ProcessInfo`] directly.
+pub struct ProcessExtra {
+    pub subtree_cpu: f32,
+    pub subtree_memory: u64,
+    pub user: String,
+    pub pinned: bool,
+}
+
+/// Everything the filter of the processes list depends on.
+#[derive(Clone)]
+struct FilterState {
+    query: Rc<RefCell<Option<Query>>>,
+    settings: Rc<RefCell<Settings>>,
+    filter_entry: gtk::SearchEntry,
+    view_mode: Rc<Cell<ViewMode>>,
+}
+
+impl FilterState {
+    fn with_filters<T>(&self, f: impl FnOnce(&RowFilters<'_>) -> T) -> T {
+        let query = self.query.borrow();
+        // The query is only used while the search bar is displayed.
+        let query = query
+            .as_ref()
+            .filter(|_| WidgetExt::is_visible(&self.filter_entry));
+        let settings = self.settings.borrow();
+        f(&RowFilters {
+            query,
+            hide_kernel_threads: settings.hide_kernel_threads,
+            states: &settings.process_states,
+        })
+    }
+
+    fn matches(&self, row: &ProcessRow) -> bool {
+        let view_mode = self.view_mode.get();
+        self.with_filters(|filters| {
+            if filters.is_empty() {
+                true
+            // In the tree and grouped modes, a parent needs to stay visible if any of its
+            // children matches, otherwise its children would be hidden as well.
+            } else if view_mode == ViewMode::List {
+                filters.row_matches(row)
+            } else {
+                filters.subtree_matches(row)
+            }
+        })
+    }
+
+    fn is_filtering(&self) -> bool {
+        self.with_filters(|filters| !filters.is_empty())
+    }
+}
+
+/// The models of the processes list, from the rows to the sorted list displayed by the view.
+#[derive(Clone)]
+pub struct ProcessModel {
+    /// The top-level rows. In the tree and grouped modes, the other rows are stored in the
+    /// children of the row they are displayed under.
+    pub root: gio::ListStore,
+    /// The rows of the displayed processes and applications.
+    pub index: Rc<RefCell<ProcessIndex<ProcessRow>>>,
+    tree: gtk::TreeListModel,
+    filter: gtk::CustomFilter,
+    filter_state: FilterState,
+    pinned_sorter: gtk::CustomSorter,
+    sort_model: gtk::SortListModel,
+    /// The sorter of the column view, sorting by the columns the user clicked.
+    column_sorter: Option<gtk::Sorter>,
+    /// The view columns, in the same order as [`ProcColumn::ALL`].
+    columns: Vec<gtk::ColumnViewColumn>,
+}
+
+impl ProcessModel {
+    pub fn set_view_mode(&self, view_mode: ViewMode) {
+        // Applications are collapsed in the grouped mode, their processes are only displayed on
+        // demand.
+        self.tree.set_autoexpand(view_mode == ViewMode::Tree);
+    }
+
+    /// Removes all the rows, before they are added back in another view mode.
+    pub fn clear(&self) {
+        self.root.remove_all();
+        self.index.borrow_mut().clear();
+    }
+
+    /// Needs to be called once rows have been updated, with the `properties` which changed and
+    /// whether rows were added or removed: list models don't follow the changes of the properties
+    /// of their items, so the filter and the sort order would be outdated otherwise.
+    pub fn values_changed(&self, properties: &HashSet<&str>, rows_changed: bool) {
+        // Filtering the whole list again is costly, and useless if all rows are displayed. In the
+        // tree and grouped modes, whether a row is displayed also depends on its children.
+        if (rows_changed || !properties.is_empty()) && self.filter_state.is_filtering() {
+            self.filter.changed(gtk::FilterChange::Different);
+        }
+        // New rows are sorted when they are added, the other ones only need to be sorted again if
+        // a value they are sorted by changed, and if it changed their order. A sort which didn't
+        // end yet compares the new values too, starting it again would only delay it.
+        if properties.contains("pinned")
+            || (self.is_sorted_by(properties)
+                && self.sort_model.pending() == 0
+                && !self.is_in_order())
+        {
+            self.pinned_sorter.changed(gtk::SorterChange::Different);
+        }
+    }
+
+    /// Returns `true` if the displayed rows are still in order. Checking it only compares each row
+    /// with the next one, which is much cheaper than sorting them again.
+    fn is_in_order(&self) -> bool {
+        let Some(sorter) = self.sort_model.sorter() else {
+            return true;
+        };
+        let mut previous = self.sort_model.item(0);
+        for position in 1..self.sort_model.n_items() {
+            let item = self.sort_model.item(position);
+            if let (Some(previous), Some(item)) = (&previous, &item) {
+                if sorter.compare(previous, item) == gtk::Ordering::Larger {
+                    return false;
+                }
+            }
+            previous = item;
+        }
+        true
+    }
+
+    /// Returns `true` if the rows are sorted by a column displaying one of `properties`.
+    fn is_sorted_by(&self, properties: &HashSet<&str>) -> bool {
+        let Some(ref sorter) = self.column_sorter else {
+            return false;
+        };
+        // The sorted column is only known since GTK 4.10, the rows are sorted again after any
+        // change with older versions.
+        if sorter.find_property("primary-sort-column").is_none() {
+            return !properties.is_empty();
+        }
+        let Some(sorted) = sorter.property::<Option<gtk::ColumnViewColumn>>("primary-sort-column")
+        else {
+            return false;
+        };
+        ProcColumn::ALL
+            .iter()
+            .zip(self.columns.iter())
+            .find(|(_, column)| **column == sorted)
+            .is_some_and(|(column, _)| properties.contains(column.property()))
+    }
+}
+
+/// Returns the process row displayed at `position` in the processes view.
+pub fn row_at(model: &impl IsA<gio::ListModel>, position: u32) -> Option<ProcessRow> {
+    model
+        .item(position)
+        .and_downcast::<gtk::TreeListRow>()
+        .and_then(|row| row.item())
+        .and_downcast::<ProcessRow>()
+}
+
+/// Adds `rows` at the end of `store`. The rows displayed under them need to be added first, so
+/// the list knows whether they can be expanded.
+pub fn append_rows(store: &gio::ListStore, rows: &[ProcessRow]) {
+    for row in rows {
+        row.set_expandable(row.has_children());
+    }
+    store.extend_from_slice(rows);
+}
+
+/// The tree list model only checks once whether a row can be expanded, so a row of `store` which
+/// gained its first children or lost its last ones is added again to be checked again.
+pub fn update_expander(store: &gio::ListStore, row: &ProcessRow) {
+    let has_children = row.has_children();
+    if has_children == row.expandable() {
+        return;
+    }
+    let object = row.upcast_ref::<glib::Object>();
+    if let Some(pos) = (0..store.n_items()).find(|pos| store.item(*pos).as_ref() == Some(object)) {
+        row.set_expandable(has_children);
+        store.splice(pos, 1, std::slice::from_ref(row));
+    }
+}
+
 #[allow(dead_code)]
 pub struct Procs {
-    pub left_tree: gtk::TreeView,
+    pub column_view: gtk::ColumnView,
     pub scroll: gtk::ScrolledWindow,
-    pub current_pid: Rc<Cell<Option<Pid>>>,
+    pub selected_pids: Rc<RefCell<Vec<Pid>>>,
     pub kill_button: gtk::Button,
+    pub signal_button: gtk::MenuButton,
     pub info_button: gtk::Button,
+    pub view_mode_button: gtk::DropDown,
+    pub columns_button: gtk::MenuButton,
+    pub filters_button: gtk::MenuButton,
+    pub view_mode: Rc<Cell<ViewMode>>,
     pub vertical_layout: gtk::Box,
-    pub list_store: gtk::ListStore,
-    pub columns: Vec<gtk::TreeViewColumn>,
+    pub model: ProcessModel,
+    /// The view columns, in the same order as [`ProcColumn::ALL`].
+    pub columns: Vec<gtk::ColumnViewColumn>,
     pub filter_entry: gtk::SearchEntry,
     pub search_bar: gtk::SearchBar,
 }
 
 impl Procs {
-    pub fn new(proc_list: &HashMap<Pid, Process>, stack: &gtk::Stack) -> Procs {
-        let left_tree = gtk::TreeView::builder().headers_visible(true).build();
-        let scroll = gtk::ScrolledWindow::builder().child(&left_tree).build();
-        let current_pid = Rc::new(Cell::new(None));
+    pub fn new(stack: &gtk::Stack, settings: &Rc<RefCell<Settings>>) -> Procs {
+        let initial_view_mode = settings.borrow().process_view_mode;
+        let column_view = gtk::ColumnView::builder().reorderable(true).build();
+        let scroll = gtk::ScrolledWindow::builder().child(&column_view).build();
+        let selected_pids = Rc::new(RefCell::new(Vec::new()));
+        let view_mode = Rc::new(Cell::new(initial_view_mode));
         let kill_button = gtk::Button::builder()
             .label("End task")
             .hexpand(true)
@@ -37,6 +542,14 @@ impl Procs {
             .margin_end(6)
             .sensitive(false)
             .build();
+        let signal_button = gtk::MenuButton::builder()
+            .label("Send signal")
+            .menu_model(&create_signals_menu())
+            .margin_top(6)
+            .margin_bottom(6)
+            .margin_end(6)
+            .sensitive(false)
+            .build();
         let info_button = gtk::Button::builder()
             .label("More information")
             .hexpand(true)
@@ -46,173 +559,379 @@ impl Procs {
             .margin_start(6)
             .sensitive(false)
             .build();
+        let view_mode_button = gtk::DropDown::builder()
+            .model(&gtk::StringList::new(
+                &ViewMode::ALL.iter().map(|m| m.title()).collect::<Vec<_>>(),
+            ))
+            .selected(
+                ViewMode::ALL
+                    .iter()
+                    .position(|m| *m == initial_view_mode)
+                    .unwrap_or(0) as _,
+            )
+            .margin_top(6)
+            .margin_bottom(6)
+            .margin_start(6)
+            .build();
+        let columns_button = gtk::MenuButton::builder()
+            .label("Columns")
+            .margin_top(6)
+            .margin_bottom(6)
+            .build();
+        let filters_button = gtk::MenuButton::builder()
+            .label("Show")
+            .margin_top(6)
+            .margin_bottom(6)
+            .build();
 
         let overlay = gtk::Overlay::builder()
             .child(&scroll)
             .hexpand(true)
             .vexpand(true)
             .build();
-        let filter_entry = gtk::SearchEntry::new();
+        let filter_entry = gtk::SearchEntry::builder()
+            .placeholder_text("name~^python mem>1G")
+            .tooltip_text(FILTER_HELP)
+            .build();
+        // Displays why the filter query couldn't be parsed.
+        let filter_error = gtk::Label::builder()
+            .visible(false)
+            .wrap(true)
+            .max_width_chars(40)
+            .xalign(0.)
+            .css_classes(vec!["error".to_owned()])
+            .build();
+        // The search options button is added next to the entry once the filter is created.
+        let filter_row = gtk::Box::new(gtk::Orientation::Horizontal, 2);
+        filter_row.append(&filter_entry);
+        let filter_layout = gtk::Box::new(gtk::Orientation::Vertical, 2);
+        filter_layout.append(&filter_row);
+        filter_layout.append(&filter_error);
         let search_bar = gtk::SearchBar::builder()
             .halign(gtk::Align::End)
             .valign(gtk::Align::End)
             .show_close_button(true)
-            .child(&filter_entry)
+            .child(&filter_layout)
             .build();
+        search_bar.connect_entry(&filter_entry);
+        let filter_state = FilterState {
+            query: Rc::new(RefCell::new(None)),
+            settings: Rc::clone(settings),
+            filter_entry: filter_entry.clone(),
+            view_mode: Rc::clone(&view_mode),
+        };
 
         // We put the filter entry at the right bottom.
         overlay.add_overlay(&search_bar);
 
-        let mut columns: Vec<gtk::TreeViewColumn> = Vec::new();
-
-        let list_store = gtk::ListStore::new(&[
-            // The first four columns of the model are going to be visible in the view.
-            glib::Type::U32,    // pid
-            glib::Type::STRING, // name
-            glib::Type::STRING, // CPU
-            glib::Type::STRING, // mem
-            glib::Type::STRING, // disk I/O
-            // These two will serve as keys when sorting by process name and CPU usage.
-            glib::Type::STRING, // name_lowercase
-            glib::Type::F32,    // CPU_f32
-            glib::Type::U64,    // mem
-            glib::Type::U64,    // disk I/O
-        ]);
-
-        for pro in proc_list.values() {
-            if let Some(exe) = pro
-                .exe()
-                .and_then(|exe| exe.file_name())
-                .or_else(|| Some(pro.name()))
-            {
-                create_and_fill_model(
-                    &list_store,
-                    pro.pid().as_u32(),
-                    pro.cmd(),
-                    exe,
-                    pro.cpu_usage(),
-                    pro.memory(),
-                );
-            }
-        }
-
         let vertical_layout = gtk::Box::new(gtk::Orientation::Vertical, 0);
         let horizontal_layout = gtk::Box::new(gtk::Orientation::Horizontal, 6);
 
-        left_tree.connect_cursor_changed(glib::clone!(
+        // The filter part.
+        let filter = gtk::CustomFilter::new(glib::clone!(
+            #[strong]
+            filter_state,
+            move |item| {
+                item.downcast_ref::<ProcessRow>()
+                    .is_some_and(|row| filter_state.matches(row))
+            }
+        ));
+
+        // In the list mode, all processes are top-level rows. In the tree mode, they are stored
+        // under their parent process.
+        let root = gio::ListStore::new::<ProcessRow>();
+        let tree = gtk::TreeListModel::new(
+            gtk::FilterListModel::new(Some(root.clone()), Some(filter.clone())),
+            false,
+            initial_view_mode == ViewMode::Tree,
+            glib::clone!(
+                #[strong]
+                filter,
+                move |item| {
+                    let row = item.downcast_ref::<ProcessRow>()?;
+                    if !row.has_children() {
+                        return None;
+                    }
+                    Some(
+                        gtk::FilterListModel::new(Some(row.children()), Some(filter.clone()))
+                            .upcast(),
+                    )
+                }
+            ),
+        );
+        // Pinned rows are displayed before the other ones, whatever the sort order.
+        let pinned_sorter =
+            create_sorter(|a: &ProcessRow, b: &ProcessRow| b.pinned().cmp(&a.pinned()));
+        let sorter = gtk::MultiSorter::new();
+        sorter.append(pinned_sorter.clone());
+        let column_sorter = column_view.sorter();
+        if let Some(ref column_sorter) = column_sorter {
+            sorter.append(column_sorter.clone());
+        }
+        let sort_model = gtk::SortListModel::new(
+            Some(tree.clone()),
+            Some(gtk::TreeListRowSorter::new(Some(sorter))),
+        );
+        // Sorting thousands of rows would block the interface.
+        sort_model.set_incremental(true);
+        let selection = gtk::MultiSelection::new(Some(sort_model.clone()));
+        column_view.set_model(Some(&selection));
+
+        selection.connect_selection_changed(glib::clone!(
             #[strong]
-            current_pid,
+            selected_pids,
             #[weak]
             kill_button,
             #[weak]
+            signal_button,
+            #[weak]
             info_button,
-            move |tree_view| {
-                let selection = tree_view.selection();
-                let (pid, ret) = if let Some((model, iter)) = selection.selected() {
-                    if let Ok(x) = model.get_value(&iter, 0).get::<u32>() {
-                        (Some(Pid::from_u32(x)), true)
+            #[weak]
+            filter,
+            move |selection, _, _| {
+                let selected = selection.selection();
+                let mut pids = Vec::with_capacity(selected.size() as _);
+                for pos in (0..selected.size()).map(|index| selected.nth(index as _)) {
+                    let Some(row) = row_at(selection, pos) else {
+                        continue;
+                    };
+                    // Selecting an application selects all its (visible) processes.
+                    if row.is_group() {
+                        pids.extend(
+                            row.child_rows()
+                                .filter(|child| filter.match_(child))
+                                .map(|child| Pid::from_u32(child.pid())),
+                        );
                     } else {
-                        (None, false)
+                        pids.push(Pid::from_u32(row.pid()));
                     }
-                } else {
-                    (None, false)
-                };
-                current_pid.set(pid);
+                }
+                pids.sort_unstable();
+                pids.dedup();
+                let ret = !pids.is_empty();
+                *selected_pids.borrow_mut() = pids;
                 kill_button.set_sensitive(ret);
+                signal_button.set_sensitive(ret);
                 info_button.set_sensitive(ret);
             }
         ));
+        // The context menu, opened with a right click on a process.
+        let context_menu = gtk::PopoverMenu::from_model(Some(&create_context_menu()));
+        context_menu.set_parent(&column_view);
+        context_menu.set_has_arrow(false);
+        context_menu.set_halign(gtk::Align::Start);
+        column_view.connect_destroy(glib::clone!(
+            #[weak]
+            context_menu,
+            move |_| context_menu.unparent()
+        ));
+        let open_context_menu: ContextMenuCallback = Rc::new(glib::clone!(
+            #[weak]
+            column_view,
+            #[weak]
+            context_menu,
+            #[weak]
+            selection,
+            move |cell: &gtk::Widget, position: u32, x: f64, y: f64| {
+                // If the clicked row isn't part of the selection, it replaces it.
+                if !selection.is_selected(position) {
+                    selection.select_item(position, true);
+                }
+                let Some((x, y)) = cell.translate_coordinates(&column_view, x, y) else {
+                    return;
+                };
+                context_menu.set_pointing_to(Some(&gtk::gdk::Rectangle::new(x as _, y as _, 1, 1)));
+                context_menu.popup();
+            }
+        ));
+
         vertical_layout.append(&overlay);
+        horizontal_layout.append(&view_mode_button);
+        horizontal_layout.append(&columns_button);
+        horizontal_layout.append(&filters_button);
         horizontal_layout.append(&info_button);
         horizontal_layout.append(&kill_button);
+        horizontal_layout.append(&signal_button);
         vertical_layout.append(&horizontal_layout);
 
-        // The filter part.
-        let filter_model = gtk::TreeModelFilter::new(&list_store, None);
-        filter_model.set_visible_func(glib::clone!(
+        let columns = ProcColumn::ALL
+            .iter()
+            .map(|column| create_column(*column, &open_context_menu))
+            .collect::<Vec<_>>();
+        // The chosen columns are displayed first, in the order saved in the settings.
+        let mut displayed = settings
+            .borrow()
+            .process_columns
+            .iter()
+            .map(|c| c.column)
+            .collect::<Vec<_>>();
+        for (pos, column) in ProcColumn::ALL.iter().enumerate() {
+            if *column == ProcColumn::Instances {
+                // It isn't part of the settings, so we put it next to the name by default.
+                let name_pos = displayed.iter().position(|c| *c == ProcColumn::Name);
+                displayed.insert(name_pos.map_or(0, |pos| pos + 1), *column);
+            } else if !displayed.contains(column) {
+                displayed.push(*column);
+            }
+            if let Some(width) = settings
+                .borrow()
+                .process_columns
+                .iter()
+                .find(|c| c.column == *column)
+                .and_then(|c| c.width)
+            {
+                columns[pos].set_fixed_width(width);
+            }
+        }
+        for column in displayed {
+            column_view.append_column(&columns[column as usize]);
+        }
+        update_columns_visibility(
+            &columns,
+            &settings.borrow().process_columns,
+            initial_view_mode,
+        );
+        columns_button.set_popover(Some(&create_columns_popover(
+            &columns, settings, &view_mode,
+        )));
+        filters_button.set_popover(Some(&create_filters_popover(settings, &filter)));
+
+        // We save the new order when columns are moved around.
+        column_view.columns().connect_items_changed(glib::clone!(
             #[weak]
-            filter_entry,
-            #[upgrade_or]
-            false,
-            move |model, iter| {
-                if !WidgetExt::is_visible(&filter_entry) {
-                    return true;
-                }
-                let text = filter_entry.text();
-                if text.is_empty() {
-                    return true;
+            settings,
+            #[strong]
+            columns,
+            move |view_columns, _, _, _| {
+                // Moving a column removes it before adding it back, and columns are also
+                // removed when the view is destroyed: we only save complete orders.
+                if view_columns.n_items() as usize != columns.len() {
+                    return;
                 }
-                let text: &str = text.as_ref();
-                // TODO: Maybe add an option to make searches case sensitive?
-                let pid = model
-                    .get_value(iter, 0)
-                    .get::<u32>()
-                    .map(|p| p.to_string())
-                    .ok()
-                    .unwrap_or_default();
-                let name = model
-                    .get_value(iter, 1)
-                    .get::<String>()
-                    .map(|s| s.to_lowercase())
-                    .ok()
-                    .unwrap_or_default();
-                pid.contains(text)
-                    || text.contains(&pid)
-                    || name.contains(text)
-                    || text.contains(&name)
+                let order = (0..view_columns.n_items())
+                    .filter_map(|pos| view_columns.item(pos))
+                    .filter_map(|c| {
+                        columns
+                            .iter()
+                            .position(|x| x.upcast_ref::<glib::Object>() == &c)
+                    })
+                    .collect::<Vec<_>>();
+                let mut settings = settings.borrow_mut();
+                settings
+                    .process_columns
+                    .sort_by_key(|c| order.iter().position(|pos| *pos == c.column as usize));
+                settings.save();
             }
         ));
-        // For the filtering to be taken into account, we need to add it directly into the
-        // "global" model.
-        let sort_model = gtk::TreeModelSort::with_model(&filter_model);
-        left_tree.set_model(Some(&sort_model));
-        left_tree.set_search_entry(Some(&filter_entry));
-
-        append_column("pid", &mut columns, &left_tree, None);
-        append_column("process name", &mut columns, &left_tree, Some(200));
-        append_column("cpu usage", &mut columns, &left_tree, None);
-        append_column("memory usage", &mut columns, &left_tree, None);
-        #[cfg(not(windows))]
-        {
-            append_column("disk I/O usage", &mut columns, &left_tree, None);
-        }
-        #[cfg(windows)]
-        {
-            append_column("I/O usage", &mut columns, &left_tree, None);
+        for (column, view_column) in ProcColumn::ALL.iter().zip(columns.iter()) {
+            let column = *column;
+            // The new width is saved when the application is closed, to avoid writing the
+            // settings file again and again while the column is resized.
+            view_column.connect_fixed_width_notify(glib::clone!(
+                #[weak]
+                settings,
+                move |view_column| {
+                    let mut settings = settings.borrow_mut();
+                    if let Some(c) = settings
+                        .process_columns
+                        .iter_mut()
+                        .find(|c| c.column == column)
+                    {
+                        c.width = Some(view_column.fixed_width()).filter(|w| *w > 0);
+                    }
+                }
+            ));
         }
 
-        // When we click the "name" column the order is defined by the
-        // "name_lowercase" effectively making the built-in comparator ignore case.
-        columns[1].set_sort_column_id(5);
-        // Likewise clicking the "CPU" column sorts by the "CPU_f32" one because
-        // we want the order to be numerical not lexicographical.
-        columns[2].set_sort_column_id(6);
-        // The memory usage display has been improved, so to make efficient sort,
-        // we have to separate the display and the actual number.
-        columns[3].set_sort_column_id(7);
-        // The disk I/O usage display has been improved, so to make efficient sort,
-        // we have to separate the display and the actual number.
-        columns[4].set_sort_column_id(8);
-
-        filter_entry.connect_search_changed(move |_| {
-            filter_model.refilter();
-        });
+        let query = Rc::clone(&filter_state.query);
+        let update_filter = Rc::new(glib::clone!(
+            #[weak]
+            filter_entry,
+            #[weak]
+            filter_error,
+            #[weak]
+            filter,
+            #[strong]
+            settings,
+            move || {
+                let settings = settings.borrow();
+                match Query::parse(
+                    &filter_entry.text(),
+                    settings.process_search,
+                    &settings.process_search_fields,
+                ) {
+                    Ok(new_query) => {
+                        *query.borrow_mut() = new_query;
+                        filter_entry.remove_css_class("error");
+                        filter_error.set_visible(false);
+                    }
+                    // While the query is invalid, we keep filtering with the last valid one.
+                    Err(error) => {
+                        filter_entry.add_css_class("error");
+                        filter_error.set_text(&error.to_string());
+                        filter_error.set_visible(true);
+                        return;
+                    }
+                }
+                filter.changed(gtk::FilterChange::Different);
+            }
+        ));
+        filter_entry.connect_search_changed(glib::clone!(
+            #[strong]
+            update_filter,
+            move |_| update_filter()
+        ));
+        filter_row.append(&create_search_options_button(
+            settings.borrow().process_search,
+            Some(&settings.borrow().process_search_fields),
+            glib::clone!(
+                #[strong]
+                settings,
+                move |options, fields| {
+                    {
+                        let mut settings = settings.borrow_mut();
+                        settings.process_search = options;
+                        settings.process_search_fields = fields;
+                        settings.save();
+                    }
+                    update_filter();
+                }
+            ),
+        ));
 
         // Sort by CPU usage by default.
-        sort_model.set_sort_column_id(gtk::SortColumn::Index(6), gtk::SortType::Descending);
+        column_view.sort_by_column(
+            Some(&columns[ProcColumn::Cpu as usize]),
+            gtk::SortType::Descending,
+        );
 
         stack.add_titled(&vertical_layout, Some("Processes"), "Processes");
 
         Procs {
-            left_tree,
+            column_view,
             scroll,
-            current_pid,
+            selected_pids,
             kill_button,
+            signal_button,
             info_button,
+            view_mode_button,
+            columns_button,
+            filters_button,
+            view_mode,
             vertical_layout: vertical_layout
                 .downcast::<gtk::Box>()
                 .expect("downcast failed"),
-            list_store,
+            model: ProcessModel {
+                root,
+                index: Rc::new(RefCell::new(ProcessIndex::new())),
+                tree,
+                filter,
+                filter_state,
+                pinned_sorter,
+                sort_model,
+                column_sorter,
+                columns: columns.clone(),
+            },
             columns,
             filter_entry,
             search_bar,
@@ -220,69 +939,479 @@ impl Procs {
     }
 }
 
-fn append_column(
-    title: &str,
-    v: &mut Vec<gtk::TreeViewColumn>,
-    left_tree: &gtk::TreeView,
-    max_width: Option<i32>,
+fn create_context_menu() -> gtk::gio::Menu {
+    let menu = gtk::gio::Menu::new();
+    menu.append(Some("Information"), Some("app.process-info"));
+    menu.append(Some("End task"), Some("app.end-task"));
+    menu.append_submenu(Some("Send signal"), &create_signals_menu());
+    menu.append(Some("Change priority..."), Some("app.change-priority"));
+    menu.append(Some("Set CPU affinity..."), Some("app.cpu-affinity"));
+
+    let pins = gtk::gio::Menu::new();
+    pins.append(Some("Pin"), Some("app.pin-process"));
+    pins.append(Some("Pin by name..."), Some("app.pin-process-name"));
+    pins.append(Some("Unpin"), Some("app.unpin-process"));
+    menu.append_section(None, &pins);
+    menu
+}
+
+fn create_columns_popover(
+    columns: &[gtk::ColumnViewColumn],
+    settings: &Rc<RefCell<Settings>>,
+    view_mode: &Rc<Cell<ViewMode>>,
+) -> gtk::Popover {
+    let layout = gtk::Box::new(gtk::Orientation::Vertical, 0);
+    let columns = columns.to_vec();
+
+    for column in ProcColumn::ALL.iter().copied() {
+        // The process name is always displayed, and so is the instances count in the grouped
+        // mode.
+        if matches!(column, ProcColumn::Name | ProcColumn::Instances) {
+            continue;
+        }
+        let check_button = gtk::CheckButton::builder()
+            .label(column.title())
+            .active(
+                settings
+                    .borrow()
+                    .process_columns
+                    .iter()
+                    .any(|c| c.column == column),
+            )
+            .build();
+        check_button.connect_toggled(glib::clone!(
+            #[weak]
+            settings,
+            #[strong]
+            view_mode,
+            #[strong]
+            columns,
+            move |check_button| {
+                let mut settings = settings.borrow_mut();
+                if check_button.is_active() {
+                    if !settings.process_columns.iter().any(|c| c.column == column) {
+                        settings.process_columns.push(ProcessColumnSettings {
+                            column,
+                            width: None,
+                        });
+                    }
+                } else {
+                    settings.process_columns.retain(|c| c.column != column);
+                }
+                update_columns_visibility(&columns, &settings.process_columns, view_mode.get());
+                settings.save();
+            }
+        ));
+        layout.append(&check_button);
+    }
+
+    gtk::Popover::builder().child(&layout).build()
+}
+
+fn create_filters_popover(
+    settings: &Rc<RefCell<Settings>>,
+    filter: &gtk::CustomFilter,
+) -> gtk::Popover {
+    let layout = gtk::Box::new(gtk::Orientation::Vertical, 0);
+
+    let kernel_threads = gtk::CheckButton::builder()
+        .label("Hide kernel threads")
+        .active(settings.borrow().hide_kernel_threads)
+        .build();
+    kernel_threads.connect_toggled(glib::clone!(
+        #[weak]
+        settings,
+        #[weak]
+        filter,
+        move |check_button| {
+            {
+                let mut settings = settings.borrow_mut();
+                settings.hide_kernel_threads = check_button.is_active();
+                settings.save();
+            }
+            filter.changed(gtk::FilterChange::Different);
+        }
+    ));
+    layout.append(&kernel_threads);
+
+    layout.append(
+        &gtk::Label::builder()
+            .label("Processes in state:")
+            .xalign(0.)
+            .margin_top(6)
+            .build(),
+    );
+    for state in StateKind::ALL.iter().copied() {
+        let check_button = gtk::CheckButton::builder()
+            .label(state.title())
+            .active(settings.borrow().process_states.contains(&state))
+            .build();
+        check_button.connect_toggled(glib::clone!(
+            #[weak]
+            settings,
+            #[weak]
+            filter,
+            move |check_button| {
+                {
+                    let mut settings = settings.borrow_mut();
+                    if check_button.is_active() {
+                        if !settings.process_states.contains(&state) {
+                            settings.process_states.push(state);
+                        }
+                    } else {
+                        settings.process_states.retain(|s| *s != state);
+                    }
+                    settings.save();
+                }
+                filter.changed(gtk::FilterChange::Different);
+            }
+        ));
+        layout.append(&check_button);
+    }
+
+    gtk::Popover::builder().child(&layout).build()
+}
+
+pub fn update_columns_visibility(
+    columns: &[gtk::ColumnViewColumn],
+    displayed: &[ProcessColumnSettings],
+    view_mode: ViewMode,
 ) {
-    let id = v.len() as i32;
-    let renderer = gtk::CellRendererText::new();
+    for (column, view_column) in ProcColumn::ALL.iter().zip(columns.iter()) {
+        view_column.set_visible(match column {
+            ProcColumn::Name => true,
+            ProcColumn::Instances => view_mode == ViewMode::Grouped,
+            _ => displayed.iter().any(|c| c.column == *column) && column.is_available_in(view_mode),
+        });
+    }
+}
 
-    if title != "process name" {
-        renderer.set_xalign(1.0);
+/// Returns the process information needed by the displayed `columns` and by the filter.
+pub fn process_refresh(
+    columns: &[gtk::ColumnViewColumn],
+    search_bar: &gtk::SearchBar,
+) -> ProcessRefresh {
+    // The executable names the processes and groups them by application, and the tasks are the
+    // threads, which are listed too.
+    let refresh = ProcessRefresh {
+        kind: ProcessRefreshKind::nothing()
+            .with_exe(UpdateKind::OnlyIfNotSet)
+            .with_tasks(),
+        priorities: false,
+        memory_details: false,
+    };
+    // The filter query can use any of the values.
+    let filtering = search_bar.is_search_mode();
+    ProcColumn::ALL
+        .iter()
+        .zip(columns.iter())
+        .filter(|(column, view_column)| {
+            view_column.is_visible() || (filtering && !column.is_memory_detail())
+        })
+        .fold(refresh, |refresh, (column, _)| column.refresh(refresh))
+}
+
+impl FilterRow for ProcessRow {
+    fn text(&self, field: TextField) -> String {
+        match field {
+            TextField::Name => self.name(),
+            TextField::Command => self.command(),
+            TextField::User => self.user(),
+            TextField::State => self.state(),
+            TextField::Exe => self.exe(),
+        }
     }
 
-    let column = gtk::TreeViewColumn::builder()
-        .title(title)
+    fn number(&self, field: NumberField) -> Option<f64> {
+        let known = |value: i64| Some(value as f64).filter(|_| value != UNKNOWN);
+        match field {
+            NumberField::Pid => Some(self.pid().into()),
+            NumberField::Cpu => Some(self.cpu().into()),
+            NumberField::Memory => Some(self.memory() as _),
+            NumberField::VirtualMemory => Some(self.virtual_memory() as _),
+            NumberField::DiskUsage => Some(self.disk_usage() as _),
+            NumberField::ParentPid => known(self.parent_pid()),
+            NumberField::Threads => known(self.threads()),
+            NumberField::Nice => known(self.nice()),
+        }
+    }
+}
+
+/// Everything deciding whether a row of the processes list is displayed.
+struct RowFilters<'a> {
+    query: Option<&'a Query>,
+    hide_kernel_threads: bool,
+    /// The process states to display.
+    states: &'a [StateKind],
+}
+
+impl RowFilters<'_> {
+    fn is_empty(&self) -> bool {
+        self.query.is_none()
+            && !self.hide_kernel_threads
+            && StateKind::ALL.iter().all(|s| self.states.contains(s))
+    }
+
+    fn row_matches(&self, row: &ProcessRow) -> bool {
+        if self.hide_kernel_threads && row.kernel_thread() {
+            return false;
+        }
+        if !self.states.contains(&row.state_kind()) {
+            return false;
+        }
+        self.query.is_none_or(|query| query.matches(row))
+    }
+
+    fn subtree_matches(&self, row: &ProcessRow) -> bool {
+        // Application rows are only displayed if some of their processes match.
+        (!row.is_group() && self.row_matches(row))
+            || row.child_rows().any(|child| self.subtree_matches(&child))
+    }
+}
+
+/// Creates the view column of `column`. A right click on one of its cells calls
+/// `open_context_menu`.
+fn create_column(
+    column: ProcColumn,
+    open_context_menu: &ContextMenuCallback,
+) -> gtk::ColumnViewColumn {
+    let factory = gtk::SignalListItemFactory::new();
+    let open_context_menu = Rc::clone(open_context_menu);
+    factory.connect_setup(move |_, list_item| {
+        let Some(list_item) = list_item.downcast_ref::<gtk::ListItem>() else {
+            return;
+        };
+        let label = gtk::Label::builder()
+            .xalign(if column.is_text() { 0. } else { 1. })
+            .use_markup(column == ProcColumn::State)
+            .build();
+        // The items of the view are the rows of the tree list model, which hold our rows.
+        let list_row = list_item.property_expression_weak("item");
+        let row = list_row.chain_property::<gtk::TreeListRow>("item");
+        let value = row.chain_property::<ProcessRow>(column.property());
+        gtk::ClosureExpression::with_callback([&row, &value], move |values| {
+            // The first value is the `this` object of the expression, which isn't used.
+            values[1]
+                .get::<Option<ProcessRow>>()
+                .ok()
+                .flatten()
+                .map(|row| column.text(&row))
+                .unwrap_or_default()
+        })
+        .bind(&label, "label", gtk::Widget::NONE);
+
+        let cell = if column == ProcColumn::Name {
+            let pin = gtk::Image::from_icon_name("view-pin-symbolic");
+            row.chain_property::<ProcessRow>("pinned")
+                .bind(&pin, "visible", gtk::Widget::NONE);
+            let layout = gtk::Box::new(gtk::Orientation::Horizontal, 4);
+            layout.append(&pin);
+            layout.append(&label);
+            // The expanders are more readable next to the process name.
+            let expander = gtk::TreeExpander::builder().child(&layout).build();
+            list_row.bind(&expander, "list-row", gtk::Widget::NONE);
+            expander.upcast::<gtk::Widget>()
+        } else {
+            label.upcast()
+        };
+        let right_click = gtk::GestureClick::builder()
+            .button(gtk::gdk::BUTTON_SECONDARY)
+            .build();
+        right_click.connect_pressed(glib::clone!(
+            #[weak]
+            list_item,
+            #[strong]
+            open_context_menu,
+            move |gesture, _, x, y| {
+                if let Some(cell) = gesture.widget() {
+                    open_context_menu(&cell, list_item.position(), x, y);
+                }
+            }
+        ));
+        cell.add_controller(right_click);
+        list_item.set_child(Some(&cell));
+    });
+
+    let view_column = gtk::ColumnViewColumn::builder()
+        .title(column.title())
+        .factory(&factory)
         .resizable(true)
-        .min_width(10)
-        .clickable(true)
-        .sort_column_id(id)
+        .sorter(&create_sorter(move |a: &ProcessRow, b: &ProcessRow| {
+            column.compare(a, b)
+        }))
         .build();
-
-    if let Some(max_width) = max_width {
-        column.set_max_width(max_width);
-        column.set_expand(true);
+    if matches!(column, ProcColumn::Name | ProcColumn::Command) {
+        view_column.set_expand(true);
     }
-    column.pack_start(&renderer, true);
-    column.add_attribute(&renderer, "text", id);
-    left_tree.append_column(&column);
-    v.push(column);
+    view_column
+}
+
+/// Adds the properties whose setter reported a change to `changed`.
+fn add_changed<const N: usize>(
+    changed: &mut HashSet<&'static str>,
+    properties: [(&'static str, bool); N],
+) {
+    changed.extend(
+        properties
+            .into_iter()
+            .filter_map(|(property, is_changed)| is_changed.then_some(property)),
+    );
 }
 
-pub fn create_and_fill_model(
-    list_store: &gtk::ListStore,
-    pid: u32,
-    cmdline: &[OsString],
-    name: &OsStr,
-    cpu: f32,
-    memory: u64,
+/// Updates all the properties of a process row, except the ones which never change. The ones
+/// which changed are added to `changed`.
+pub fn update_process_row(
+    row: &ProcessRow,
+    p: &ProcessInfo,
+    extra: &ProcessExtra,
+    changed: &mut HashSet<&'static str>,
 ) {
+    add_changed(
+        changed,
+        [
+            ("cpu", row.set_cpu(p.cpu_usage)),
+            ("memory", row.set_memory(p.memory)),
+            ("disk-usage", row.set_disk_usage(p.disk_usage)),
+            ("subtree-cpu", row.set_subtree_cpu(extra.subtree_cpu)),
+            (
+                "subtree-memory",
+                row.set_subtree_memory(extra.subtree_memory),
+            ),
+            ("user", row.set_user(extra.user.clone())),
+            ("pinned", row.set_pinned(extra.pinned)),
+            ("state", row.set_state(p.status.to_string())),
+            ("state-kind", row.set_state_kind(StateKind::of(p.status))),
+            (
+                "threads",
+                row.set_threads(p.threads.map_or(UNKNOWN, |threads| threads as i64)),
+            ),
+            ("virtual-memory", row.set_virtual_memory(p.virtual_memory)),
+            ("nice", row.set_nice(p.nice.map_or(UNKNOWN, i64::from))),
+            ("io-priority", row.set_io_priority(p.io_priority)),
+            (
+                "parent-pid",
+                row.set_parent_pid(p.parent.map_or(UNKNOWN, |parent| parent.as_u32().into())),
+            ),
+            ("pss", row.set_pss(known_size(p, |usage| usage.pss))),
+            ("uss", row.set_uss(known_size(p, |usage| usage.private))),
+            ("swap", row.set_swap(known_size(p, |usage| usage.swap))),
+        ],
+    );
+}
+
+/// Summarizes the values [`update_process_row`] sets, so the row of a process is only updated
+/// when one of them changes.
+pub fn row_fingerprint(p: &ProcessInfo, extra: &ProcessExtra) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    p.cpu_usage.to_bits().hash(&mut hasher);
+    extra.subtree_cpu.to_bits().hash(&mut hasher);
+    (
+        p.memory,
+        p.disk_usage,
+        p.status,
+        p.threads,
+        p.virtual_memory,
+        p.nice,
+        p.io_priority,
+        p.parent,
+        p.memory_details,
+    )
+        .hash(&mut hasher);
+    (extra.subtree_memory, &extra.user, extra.pinned).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Returns a value of the memory details of `p`, or [`UNKNOWN`] if they weren't read.
+fn known_size(p: &ProcessInfo, value: fn(&MemoryUsage) -> u64) -> i64 {
+    p.memory_details
+        .as_ref()
+        .map_or(UNKNOWN, |usage| value(usage) as i64)
+}
+
+pub fn create_process_row(p: &ProcessInfo, extra: &ProcessExtra) -> Option<ProcessRow> {
+    let name = p.application_name();
     let name = if name.is_empty() {
-        let Some(cmd) = cmdline
+        let cmd = p
+            .cmd
             .iter()
             .map(|c| c.to_string_lossy().to_string())
-            .next()
-        else {
-            return;
-        };
+            .next()?;
         cmd
     } else {
         name.to_string_lossy().to_string()
     };
-    list_store.insert_with_values(
-        None,
-        &[
-            (0, &pid),
-            (1, &name),
-            (2, &format!("{cpu:.1}")),
-            (3, &format_number(memory)),
-            (4, &String::new()),
-            (5, &name.to_lowercase()),
-            (6, &cpu),
-            (7, &memory),
-            (8, &0),
+    let command = p
+        .cmd
+        .iter()
+        .map(|c| c.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let row = ProcessRow::new();
+    row.set_pid(p.pid.as_u32());
+    row.set_name(name);
+    row.set_start_time(p.start_time);
+    row.set_command(command);
+    row.set_exe(
+        p.exe
+            .as_ref()
+            .map(|exe| exe.display().to_string())
+            .unwrap_or_default(),
+    );
+    row.set_kernel_thread(p.thread_kind == Some(ThreadKind::Kernel));
+    update_process_row(&row, p, extra, &mut HashSet::new());
+    Some(row)
+}
+
+/// Creates the row grouping the processes of the application `name`.
+pub fn create_group_row(name: &str, exe: &str) -> ProcessRow {
+    let row = ProcessRow::new_group(name);
+    row.set_exe(exe.to_owned());
+    row
+}
+
+/// Updates the row of an application with the sum of the usage of its `processes`. `user` is
+/// the user running them, or an empty string if they are run by different users. The row is
+/// `pinned` if any of its processes is. The properties which changed are added to `changed`.
+pub fn update_group_row(
+    row: &ProcessRow,
+    processes: &[&ProcessInfo],
+    user: &str,
+    pinned: bool,
+    changed: &mut HashSet<&'static str>,
+) {
+    let cpu = processes.iter().map(|p| p.cpu_usage).sum::<f32>();
+    let memory = processes.iter().map(|p| p.memory).sum::<u64>();
+    let disk_usage = processes.iter().map(|p| p.disk_usage).sum::<u64>();
+    let virtual_memory = processes.iter().map(|p| p.virtual_memory).sum::<u64>();
+    let threads = processes.iter().filter_map(|p| p.threads).sum::<usize>() as i64;
+    // The memory details are only unknown if they are unknown for all the processes.
+    let size = |value: fn(&MemoryUsage) -> u64| {
+        processes
+            .iter()
+            .filter_map(|p| p.memory_details.as_ref().map(value))
+            .reduce(|a, b| a + b)
+            .map_or(UNKNOWN, |size| size as i64)
+    };
+
+    add_changed(
+        changed,
+        [
+            ("cpu", row.set_cpu(cpu)),
+            ("memory", row.set_memory(memory)),
+            ("disk-usage", row.set_disk_usage(disk_usage)),
+            ("subtree-cpu", row.set_subtree_cpu(cpu)),
+            ("subtree-memory", row.set_subtree_memory(memory)),
+            ("user", row.set_user(user.to_owned())),
+            ("pinned", row.set_pinned(pinned)),
+            (
+                "threads",
+                row.set_threads(if threads > 0 { threads } else { UNKNOWN }),
+            ),
+            ("virtual-memory", row.set_virtual_memory(virtual_memory)),
+            ("instances", row.set_instances(processes.len() as u32)),
+            ("pss", row.set_pss(size(|usage| usage.pss))),
+            ("uss", row.set_uss(size(|usage| usage.private))),
+            ("swap", row.set_swap(size(|usage| usage.swap))),
         ],
     );
 }
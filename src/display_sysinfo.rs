@@ -4,8 +4,8 @@ use gtk::prelude::*;
 use std::cell::RefCell;
 use std::iter;
 use std::rc::Rc;
-use std::sync::{Arc, Mutex};
 
+use crate::collector::SystemInfo;
 use crate::graph::GraphWidget;
 use crate::settings::Settings;
 use crate::utils::{format_number, graph_label_units, RotateVec};
@@ -68,12 +68,7 @@ pub struct DisplaySysInfo {
 }
 
 impl DisplaySysInfo {
-    pub fn new(
-        sys: &Arc<Mutex<sysinfo::System>>,
-        sys_components: &sysinfo::Components,
-        stack: &gtk::Stack,
-        settings: &Settings,
-    ) -> DisplaySysInfo {
+    pub fn new(sys: &SystemInfo, stack: &gtk::Stack, settings: &Settings) -> DisplaySysInfo {
         let vertical_layout = gtk::Box::new(gtk::Orientation::Vertical, 0);
         let mut procs = Vec::new();
         let scroll = gtk::ScrolledWindow::new();
@@ -92,9 +87,8 @@ impl DisplaySysInfo {
             ]
         })));
 
-        let sys = sys.lock().expect("failed to lock in DisplaySysInfo::new");
         // RAM
-        let ram_usage_history = GraphWidget::new(Some(sys.total_memory() as f32), true);
+        let ram_usage_history = GraphWidget::new(Some(sys.total_memory as f32), true);
         ram_usage_history.set_margin_start(3);
         ram_usage_history.set_margin_end(6);
         ram_usage_history.set_labels_callback(Some(Box::new(graph_label_units)));
@@ -143,20 +137,20 @@ impl DisplaySysInfo {
             p.set_margin_end(5);
             p.set_margin_start(5);
             p.set_show_text(true);
-            let cpu_usage = sys.global_cpu_usage();
+            let cpu_usage = sys.global_cpu_usage;
             p.set_text(Some(&format!("{cpu_usage:.1} %")));
             p.set_fraction(f64::from(cpu_usage / 100.));
             vertical_layout.append(p);
         }
         let check_box = create_header("Processors usage", &vertical_layout, settings.display_graph);
-        for (i, pro) in sys.cpus().iter().enumerate() {
+        for (i, cpu_usage) in sys.cpu_usages.iter().enumerate() {
             procs.push(gtk::ProgressBar::new());
             let p: &gtk::ProgressBar = &procs[i + 1];
             let l = gtk::Label::new(Some(&format!("{i}")));
 
-            p.set_text(Some(&format!("{:.1} %", pro.cpu_usage())));
+            p.set_text(Some(&format!("{cpu_usage:.1} %")));
             p.set_show_text(true);
-            p.set_fraction(f64::from(pro.cpu_usage()));
+            p.set_fraction(f64::from(*cpu_usage));
             non_graph_layout.attach(&l, 0, i as i32 - 1, 1, 1);
             non_graph_layout.attach(p, 1, i as i32 - 1, 11, 1);
             cpu_usage_history.push(
@@ -192,27 +186,27 @@ impl DisplaySysInfo {
         //
         // TEMPERATURES PART
         //
-        if !sys_components.is_empty() {
+        if !sys.components.is_empty() {
             check_box3 = Some(create_header(
                 "Components' temperature",
                 &vertical_layout,
                 settings.display_graph,
             ));
-            for component in sys_components {
-                let Some(temperature) = component.temperature() else {
+            for component in sys.components.iter() {
+                let Some(temperature) = component.temperature else {
                     continue;
                 };
                 let horizontal_layout = gtk::Box::new(gtk::Orientation::Horizontal, 10);
                 // TODO: add max and critical temperatures as well
                 let temp = gtk::Label::new(Some(&format!("{temperature:.1} °C")));
-                horizontal_layout.append(&gtk::Label::new(Some(component.label())));
+                horizontal_layout.append(&gtk::Label::new(Some(&component.label)));
                 horizontal_layout.append(&temp);
                 horizontal_layout.set_homogeneous(true);
                 non_graph_layout3.append(&horizontal_layout);
                 components.push(temp);
                 temperature_usage_history.push(
                     RotateVec::new(iter::repeat_n(0f32, 61).collect()),
-                    component.label(),
+                    &component.label,
                     None,
                 );
             }
@@ -288,7 +282,7 @@ impl DisplaySysInfo {
             temperature_usage_history,
             temperature_check_box: check_box3,
         };
-        tmp.update_system_info(&sys, sys_components, settings.display_fahrenheit);
+        tmp.update_system_info(sys, settings.display_fahrenheit);
         tmp
     }
 
@@ -300,12 +294,7 @@ impl DisplaySysInfo {
         }
     }
 
-    pub fn update_system_info(
-        &mut self,
-        sys: &sysinfo::System,
-        sys_components: &sysinfo::Components,
-        display_fahrenheit: bool,
-    ) {
+    pub fn update_system_info(&mut self, sys: &SystemInfo, display_fahrenheit: bool) {
         let disp = |total, used| {
             format!(
                 "{} / {}",
@@ -314,8 +303,8 @@ impl DisplaySysInfo {
             )
         };
 
-        let total_ram = sys.total_memory();
-        let used = sys.used_memory();
+        let total_ram = sys.total_memory;
+        let used = sys.used_memory;
         self.ram.set_text(Some(&disp(total_ram, used)));
         if total_ram != 0 {
             self.ram.set_fraction(used as f64 / total_ram as f64);
@@ -332,9 +321,9 @@ impl DisplaySysInfo {
             });
         }
 
-        let total = ::std::cmp::max(sys.total_swap(), total_ram);
-        let used = sys.used_swap();
-        self.swap.set_text(Some(&disp(sys.total_swap(), used)));
+        let total = ::std::cmp::max(sys.total_swap, total_ram);
+        let used = sys.used_swap;
+        self.swap.set_text(Some(&disp(sys.total_swap, used)));
 
         let mut fraction = if total != 0 {
             used as f64 / total as f64
@@ -358,12 +347,13 @@ impl DisplaySysInfo {
         // temperature part
         let t = self.temperature_usage_history.borrow_mut();
         #[allow(clippy::collapsible_else_if)]
-        for (pos, (component, label)) in sys_components
+        for (pos, (component, label)) in sys
+            .components
             .iter()
             .zip(self.components.iter())
             .enumerate()
         {
-            if let Some(temperature) = component.temperature() {
+            if let Some(temperature) = component.temperature {
                 t.data(pos, |d| {
                     d.move_start();
                     if let Some(t) = d.get_mut(0) {
@@ -385,23 +375,23 @@ impl DisplaySysInfo {
         }
     }
 
-    pub fn update_system_info_display(&mut self, sys: &sysinfo::System) {
+    pub fn update_system_info_display(&mut self, sys: &SystemInfo) {
         let v = &*self.procs.borrow_mut();
         let h = &mut *self.cpu_usage_history.borrow_mut();
 
-        let cpu_usage = sys.global_cpu_usage();
+        let cpu_usage = sys.global_cpu_usage;
         v[0].set_text(Some(&format!("{cpu_usage:.1} %")));
         v[0].set_show_text(true);
         v[0].set_fraction(f64::from(cpu_usage / 100.));
-        for (i, pro) in sys.cpus().iter().enumerate() {
+        for (i, cpu_usage) in sys.cpu_usages.iter().enumerate() {
             let i = i + 1;
-            v[i].set_text(Some(&format!("{:.1} %", pro.cpu_usage())));
+            v[i].set_text(Some(&format!("{cpu_usage:.1} %")));
             v[i].set_show_text(true);
-            v[i].set_fraction(f64::from(pro.cpu_usage() / 100.));
+            v[i].set_fraction(f64::from(cpu_usage / 100.));
             h.data(i - 1, |d| {
                 d.move_start();
                 if let Some(h) = d.get_mut(0) {
-                    *h = pro.cpu_usage() / 100.;
+                    *h = cpu_usage / 100.;
                 }
             });
         }
@@ -0,0 +1,319 @@
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{gio, glib};
+use sysinfo::Uid;
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::iter;
+
+use crate::collector::ProcessTable;
+use crate::graph::GraphWidget;
+use crate::process_index::ProcessEntry;
+use crate::utils::{
+    compare_ignore_case, create_sorter, create_text_column, format_number, graph_label_units,
+    sort_again, RotateVec, UsersCache,
+};
+
+mod imp {
+    use super::*;
+
+    use std::cell::RefCell;
+
+    #[derive(Default, glib::Properties)]
+    #[properties(wrapper_type = super::UserRow)]
+    pub struct UserRow {
+        #[property(get, construct_only)]
+        uid: RefCell<String>,
+        #[property(get, construct_only)]
+        name: RefCell<String>,
+        #[property(get, set)]
+        processes: RefCell<u32>,
+        #[property(get, set)]
+        cpu: RefCell<f32>,
+        #[property(get, set)]
+        memory: RefCell<u64>,
+        #[property(get, set)]
+        disk_usage: RefCell<u64>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for UserRow {
+        const NAME: &'static str = "UserRow";
+        type Type = super::UserRow;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for UserRow {}
+}
+
+glib::wrapper! {
+    /// A row of the users list.
+    pub struct UserRow(ObjectSubclass<imp::UserRow>);
+}
+
+impl UserRow {
+    fn new(uid: &Uid, name: &str) -> Self {
+        glib::Object::builder()
+            .property("uid", uid.to_string())
+            .property("name", name)
+            .build()
+    }
+
+    fn update(&self, user: &UserTotals) {
+        self.set_processes(user.processes);
+        self.set_cpu(user.cpu_usage);
+        self.set_memory(user.memory);
+        self.set_disk_usage(user.disk_usage);
+    }
+}
+
+/// Resources used by all the processes of a user.
+#[derive(Default)]
+struct UserTotals {
+    processes: u32,
+    cpu_usage: f32,
+    memory: u64,
+    disk_usage: u64,
+}
+
+fn compute_totals(processes: &ProcessTable) -> HashMap<Uid, UserTotals> {
+    let mut totals: HashMap<Uid, UserTotals> = HashMap::new();
+    // Threads share the resources of their process, they would be counted twice otherwise.
+    for process in processes.values().filter(|p| !p.is_userland_thread()) {
+        let Some(ref uid) = process.user_id else {
+            continue;
+        };
+        let user = totals.entry(uid.clone()).or_default();
+        user.processes += 1;
+        user.cpu_usage += process.cpu_usage;
+        user.memory += process.memory;
+        user.disk_usage += process.disk_usage;
+    }
+    totals
+}
+
+fn append_column<F: Fn(&UserRow) -> String + 'static>(
+    column_view: &gtk::ColumnView,
+    title: &str,
+    property: &str,
+    text: F,
+    compare: fn(&UserRow, &UserRow) -> Ordering,
+) -> gtk::ColumnViewColumn {
+    let xalign = if property == "name" { 0. } else { 1. };
+    let column = create_text_column(title, property, xalign, text);
+    column.set_sorter(Some(&create_sorter(compare)));
+    column_view.append_column(&column);
+    column
+}
+
+fn create_graph(title: &str, graph: &GraphWidget, layout: &gtk::Box) {
+    graph.set_margin_start(3);
+    graph.set_margin_end(6);
+    layout.append(&gtk::Label::new(Some(title)));
+    layout.append(graph);
+}
+
+/// The "Users" tab, summing the resources used by the processes of each user.
+pub struct UsersSummary {
+    list_store: gio::ListStore,
+    sort_model: gtk::SortListModel,
+    cpu_usage_history: GraphWidget,
+    ram_usage_history: GraphWidget,
+    disk_usage_history: GraphWidget,
+    // The users in the order of their curves in the graphs. Curves are never removed, a user
+    // without processes simply goes down to 0.
+    graph_users: Vec<Uid>,
+}
+
+impl UsersSummary {
+    pub fn new(stack: &gtk::Stack, total_memory: u64) -> Self {
+        let list_store = gio::ListStore::new::<UserRow>();
+        let column_view = gtk::ColumnView::new(None::<gtk::SelectionModel>);
+        let sort_model = gtk::SortListModel::new(Some(list_store.clone()), column_view.sorter());
+        column_view.set_model(Some(&gtk::NoSelection::new(Some(sort_model.clone()))));
+        append_column(&column_view, "user", "name", UserRow::name, |a, b| {
+            compare_ignore_case(&a.name(), &b.name())
+        });
+        append_column(
+            &column_view,
+            "processes",
+            "processes",
+            |row| row.processes().to_string(),
+            |a, b| a.processes().cmp(&b.processes()),
+        );
+        let cpu_column = append_column(
+            &column_view,
+            "CPU",
+            "cpu",
+            |row| format!("{:.1}", row.cpu()),
+            |a, b| a.cpu().total_cmp(&b.cpu()),
+        );
+        append_column(
+            &column_view,
+            "memory",
+            "memory",
+            |row| format_number(row.memory()),
+            |a, b| a.memory().cmp(&b.memory()),
+        );
+        append_column(
+            &column_view,
+            if cfg!(windows) {
+                "I/O usage"
+            } else {
+                "disk I/O"
+            },
+            "disk-usage",
+            |row| {
+                if row.disk_usage() > 0 {
+                    format_number(row.disk_usage())
+                } else {
+                    String::new()
+                }
+            },
+            |a, b| a.disk_usage().cmp(&b.disk_usage()),
+        );
+        // The users using the most CPU come first.
+        column_view.sort_by_column(Some(&cpu_column), gtk::SortType::Descending);
+        let tree_scroll = gtk::ScrolledWindow::builder()
+            .child(&column_view)
+            .vexpand(true)
+            .min_content_height(150)
+            .build();
+
+        let cpu_usage_history = GraphWidget::new(Some(100.), false);
+        // Like for processes, a user can use more than 100% if it has more than one CPU.
+        cpu_usage_history.set_minimum(Some(100.));
+        cpu_usage_history.set_labels_callback(Some(Box::new(|v| {
+            let nb = v.ceil().max(100.) as u64;
+            [
+                nb.to_string(),
+                (nb / 2).to_string(),
+                "0".to_string(),
+                "%".to_string(),
+            ]
+        })));
+        let ram_usage_history = GraphWidget::new(Some(total_memory as f32), false);
+        ram_usage_history.set_overhead(Some(20.));
+        ram_usage_history.set_labels_callback(Some(Box::new(graph_label_units)));
+        let disk_usage_history = GraphWidget::new(Some(0f32), false);
+        disk_usage_history.set_overhead(Some(20.));
+        disk_usage_history.set_labels_callback(Some(Box::new(graph_label_units)));
+
+        let graphs_layout = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(5)
+            .margin_top(10)
+            .margin_bottom(10)
+            .build();
+        create_graph("CPU usage", &cpu_usage_history, &graphs_layout);
+        create_graph("Memory usage", &ram_usage_history, &graphs_layout);
+        #[cfg(not(windows))]
+        create_graph("Disk I/O usage", &disk_usage_history, &graphs_layout);
+        #[cfg(windows)]
+        create_graph("I/O usage", &disk_usage_history, &graphs_layout);
+        let graphs_scroll = gtk::ScrolledWindow::builder()
+            .child(&graphs_layout)
+            .vexpand(true)
+            .build();
+
+        let paned = gtk::Paned::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .start_child(&tree_scroll)
+            .end_child(&graphs_scroll)
+            .shrink_start_child(false)
+            .shrink_end_child(false)
+            .build();
+        stack.add_titled(&paned, Some("Users"), "Users");
+
+        UsersSummary {
+            list_store,
+            sort_model,
+            cpu_usage_history,
+            ram_usage_history,
+            disk_usage_history,
+            graph_users: Vec::new(),
+        }
+    }
+
+    /// Samples the totals of each user into the graphs. The list and the graphs are only
+    /// redrawn if `displayed` is set.
+    pub fn update(&mut self, processes: &ProcessTable, users: &mut UsersCache, displayed: bool) {
+        let totals = compute_totals(processes);
+        self.update_graphs(&totals, users, displayed);
+        if displayed {
+            self.update_list(&totals, users);
+        }
+    }
+
+    fn update_list(&self, totals: &HashMap<Uid, UserTotals>, users: &mut UsersCache) {
+        let mut seen = Vec::with_capacity(totals.len());
+        let mut pos = 0;
+        while let Some(row) = self.list_store.item(pos).and_downcast::<UserRow>() {
+            let uid = row.uid();
+            match totals.iter().find(|(u, _)| u.to_string() == uid) {
+                Some((uid, user)) => {
+                    row.update(user);
+                    seen.push(uid);
+                    pos += 1;
+                }
+                None => self.list_store.remove(pos),
+            }
+        }
+        for (uid, user) in totals.iter().filter(|(uid, _)| !seen.contains(uid)) {
+            let row = UserRow::new(uid, &users.name(uid));
+            row.update(user);
+            self.list_store.append(&row);
+        }
+
+        sort_again(&self.sort_model);
+    }
+
+    fn update_graphs(
+        &mut self,
+        totals: &HashMap<Uid, UserTotals>,
+        users: &mut UsersCache,
+        redraw: bool,
+    ) {
+        let graphs = [
+            &self.cpu_usage_history,
+            &self.ram_usage_history,
+            &self.disk_usage_history,
+        ];
+
+        for uid in totals.keys() {
+            if !self.graph_users.contains(uid) {
+                let name = users.name(uid);
+                for graph in graphs {
+                    graph.push(
+                        RotateVec::new(iter::repeat_n(0f32, 61).collect()),
+                        &name,
+                        None,
+                    );
+                }
+                self.graph_users.push(uid.clone());
+            }
+        }
+        for (pos, uid) in self.graph_users.iter().enumerate() {
+            let user = totals.get(uid);
+            let values = [
+                user.map(|u| u.cpu_usage),
+                user.map(|u| u.memory as f32),
+                user.map(|u| u.disk_usage as f32),
+            ];
+            for (graph, value) in graphs.iter().zip(values) {
+                graph.data(pos, |d| {
+                    d.move_start();
+                    if let Some(p) = d.get_mut(0) {
+                        *p = value.unwrap_or(0.);
+                    }
+                });
+            }
+        }
+        if redraw {
+            for graph in graphs {
+                graph.queue_draw();
+            }
+        }
+    }
+}
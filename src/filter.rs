@@ -0,0 +1,875 @@
+//! The query language used to filter the processes list.
+//!
+//! A query is a list of terms, implicitly combined with `AND`. Terms can also be combined with
+//! `OR`, negated with `NOT` and grouped with parentheses. `NOT` has the highest precedence, then
+//! `AND`, then `OR`. A term is either:
+//!
+//!  * a plain word, matching processes whose name or PID contains it. The searched fields can be
+//!    changed in the search options, and the word can be used as a regular expression.
+//!  * `field:value`, matching processes whose field contains the value for text fields (or
+//!    matches it in the regular expression mode) or is equal to it for numeric fields.
+//!  * `field=value` or `field!=value` for exact comparisons.
+//!  * `field>value`, `field>=value`, `field<value`, `field<=value` for numeric fields.
+//!  * `field~regex`, matching text fields with a regular expression.
+//!
+//! Values can be quoted to contain whitespace or parentheses: `cmd:"--port 80"`. Sizes accept
+//! the `K`, `M`, `G` and `T` suffixes (`mem>500M`). Text comparisons ignore case unless the
+//! search is case sensitive.
+
+use regex::{Regex, RegexBuilder};
+use serde_derive::{Deserialize, Serialize};
+
+use std::fmt;
+
+/// Options of a search bar, saved in the settings.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SearchOptions {
+    /// Search text is used as a regular expression.
+    pub regex: bool,
+    pub case_sensitive: bool,
+}
+
+/// The fields in which plain words are searched in the processes list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchField {
+    Name,
+    Command,
+    Exe,
+    Pid,
+}
+
+impl SearchField {
+    pub const ALL: &'static [SearchField] = &[
+        SearchField::Name,
+        SearchField::Command,
+        SearchField::Exe,
+        SearchField::Pid,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::Name => "Name",
+            Self::Command => "Command line",
+            Self::Exe => "Executable path",
+            Self::Pid => "PID",
+        }
+    }
+}
+
+/// Matches text, either as a substring or as a regular expression.
+#[derive(Debug)]
+pub enum Matcher {
+    Substring { value: String, case_sensitive: bool },
+    Regex(Regex),
+}
+
+impl Matcher {
+    pub fn new(pattern: &str, options: SearchOptions) -> Result<Matcher, regex::Error> {
+        if options.regex {
+            RegexBuilder::new(pattern)
+                .case_insensitive(!options.case_sensitive)
+                .build()
+                .map(Self::Regex)
+        } else if options.case_sensitive {
+            Ok(Self::Substring {
+                value: pattern.to_owned(),
+                case_sensitive: true,
+            })
+        } else {
+            Ok(Self::Substring {
+                value: pattern.to_lowercase(),
+                case_sensitive: false,
+            })
+        }
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        match self {
+            Self::Substring {
+                value,
+                case_sensitive: true,
+            } => text.contains(value.as_str()),
+            Self::Substring { value, .. } => text.to_lowercase().contains(value.as_str()),
+            Self::Regex(regex) => regex.is_match(text),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextField {
+    Name,
+    Command,
+    User,
+    State,
+    Exe,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NumberField {
+    Pid,
+    ParentPid,
+    Cpu,
+    Memory,
+    VirtualMemory,
+    DiskUsage,
+    Threads,
+    Nice,
+}
+
+impl NumberField {
+    fn is_size(self) -> bool {
+        matches!(self, Self::Memory | Self::VirtualMemory | Self::DiskUsage)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Field {
+    Text(TextField),
+    Number(NumberField),
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Field> {
+        Some(match name.to_lowercase().as_str() {
+            "name" => Self::Text(TextField::Name),
+            "cmd" | "command" => Self::Text(TextField::Command),
+            "user" => Self::Text(TextField::User),
+            "state" | "status" => Self::Text(TextField::State),
+            "exe" | "path" => Self::Text(TextField::Exe),
+            "pid" => Self::Number(NumberField::Pid),
+            "ppid" | "parent" => Self::Number(NumberField::ParentPid),
+            "cpu" => Self::Number(NumberField::Cpu),
+            "mem" | "memory" => Self::Number(NumberField::Memory),
+            "vmem" | "virtual" => Self::Number(NumberField::VirtualMemory),
+            "disk" | "io" => Self::Number(NumberField::DiskUsage),
+            "threads" => Self::Number(NumberField::Threads),
+            "nice" => Self::Number(NumberField::Nice),
+            _ => return None,
+        })
+    }
+}
+
+/// Gives access to the values of a process for the filter.
+pub trait FilterRow {
+    fn text(&self, field: TextField) -> String;
+    fn number(&self, field: NumberField) -> Option<f64>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operator {
+    Contains,
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+    Regex,
+}
+
+impl Operator {
+    /// Returns the operator at the start of `s` and its length.
+    fn parse(s: &str) -> Option<(Operator, usize)> {
+        // The two characters operators need to be checked first.
+        [
+            (">=", Self::GreaterOrEqual),
+            ("<=", Self::LessOrEqual),
+            ("!=", Self::NotEqual),
+            (":", Self::Contains),
+            ("=", Self::Equal),
+            (">", Self::Greater),
+            ("<", Self::Less),
+            ("~", Self::Regex),
+        ]
+        .into_iter()
+        .find(|(op, _)| s.starts_with(op))
+        .map(|(op, operator)| (operator, op.len()))
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Contains => ":",
+            Self::Equal => "=",
+            Self::NotEqual => "!=",
+            Self::Greater => ">",
+            Self::GreaterOrEqual => ">=",
+            Self::Less => "<",
+            Self::LessOrEqual => "<=",
+            Self::Regex => "~",
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Term {
+    /// A plain word, matched against the searched fields.
+    Word {
+        matcher: Matcher,
+        fields: Vec<SearchField>,
+    },
+    Matches {
+        field: TextField,
+        matcher: Matcher,
+    },
+    Equal {
+        field: TextField,
+        value: String,
+        case_sensitive: bool,
+        negated: bool,
+    },
+    Number {
+        field: NumberField,
+        operator: Operator,
+        value: f64,
+    },
+}
+
+impl Term {
+    fn matches(&self, row: &dyn FilterRow) -> bool {
+        match self {
+            Self::Word { matcher, fields } => fields.iter().any(|field| match field {
+                SearchField::Name => matcher.is_match(&row.text(TextField::Name)),
+                SearchField::Command => matcher.is_match(&row.text(TextField::Command)),
+                SearchField::Exe => matcher.is_match(&row.text(TextField::Exe)),
+                SearchField::Pid => row
+                    .number(NumberField::Pid)
+                    .is_some_and(|pid| matcher.is_match(&pid.to_string())),
+            }),
+            Self::Matches { field, matcher } => matcher.is_match(&row.text(*field)),
+            Self::Equal {
+                field,
+                value,
+                case_sensitive,
+                negated,
+            } => {
+                let text = row.text(*field);
+                let equal = if *case_sensitive {
+                    text == *value
+                } else {
+                    text.to_lowercase() == *value
+                };
+                equal != *negated
+            }
+            Self::Number {
+                field,
+                operator,
+                value,
+            } => {
+                let Some(number) = row.number(*field) else {
+                    return false;
+                };
+                match operator {
+                    Operator::Contains | Operator::Equal => number == *value,
+                    Operator::NotEqual => number != *value,
+                    Operator::Greater => number > *value,
+                    Operator::GreaterOrEqual => number >= *value,
+                    Operator::Less => number < *value,
+                    Operator::LessOrEqual => number <= *value,
+                    Operator::Regex => false,
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Expr {
+    Term(Term),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn matches(&self, row: &dyn FilterRow) -> bool {
+        match self {
+            Self::Term(term) => term.matches(row),
+            Self::Not(expr) => !expr.matches(row),
+            Self::And(left, right) => left.matches(row) && right.matches(row),
+            Self::Or(left, right) => left.matches(row) || right.matches(row),
+        }
+    }
+}
+
+/// A parsed filter query.
+#[derive(Debug)]
+pub struct Query(Expr);
+
+impl Query {
+    /// Parses `input`. Returns `Ok(None)` if there is nothing to filter. Plain words are searched
+    /// in `fields`.
+    pub fn parse(
+        input: &str,
+        options: SearchOptions,
+        fields: &[SearchField],
+    ) -> Result<Option<Query>, ParseError> {
+        let tokens = tokenize(input)?;
+        if tokens.is_empty() {
+            return Ok(None);
+        }
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            input_len: input.chars().count(),
+            options,
+            fields,
+        };
+        let expr = parser.parse_or()?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(Some(Query(expr))),
+            Some(token) if token.kind == TokenKind::CloseParen => Err(ParseError::new(
+                "Unexpected closing parenthesis",
+                token.position,
+            )),
+            Some(token) => Err(ParseError::new("Unexpected input", token.position)),
+        }
+    }
+
+    pub fn matches(&self, row: &dyn FilterRow) -> bool {
+        self.0.matches(row)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    /// Position of the error in the query, in characters.
+    pub position: usize,
+}
+
+impl ParseError {
+    fn new<S: Into<String>>(message: S, position: usize) -> Self {
+        Self {
+            message: message.into(),
+            position,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at character {})", self.message, self.position + 1)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum TokenKind {
+    OpenParen,
+    CloseParen,
+    And,
+    Or,
+    Not,
+    Word {
+        text: String,
+        /// `true` if the word started with a quote, in which case it is never a keyword nor a
+        /// `field:value` term.
+        quoted: bool,
+    },
+}
+
+#[derive(Debug)]
+struct Token {
+    kind: TokenKind,
+    position: usize,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().enumerate().peekable();
+
+    while let Some((position, c)) = chars.next() {
+        let kind = match c {
+            c if c.is_whitespace() => continue,
+            '(' => TokenKind::OpenParen,
+            ')' => TokenKind::CloseParen,
+            _ => {
+                let quoted = c == '"';
+                let mut text = String::new();
+                let mut in_quotes = false;
+                let mut c = c;
+                loop {
+                    match c {
+                        '"' => in_quotes = !in_quotes,
+                        '\\' if in_quotes && matches!(chars.peek(), Some((_, '"' | '\\'))) => {
+                            text.extend(chars.next().map(|(_, c)| c));
+                        }
+                        c => text.push(c),
+                    }
+                    // Outside of quotes, a word ends with a whitespace or a parenthesis.
+                    match chars.next_if(|(_, next)| {
+                        in_quotes || !(next.is_whitespace() || *next == '(' || *next == ')')
+                    }) {
+                        Some((_, next)) => c = next,
+                        None => break,
+                    }
+                }
+                if in_quotes {
+                    return Err(ParseError::new("Missing closing quote", position));
+                }
+                if quoted {
+                    TokenKind::Word { text, quoted }
+                } else {
+                    match text.to_lowercase().as_str() {
+                        "and" => TokenKind::And,
+                        "or" => TokenKind::Or,
+                        "not" => TokenKind::Not,
+                        _ => TokenKind::Word { text, quoted },
+                    }
+                }
+            }
+        };
+        tokens.push(Token { kind, position });
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    input_len: usize,
+    options: SearchOptions,
+    fields: &'a [SearchField],
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.pos).map(|t| &t.kind)
+    }
+
+    fn end_position(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map(|t| t.position)
+            .unwrap_or(self.input_len)
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.parse_and()?;
+        while self.peek() == Some(&TokenKind::Or) {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.parse_unary()?;
+        loop {
+            match self.peek() {
+                Some(TokenKind::And) => self.pos += 1,
+                // Terms next to each other are implicitly combined with `AND`.
+                Some(TokenKind::Word { .. } | TokenKind::Not | TokenKind::OpenParen) => {}
+                _ => return Ok(expr),
+            }
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+        let position = self.end_position();
+        let Some(token) = self.tokens.get(self.pos) else {
+            return Err(ParseError::new("Expected a filter", position));
+        };
+        match &token.kind {
+            TokenKind::Not => {
+                self.pos += 1;
+                Ok(Expr::Not(Box::new(self.parse_unary()?)))
+            }
+            TokenKind::OpenParen => {
+                self.pos += 1;
+                if self.peek() == Some(&TokenKind::CloseParen) {
+                    return Err(ParseError::new("Empty parentheses", position));
+                }
+                let expr = self.parse_or()?;
+                if self.peek() != Some(&TokenKind::CloseParen) {
+                    return Err(ParseError::new("Missing closing parenthesis", position));
+                }
+                self.pos += 1;
+                Ok(expr)
+            }
+            TokenKind::Word { text, quoted } => {
+                let term = parse_term(text, *quoted, token.position, self.options, self.fields)?;
+                self.pos += 1;
+                Ok(Expr::Term(term))
+            }
+            TokenKind::CloseParen => {
+                Err(ParseError::new("Unexpected closing parenthesis", position))
+            }
+            TokenKind::And | TokenKind::Or => Err(ParseError::new(
+                "Expected a filter before the operator",
+                position,
+            )),
+        }
+    }
+}
+
+fn invalid_regex(value: &str, position: usize) -> ParseError {
+    ParseError::new(format!("Invalid regular expression `{value}`"), position)
+}
+
+fn parse_term(
+    text: &str,
+    quoted: bool,
+    position: usize,
+    options: SearchOptions,
+    fields: &[SearchField],
+) -> Result<Term, ParseError> {
+    let name_len = text
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .unwrap_or(text.len());
+    let name = &text[..name_len];
+    let field = match (Operator::parse(&text[name_len..]), quoted) {
+        (Some(operator), false) => Field::from_name(name).map(|field| (field, operator)),
+        _ => None,
+    };
+    // Words like `localhost:8080` only look like a field, they are searched as they are.
+    let Some((field, (operator, operator_len))) = field else {
+        return match Matcher::new(text, options) {
+            Ok(matcher) => Ok(Term::Word {
+                matcher,
+                fields: fields.to_vec(),
+            }),
+            Err(_) => Err(invalid_regex(text, position)),
+        };
+    };
+    let value = &text[name_len + operator_len..];
+    let value_position = position + text[..name_len + operator_len].chars().count();
+    if value.is_empty() {
+        return Err(ParseError::new(
+            format!("Missing value after `{name}{}`", operator.as_str()),
+            value_position,
+        ));
+    }
+    match field {
+        Field::Text(field) => match operator {
+            Operator::Contains | Operator::Regex => {
+                let options = SearchOptions {
+                    regex: options.regex || operator == Operator::Regex,
+                    ..options
+                };
+                match Matcher::new(value, options) {
+                    Ok(matcher) => Ok(Term::Matches { field, matcher }),
+                    Err(_) => Err(invalid_regex(value, value_position)),
+                }
+            }
+            Operator::Equal | Operator::NotEqual => Ok(Term::Equal {
+                field,
+                value: if options.case_sensitive {
+                    value.to_owned()
+                } else {
+                    value.to_lowercase()
+                },
+                case_sensitive: options.case_sensitive,
+                negated: operator == Operator::NotEqual,
+            }),
+            _ => Err(ParseError::new(
+                format!("`{}` cannot be used with `{name}`", operator.as_str()),
+                position + name.chars().count(),
+            )),
+        },
+        Field::Number(field) => {
+            if operator == Operator::Regex {
+                return Err(ParseError::new(
+                    format!("`~` cannot be used with `{name}`"),
+                    position + name.chars().count(),
+                ));
+            }
+            let Some(value) = parse_number(value, field) else {
+                return Err(ParseError::new(
+                    format!("Invalid number `{value}`"),
+                    value_position,
+                ));
+            };
+            Ok(Term::Number {
+                field,
+                operator,
+                value,
+            })
+        }
+    }
+}
+
+fn parse_number(value: &str, field: NumberField) -> Option<f64> {
+    let lower = value.to_lowercase();
+    let mut number = lower.as_str();
+    let mut multiplier = 1.;
+    if field == NumberField::Cpu {
+        number = number.strip_suffix('%').unwrap_or(number);
+    } else if field.is_size() {
+        // Sizes are displayed with decimal units so we use the same here.
+        number = number.strip_suffix('b').unwrap_or(number);
+        for (suffix, value) in [('k', 1e3), ('m', 1e6), ('g', 1e9), ('t', 1e12)] {
+            if let Some(n) = number.strip_suffix(suffix) {
+                number = n;
+                multiplier = value;
+                break;
+            }
+        }
+    }
+    number
+        .parse::<f64>()
+        .ok()
+        .filter(|n| n.is_finite())
+        .map(|n| n * multiplier)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Row {
+        name: &'static str,
+        cmd: &'static str,
+        user: &'static str,
+        pid: u32,
+        cpu: f32,
+        memory: u64,
+    }
+
+    impl FilterRow for Row {
+        fn text(&self, field: TextField) -> String {
+            match field {
+                TextField::Name => self.name,
+                TextField::Command => self.cmd,
+                TextField::User => self.user,
+                TextField::State => "Run",
+                TextField::Exe => "/usr/bin/java",
+            }
+            .to_owned()
+        }
+
+        fn number(&self, field: NumberField) -> Option<f64> {
+            match field {
+                NumberField::Pid => Some(self.pid as _),
+                NumberField::Cpu => Some(self.cpu as _),
+                NumberField::Memory => Some(self.memory as _),
+                _ => None,
+            }
+        }
+    }
+
+    const PYTHON: Row = Row {
+        name: "python3",
+        cmd: "python3 -m http.server --port 80",
+        user: "root",
+        pid: 1234,
+        cpu: 12.5,
+        memory: 1_500_000_000,
+    };
+    const BASH: Row = Row {
+        name: "bash",
+        cmd: "/bin/bash",
+        user: "alice",
+        pid: 42,
+        cpu: 0.,
+        memory: 5_000_000,
+    };
+
+    const DEFAULT_FIELDS: &[SearchField] = &[SearchField::Name, SearchField::Pid];
+
+    fn matches_with(
+        query: &str,
+        row: &Row,
+        options: SearchOptions,
+        fields: &[SearchField],
+    ) -> bool {
+        Query::parse(query, options, fields)
+            .expect("failed to parse")
+            .expect("empty query")
+            .matches(row)
+    }
+
+    fn matches(query: &str, row: &Row) -> bool {
+        matches_with(query, row, SearchOptions::default(), DEFAULT_FIELDS)
+    }
+
+    fn error(query: &str) -> ParseError {
+        Query::parse(query, SearchOptions::default(), DEFAULT_FIELDS)
+            .expect_err("query should not parse")
+    }
+
+    #[test]
+    fn empty_query() {
+        let parse = |query| Query::parse(query, SearchOptions::default(), DEFAULT_FIELDS);
+        assert!(parse("").unwrap().is_none());
+        assert!(parse("   ").unwrap().is_none());
+    }
+
+    #[test]
+    fn search_options() {
+        let case_sensitive = SearchOptions {
+            case_sensitive: true,
+            ..SearchOptions::default()
+        };
+        let regex = SearchOptions {
+            regex: true,
+            ..SearchOptions::default()
+        };
+        assert!(!matches_with(
+            "PYTH",
+            &PYTHON,
+            case_sensitive,
+            DEFAULT_FIELDS
+        ));
+        assert!(matches_with(
+            "pyth",
+            &PYTHON,
+            case_sensitive,
+            DEFAULT_FIELDS
+        ));
+        assert!(!matches_with(
+            "user=ROOT",
+            &PYTHON,
+            case_sensitive,
+            DEFAULT_FIELDS
+        ));
+        assert!(!matches_with(
+            "name~^PY",
+            &PYTHON,
+            case_sensitive,
+            DEFAULT_FIELDS
+        ));
+        assert!(matches_with(
+            "name~^PY",
+            &PYTHON,
+            SearchOptions::default(),
+            DEFAULT_FIELDS
+        ));
+
+        assert!(matches_with("^py.*3$", &PYTHON, regex, DEFAULT_FIELDS));
+        assert!(!matches("^py.*3$", &PYTHON));
+        assert!(matches_with("user:^r", &PYTHON, regex, DEFAULT_FIELDS));
+        assert_eq!(
+            Query::parse("py[", regex, DEFAULT_FIELDS).unwrap_err(),
+            ParseError::new("Invalid regular expression `py[`", 0),
+        );
+    }
+
+    #[test]
+    fn search_fields() {
+        let options = SearchOptions::default();
+        assert!(!matches("http.server", &PYTHON));
+        assert!(matches_with(
+            "http.server",
+            &PYTHON,
+            options,
+            &[SearchField::Command]
+        ));
+        assert!(matches_with("java", &PYTHON, options, &[SearchField::Exe]));
+        assert!(!matches_with(
+            "java",
+            &PYTHON,
+            options,
+            &[SearchField::Name]
+        ));
+        assert!(!matches_with(
+            "1234",
+            &PYTHON,
+            options,
+            &[SearchField::Name]
+        ));
+        assert!(!matches_with("python", &PYTHON, options, &[]));
+        assert!(matches_with("exe:java", &PYTHON, options, &[]));
+    }
+
+    #[test]
+    fn plain_words() {
+        assert!(matches("PYTH", &PYTHON));
+        assert!(matches("123", &PYTHON));
+        assert!(!matches("bash", &PYTHON));
+        // A quoted word is never a field.
+        assert!(!matches("\"cpu>1\"", &PYTHON));
+        // Neither is a word which doesn't start with the name of a field.
+        assert!(!matches("java:", &PYTHON));
+        assert!(!matches("localhost:8080", &PYTHON));
+        assert!(!matches(":80", &PYTHON));
+    }
+
+    #[test]
+    fn numbers() {
+        assert!(matches("cpu>10", &PYTHON));
+        assert!(!matches("cpu>10", &BASH));
+        assert!(matches("cpu<=12.5%", &PYTHON));
+        assert!(matches("pid:1234", &PYTHON));
+        assert!(!matches("pid:123", &PYTHON));
+        assert!(matches("pid!=1", &PYTHON));
+        assert!(matches("mem>1G", &PYTHON));
+        assert!(matches("mem<500MB", &BASH));
+        assert!(matches("mem>=1.5g", &PYTHON));
+        // Missing values never match.
+        assert!(!matches("nice<100", &PYTHON));
+    }
+
+    #[test]
+    fn text_fields() {
+        assert!(matches("user:root", &PYTHON));
+        assert!(matches("user=ROOT", &PYTHON));
+        assert!(!matches("user=roo", &PYTHON));
+        assert!(matches("user!=alice", &PYTHON));
+        assert!(matches("name~^python", &PYTHON));
+        assert!(!matches("name~^python$", &PYTHON));
+        assert!(matches("cmd:\"--port 80\"", &PYTHON));
+        assert!(matches("command:http.server", &PYTHON));
+    }
+
+    #[test]
+    fn boolean_operators() {
+        let query = "name~^python mem>1G";
+        assert!(matches(query, &PYTHON));
+        assert!(!matches(query, &BASH));
+        assert!(matches("user:alice OR user:root", &PYTHON));
+        assert!(matches("user:alice or user:root", &BASH));
+        assert!(matches("NOT user:alice", &PYTHON));
+        assert!(!matches("not user:alice", &BASH));
+        assert!(matches("bash AND NOT cpu>1", &BASH));
+        // `AND` has a higher precedence than `OR`.
+        assert!(matches("user:root OR user:nobody cpu>100", &PYTHON));
+        assert!(!matches("(user:root OR user:nobody) cpu>100", &PYTHON));
+        assert!(matches("NOT (bash OR cpu>100)", &PYTHON));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            error("cpu>10 mem>lots"),
+            ParseError::new("Invalid number `lots`", 11)
+        );
+        assert_eq!(
+            error("name>1"),
+            ParseError::new("`>` cannot be used with `name`", 4)
+        );
+        assert_eq!(
+            error("cpu~1"),
+            ParseError::new("`~` cannot be used with `cpu`", 3)
+        );
+        assert_eq!(
+            error("name~\"(python\""),
+            ParseError::new("Invalid regular expression `(python`", 5)
+        );
+        assert_eq!(
+            error("user:"),
+            ParseError::new("Missing value after `user:`", 5)
+        );
+        assert_eq!(
+            error("cmd:\"--port 80"),
+            ParseError::new("Missing closing quote", 0)
+        );
+        assert_eq!(
+            error("(bash"),
+            ParseError::new("Missing closing parenthesis", 0)
+        );
+        assert_eq!(
+            error("bash)"),
+            ParseError::new("Unexpected closing parenthesis", 4)
+        );
+        assert_eq!(error("()"), ParseError::new("Empty parentheses", 0));
+        assert_eq!(error("bash OR"), ParseError::new("Expected a filter", 7));
+        assert_eq!(
+            error("OR bash"),
+            ParseError::new("Expected a filter before the operator", 0)
+        );
+        assert_eq!(error("NOT"), ParseError::new("Expected a filter", 3));
+    }
+}
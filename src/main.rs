@@ -14,94 +14,247 @@ use gtk::prelude::*;
 use gtk::{gdk, gdk_pixbuf, gio, glib};
 use gtk::{AboutDialog, Dialog, Entry, MessageDialog};
 
-use sysinfo::{Networks, Pid, ProcessesToUpdate, RefreshKind};
+use sysinfo::{
+    CpuRefreshKind, Pid, ProcessRefreshKind, ProcessesToUpdate, RefreshKind, UpdateKind,
+};
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::{HashMap, HashSet};
 #[cfg(unix)]
 use std::os::unix::process::CommandExt;
 use std::process::{Command, Stdio};
 use std::rc::Rc;
-use std::sync::{Arc, Mutex};
-use std::thread;
-use std::time::Duration;
 
+mod affinity;
+mod collector;
 mod color;
 mod display_disk;
 #[macro_use]
 mod display_sysinfo;
 mod display_network;
 mod display_procs;
+mod display_users;
+mod filter;
 mod graph;
+mod memory_maps;
 mod network_dialog;
 mod notebook;
+mod open_files;
+mod pins;
+mod priority;
 mod process_dialog;
+mod process_index;
+mod process_row;
+mod property_list;
 mod settings;
+mod signals;
+mod sockets;
+#[cfg(test)]
+mod test_support;
+mod threads;
 mod utils;
 
+use collector::{Collector, ProcessInfo, ProcessRefresh, ProcessTable, Refreshes, Snapshot};
 use display_network::Network;
-use display_procs::{create_and_fill_model, Procs};
+use display_procs::{
+    append_rows, create_group_row, create_process_row, row_fingerprint, update_expander,
+    update_group_row, update_process_row, ProcessExtra, ProcessModel, Procs,
+};
 use display_sysinfo::DisplaySysInfo;
+use display_users::UsersSummary;
+use pins::Pins;
+use process_index::{compute_subtree_totals, Parent, ProcessEntry, ProcessIndex, ViewMode};
+use process_row::ProcessRow;
 use settings::Settings;
-use utils::format_number;
+use utils::UsersCache;
 
 pub const APPLICATION_NAME: &str = "fr.guillaume_gomez.ProcessViewer";
 
-fn update_window(list: &gtk::ListStore, entries: &HashMap<Pid, sysinfo::Process>) {
-    let mut seen: HashSet<Pid> = HashSet::new();
-
-    if let Some(iter) = list.iter_first() {
-        let mut valid = true;
-        while valid {
-            let pid = match list.get_value(&iter, 0).get::<u32>() {
-                Ok(pid) => Pid::from_u32(pid),
-                _ => {
-                    valid = list.iter_next(&iter);
-                    continue;
-                }
-            };
-            if let Some(p) = entries.get(&(pid)) {
-                let disk_usage = p.disk_usage();
-                let disk_usage = disk_usage.written_bytes + disk_usage.read_bytes;
-                let memory = p.memory();
-                list.set(
-                    &iter,
-                    &[
-                        (2, &format!("{:.1}", p.cpu_usage())),
-                        (3, &format_number(memory)),
-                        (
-                            4,
-                            &if disk_usage > 0 {
-                                format_number(disk_usage)
-                            } else {
-                                String::new()
-                            },
-                        ),
-                        (6, &p.cpu_usage()),
-                        (7, &memory),
-                        (8, &disk_usage),
-                    ],
-                );
-                valid = list.iter_next(&iter);
-                seen.insert(pid);
-            } else {
-                valid = list.remove(&iter);
+struct UpdateContext<'a> {
+    totals: HashMap<Pid, (f32, u64)>,
+    users: &'a mut UsersCache,
+    pins: &'a Pins,
+}
+
+impl UpdateContext<'_> {
+    fn extra(&mut self, p: &ProcessInfo) -> ProcessExtra {
+        let (subtree_cpu, subtree_memory) = self
+            .totals
+            .get(&p.pid)
+            .copied()
+            .unwrap_or((p.cpu_usage, p.memory));
+        ProcessExtra {
+            subtree_cpu,
+            subtree_memory,
+            user: p
+                .user_id
+                .as_ref()
+                .map(|uid| self.users.name(uid))
+                .unwrap_or_default(),
+            pinned: self.pins.is_pinned(p.pid, &p.group_name()),
+        }
+    }
+}
+
+/// Returns the list of the rows displayed under `parent`, or `None` if it isn't displayed
+/// anymore.
+fn children_store(
+    model: &ProcessModel,
+    index: &ProcessIndex<ProcessRow>,
+    parent: Option<&Parent>,
+) -> Option<gio::ListStore> {
+    match parent {
+        None => Some(model.root.clone()),
+        Some(Parent::Process(pid)) => index.row(*pid).map(ProcessRow::children),
+        Some(Parent::Group(name)) => index.group(name).map(ProcessRow::children),
+    }
+}
+
+fn update_window(
+    model: &ProcessModel,
+    entries: &ProcessTable,
+    view_mode: ViewMode,
+    users: &mut UsersCache,
+    pins: &Pins,
+) {
+    let tree_mode = view_mode == ViewMode::Tree;
+    let mut ctx = UpdateContext {
+        totals: if tree_mode {
+            compute_subtree_totals(entries, tree_mode)
+        } else {
+            HashMap::new()
+        },
+        users,
+        pins,
+    };
+    let mut index = model.index.borrow_mut();
+    let changes = index.diff(entries, view_mode, |p| row_fingerprint(p, &ctx.extra(p)));
+    let rows_changed = !changes.removed.is_empty()
+        || !changes.removed_groups.is_empty()
+        || !changes.added.is_empty();
+    let mut changed = HashSet::new();
+    // The rows whose children changed, they might need to be expanded or not anymore.
+    let mut touched = HashSet::new();
+
+    // The rows are removed from each list at once.
+    let mut removed: HashMap<Option<Parent>, HashSet<ProcessRow>> = HashMap::new();
+    for (row, parent) in changes.removed {
+        removed.entry(parent).or_default().insert(row);
+    }
+    removed
+        .entry(None)
+        .or_default()
+        .extend(changes.removed_groups);
+    for (parent, rows) in removed {
+        // If the parent was removed too, its children went away with it.
+        if let Some(store) = children_store(model, &index, parent.as_ref()) {
+            store.retain(|item| {
+                !item
+                    .downcast_ref::<ProcessRow>()
+                    .is_some_and(|row| rows.contains(row))
+            });
+        }
+        touched.insert(parent);
+    }
+
+    for (row, p) in changes.updated.iter() {
+        let extra = ctx.extra(p);
+        update_process_row(row, p, &extra, &mut changed);
+    }
+
+    let mut new_groups = Vec::new();
+    for (name, processes) in changes.groups.iter() {
+        let row = match index.group(name) {
+            Some(row) => row.clone(),
+            None => {
+                let exe = processes
+                    .iter()
+                    .find_map(|p| p.exe.as_ref())
+                    .map(|exe| exe.display().to_string())
+                    .unwrap_or_default();
+                let row = create_group_row(name, &exe);
+                index.insert_group(name.clone(), row.clone());
+                new_groups.push(row.clone());
+                row
+            }
+        };
+        update_group(&mut ctx, &row, processes, &mut changed);
+    }
+
+    // The new rows are added to each list at once. Parents come first in `added`, so the rows
+    // of a list which are displayed under a new row are created before it.
+    let mut added: Vec<(Option<Parent>, Vec<ProcessRow>)> = Vec::new();
+    let mut added_positions = HashMap::new();
+    for (p, mut parent) in changes.added {
+        if let Some(Parent::Process(pid)) = parent {
+            // The row of the parent couldn't be created.
+            if index.row(pid).is_none() {
+                parent = None;
             }
         }
+        let extra = ctx.extra(p);
+        let Some(row) = create_process_row(p, &extra) else {
+            continue;
+        };
+        index.insert(p, parent.clone(), row.clone());
+        let pos = *added_positions.entry(parent.clone()).or_insert_with(|| {
+            added.push((parent, Vec::new()));
+            added.len() - 1
+        });
+        added[pos].1.push(row);
+    }
+    // The children of a new row need to be added before it, for it to be expandable.
+    for (parent, rows) in added.into_iter().rev() {
+        if let Some(store) = children_store(model, &index, parent.as_ref()) {
+            append_rows(&store, &rows);
+        }
+        touched.insert(parent);
+    }
+    append_rows(&model.root, &new_groups);
+
+    for parent in touched.into_iter().flatten() {
+        let (row, store) = match parent {
+            Parent::Process(pid) => (
+                index.row(pid),
+                children_store(model, &index, index.parent_of(pid)),
+            ),
+            Parent::Group(ref name) => (index.group(name), Some(model.root.clone())),
+        };
+        if let (Some(row), Some(store)) = (row, store) {
+            update_expander(&store, row);
+        }
     }
 
-    for (pid, pro) in entries.iter() {
-        if !seen.contains(pid) {
-            create_and_fill_model(
-                list,
-                pid.as_u32(),
-                pro.cmd(),
-                pro.name(),
-                pro.cpu_usage(),
-                pro.memory(),
-            );
+    model.values_changed(&changed, rows_changed);
+}
+
+/// Updates the row of an application with its `processes`. The properties which changed are added
+/// to `changed`.
+fn update_group(
+    ctx: &mut UpdateContext<'_>,
+    row: &ProcessRow,
+    processes: &[&ProcessInfo],
+    changed: &mut HashSet<&'static str>,
+) {
+    let mut user: Option<String> = None;
+    let mut same_user = true;
+    for p in processes {
+        let name = p.user_id.as_ref().map(|uid| ctx.users.name(uid));
+        match user {
+            None => user = name,
+            Some(ref user) if name.as_ref() != Some(user) => same_user = false,
+            _ => {}
         }
     }
+    let user = if same_user {
+        user.unwrap_or_default()
+    } else {
+        String::new()
+    };
+    let pinned = processes
+        .iter()
+        .any(|p| ctx.pins.is_pinned(p.pid, &p.group_name()));
+    update_group_row(row, processes, &user, pinned, changed)
 }
 
 fn parse_quote(line: &str, quote: char) -> Vec<String> {
@@ -195,230 +348,194 @@ fn run_command<T: IsA<gtk::Window>>(input: &Entry, window: &T, d: &Dialog) {
     m.show();
 }
 
-fn create_new_proc_diag(
-    process_dialogs: &Rc<RefCell<Vec<process_dialog::ProcDialog>>>,
-    pid: Pid,
-    sys: &sysinfo::System,
-) {
-    if let Some(proc_diag) = process_dialogs
+fn create_new_proc_diag(rfs: &RequiredForSettings, pid: Pid) {
+    let processes = rfs.processes.borrow();
+    let Some(process) = processes.get(&pid) else {
+        return;
+    };
+    // A dialog opened for a previous process with the same PID isn't reused.
+    if let Some(proc_diag) = rfs
+        .process_dialogs
         .borrow()
         .iter()
         .filter(|x| !x.is_dead)
-        .find(|x| x.pid == pid)
+        .find(|x| x.is_same_process(process))
     {
         proc_diag.popup.present();
         return;
     }
-    let total_memory = sys.total_memory();
-    if let Some(process) = sys.process(pid) {
-        process_dialogs
-            .borrow_mut()
-            .push(process_dialog::create_process_dialog(process, total_memory));
-    }
+    let mut sys = rfs.sys.borrow_mut();
+    sys.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        true,
+        ProcessRefreshKind::everything(),
+    );
+    let Some(details) = sys.process(pid) else {
+        return;
+    };
+    let dialog = process_dialog::create_process_dialog(process, details, rfs.total_memory);
+    rfs.process_dialogs.borrow_mut().push(dialog);
+    // The collector needs to refresh the processes for the dialog.
+    (rfs.update_refreshes)();
 }
 
-#[derive(Clone)]
-pub struct RequiredForSettings {
-    process_refresh_timeout: Arc<Mutex<u32>>,
-    network_refresh_timeout: Arc<Mutex<u32>>,
-    system_refresh_timeout: Arc<Mutex<u32>>,
-    sys: Arc<Mutex<sysinfo::System>>,
-    process_dialogs: Rc<RefCell<Vec<process_dialog::ProcDialog>>>,
-    list_store: gtk::ListStore,
-    display_tab: Rc<RefCell<DisplaySysInfo>>,
-    network_tab: Rc<RefCell<Network>>,
+/// What is displayed, apart from what the collector already knows about.
+#[derive(Clone, Copy, Debug, Default)]
+struct Displayed {
+    processes_list: bool,
+    users_tab: bool,
+    /// The users tab samples its history: while it is displayed, or in the background if the
+    /// settings ask for it.
+    users_history: bool,
 }
 
-fn setup_timeout(rfs: &RequiredForSettings) {
-    let (sender, receiver) = async_channel::bounded(1);
+/// Computes what the collector needs to refresh again and sends it, which is needed whenever
+/// what is displayed changes.
+type UpdateRefreshes = Rc<dyn Fn() -> Displayed>;
 
-    let sys = &rfs.sys;
-    let process_dialogs = &rfs.process_dialogs;
-    let list_store = &rfs.list_store;
-    let process_refresh_timeout = &rfs.process_refresh_timeout;
-
-    thread::spawn(glib::clone!(
-        #[weak]
-        sys,
-        #[weak]
-        process_refresh_timeout,
-        move || {
-            loop {
-                let sleep_dur = Duration::from_millis(
-                    *process_refresh_timeout
-                        .lock()
-                        .expect("failed to lock process refresh mutex") as _,
-                );
-                thread::sleep(sleep_dur);
-                sys.lock()
-                    .expect("failed to lock to refresh processes")
-                    .refresh_processes(ProcessesToUpdate::All, false);
-                sender
-                    .send_blocking(())
-                    .expect("failed to send data through process refresh channel");
-            }
-        }
-    ));
-
-    glib::spawn_future_local(glib::clone!(
-        #[weak]
-        sys,
+fn create_update_refreshes(
+    collector: &Collector,
+    stack: &gtk::Stack,
+    procs: &Procs,
+    process_dialogs: &Rc<RefCell<Vec<process_dialog::ProcDialog>>>,
+    network_tab: &Rc<RefCell<Network>>,
+    settings: &Rc<RefCell<Settings>>,
+) -> UpdateRefreshes {
+    Rc::new(glib::clone!(
+        #[strong]
+        collector,
         #[weak]
-        list_store,
+        stack,
+        #[strong(rename_to = columns)]
+        procs.columns,
+        #[weak(rename_to = search_bar)]
+        procs.search_bar,
         #[weak]
         process_dialogs,
-        async move {
-            loop {
-                match receiver.recv().await {
-                    Ok(_) => {}
-                    Err(error) => {
-                        eprintln!("Stopping process info update loop: {error:?}");
-                        return;
-                    }
-                }
-                // first part, deactivate sorting
-                let sorted = TreeSortableExtManual::sort_column_id(&list_store);
-                list_store.set_unsorted();
-
-                let mut dialogs = process_dialogs.borrow_mut();
-
-                if let Ok(sys) = sys.lock() {
-                    // we update the tree view
-                    update_window(&list_store, sys.processes());
-
-                    // we re-enable the sorting
-                    if let Some((col, order)) = sorted {
-                        list_store.set_sort_column_id(col, order);
-                    }
-                    for dialog in dialogs.iter_mut().filter(|x| !x.is_dead) {
-                        // TODO: check if the process name matches the PID too!
-                        if let Some(process) = sys.processes().get(&dialog.pid) {
-                            dialog.update(process);
-                        } else {
-                            dialog.set_dead();
-                        }
-                    }
-                    dialogs.retain(|x| !x.need_remove());
-                }
-            }
-        }
-    ));
-}
-
-fn setup_network_timeout(rfs: &RequiredForSettings, networks: Arc<Mutex<Networks>>) {
-    let (sender, receiver) = async_channel::bounded(1);
-
-    let network_refresh_timeout = &rfs.network_refresh_timeout;
-    let network_tab = &rfs.network_tab;
-
-    thread::spawn(glib::clone!(
         #[weak]
-        networks,
+        network_tab,
         #[weak]
-        network_refresh_timeout,
+        settings,
+        #[upgrade_or_default]
         move || {
-            loop {
-                let sleep_dur = Duration::from_millis(
-                    *network_refresh_timeout
-                        .lock()
-                        .expect("failed to lock networks refresh mutex") as _,
-                );
-                thread::sleep(sleep_dur);
-                networks
-                    .lock()
-                    .expect("failed to lock to refresh networks")
-                    .refresh(true);
-                sender
-                    .send_blocking(())
-                    .expect("failed to send data through networks refresh channel");
+            let tab = stack.visible_child_name();
+            let is_visible = |name: &str| tab.as_deref() == Some(name);
+            let users_tab = is_visible("Users");
+            let displayed = Displayed {
+                processes_list: is_visible("Processes"),
+                users_tab,
+                users_history: users_tab || settings.borrow().users_history_in_background,
+            };
+            let has_dialogs = process_dialogs
+                .borrow()
+                .iter()
+                .any(|dialog| !dialog.is_dead && !dialog.need_remove());
+
+            let mut processes = displayed
+                .processes_list
+                .then(|| display_procs::process_refresh(&columns, &search_bar));
+            if displayed.users_history || has_dialogs {
+                let refresh = processes.unwrap_or(ProcessRefresh {
+                    kind: ProcessRefreshKind::nothing().with_tasks(),
+                    priorities: false,
+                    memory_details: false,
+                });
+                processes = Some(ProcessRefresh {
+                    kind: refresh.kind.with_cpu().with_memory().with_disk_usage(),
+                    ..refresh
+                });
+            }
+            if displayed.users_history {
+                processes = processes.map(|refresh| ProcessRefresh {
+                    kind: refresh.kind.with_user(UpdateKind::OnlyIfNotSet),
+                    ..refresh
+                });
             }
+            collector.set_refreshes(Refreshes {
+                processes,
+                system: is_visible("System"),
+                networks: is_visible("Networks") || network_tab.borrow().has_dialogs(),
+            });
+            displayed
         }
-    ));
+    ))
+}
 
-    glib::spawn_future_local(glib::clone!(
-        #[weak]
-        network_tab,
-        async move {
-            loop {
-                match receiver.recv().await {
-                    Ok(_) => {}
-                    Err(error) => {
-                        eprintln!("Stopping network info update loop: {error:?}");
-                        return;
-                    }
-                }
-                network_tab
-                    .borrow_mut()
-                    .update_networks(&networks.lock().expect("failed to lock to update networks"));
-            }
+#[derive(Clone)]
+pub struct RequiredForSettings {
+    collector: Collector,
+    update_refreshes: UpdateRefreshes,
+    /// The last snapshot of the processes.
+    processes: Rc<RefCell<ProcessTable>>,
+    total_memory: u64,
+    /// Only refreshed when an action needs it, like sending a signal. The snapshots are used
+    /// otherwise.
+    sys: Rc<RefCell<sysinfo::System>>,
+    process_dialogs: Rc<RefCell<Vec<process_dialog::ProcDialog>>>,
+    process_model: ProcessModel,
+    process_view_mode: Rc<Cell<ViewMode>>,
+    users: Rc<RefCell<UsersCache>>,
+    pins: Rc<RefCell<Pins>>,
+    display_tab: Rc<RefCell<DisplaySysInfo>>,
+    network_tab: Rc<RefCell<Network>>,
+    users_tab: Rc<RefCell<UsersSummary>>,
+}
+
+fn update_processes(rfs: &RequiredForSettings, processes: ProcessTable) {
+    // Dialogs could have been closed since the last update.
+    let displayed = (rfs.update_refreshes)();
+    if displayed.processes_list {
+        update_window(
+            &rfs.process_model,
+            &processes,
+            rfs.process_view_mode.get(),
+            &mut rfs.users.borrow_mut(),
+            &rfs.pins.borrow(),
+        );
+    }
+    if displayed.users_history {
+        rfs.users_tab.borrow_mut().update(
+            &processes,
+            &mut rfs.users.borrow_mut(),
+            displayed.users_tab,
+        );
+    }
+    let mut dialogs = rfs.process_dialogs.borrow_mut();
+    for dialog in dialogs.iter_mut().filter(|x| !x.is_dead) {
+        match processes.get(&dialog.pid) {
+            Some(process) if dialog.is_same_process(process) => dialog.update(process),
+            // Either the process ended or its PID was reused by a new process.
+            _ => dialog.set_dead(),
         }
-    ));
+    }
+    dialogs.retain(|x| !x.need_remove());
+    *rfs.processes.borrow_mut() = processes;
 }
 
-fn setup_system_timeout(
+/// Displays the snapshots sent by the collector, until it stops.
+fn setup_updates(
     rfs: &RequiredForSettings,
     settings: &Rc<RefCell<Settings>>,
-    components: sysinfo::Components,
+    snapshots: async_channel::Receiver<Snapshot>,
 ) {
-    let (sender, receiver) = async_channel::bounded(1);
-
-    let system_refresh_timeout = &rfs.system_refresh_timeout;
-    let sys = &rfs.sys;
-    let display_tab = &rfs.display_tab;
-    let components = Arc::new(Mutex::new(components));
-
-    thread::spawn(glib::clone!(
-        #[weak]
-        sys,
-        #[weak]
-        system_refresh_timeout,
-        #[weak]
-        components,
-        move || {
-            loop {
-                let sleep_dur = Duration::from_millis(
-                    *system_refresh_timeout
-                        .lock()
-                        .expect("failed to lock system refresh mutex") as _,
-                );
-                thread::sleep(sleep_dur);
-                sys.lock()
-                    .expect("failed to lock to refresh system")
-                    .refresh_all();
-                components
-                    .lock()
-                    .expect("failed to lock components")
-                    .refresh(true);
-                sender
-                    .send_blocking(())
-                    .expect("failed to send data through system refresh channel");
-            }
-        }
-    ));
-
     glib::spawn_future_local(glib::clone!(
-        #[weak]
-        sys,
-        #[weak]
-        display_tab,
+        #[strong]
+        rfs,
         #[weak]
         settings,
         async move {
-            loop {
-                match receiver.recv().await {
-                    Ok(_) => {}
-                    Err(error) => {
-                        eprintln!("Stopping system info update loop: {error:?}");
-                        return;
+            while let Ok(snapshot) = snapshots.recv().await {
+                match snapshot {
+                    Snapshot::Processes(processes) => update_processes(&rfs, processes),
+                    Snapshot::System(system) => {
+                        let mut info = rfs.display_tab.borrow_mut();
+                        info.update_system_info(&system, settings.borrow().display_fahrenheit);
+                        info.update_system_info_display(&system);
+                    }
+                    Snapshot::Networks(networks) => {
+                        rfs.network_tab.borrow_mut().update_networks(networks)
                     }
                 }
-                let mut info = display_tab.borrow_mut();
-                let sys = sys.lock().expect("failed to lock to update system");
-                let components = components.lock().expect("failed to lock components");
-                let display_fahrenheit = settings.borrow().display_fahrenheit;
-
-                info.update_system_info(&sys, &components, display_fahrenheit);
-                info.update_system_info_display(&sys);
             }
         }
     ));
@@ -471,35 +588,39 @@ fn build_ui(application: &gtk::Application) {
     let (header_bar, search_filter_button) = create_header_bar(&stack);
     window.set_titlebar(Some(&header_bar));
 
-    let mut sys = sysinfo::System::new_with_specifics(RefreshKind::everything());
-    let procs = Procs::new(sys.processes(), &stack);
-    let current_pid = Rc::clone(&procs.current_pid);
+    let settings = Rc::new(RefCell::new(Settings::load()));
+    let (collector, initial, snapshots) = Collector::start(&settings.borrow());
+    // The CPUs are listed for the affinity dialog.
+    let sys = Rc::new(RefCell::new(sysinfo::System::new_with_specifics(
+        RefreshKind::nothing().with_cpu(CpuRefreshKind::nothing()),
+    )));
+    let procs = Procs::new(&stack, &settings);
+    let users = Rc::new(RefCell::new(UsersCache::new()));
+    let pins = Rc::new(RefCell::new(Pins::new(&settings.borrow().pinned_processes)));
+    let selected_pids = Rc::clone(&procs.selected_pids);
     let info_button = procs.info_button.clone();
 
     window.set_default_size(630, 700);
 
-    sys.refresh_all();
-    let sys = Arc::new(Mutex::new(sys));
-    procs.kill_button.connect_clicked(glib::clone!(
-        #[weak]
-        current_pid,
-        #[weak]
-        sys,
-        move |_| {
-            let sys = sys.lock().expect("failed to lock to kill a process");
-            if let Some(process) = current_pid.get().and_then(|pid| sys.process(pid)) {
-                process.kill();
-            }
-        }
-    ));
-
-    let settings = Settings::load();
-    let sys_components = sysinfo::Components::new_with_refreshed_list();
-    let display_tab = DisplaySysInfo::new(&sys, &sys_components, &stack, &settings);
-
-    let settings = Rc::new(RefCell::new(settings));
-    let networks = Arc::new(Mutex::new(Networks::new_with_refreshed_list()));
-    let network_tab = Rc::new(RefCell::new(Network::new(&stack, &networks)));
+    update_window(
+        &procs.model,
+        &initial.processes,
+        procs.view_mode.get(),
+        &mut users.borrow_mut(),
+        &pins.borrow(),
+    );
+    let total_memory = initial.system.total_memory;
+    let mut users_tab = UsersSummary::new(&stack, total_memory);
+    users_tab.update(&initial.processes, &mut users.borrow_mut(), true);
+    let users_tab = Rc::new(RefCell::new(users_tab));
+
+    let display_tab = DisplaySysInfo::new(&initial.system, &stack, &settings.borrow());
+
+    let network_tab = Rc::new(RefCell::new(Network::new(
+        &stack,
+        initial.networks,
+        &settings,
+    )));
     display_disk::create_disk_info(&stack);
 
     let display_tab = Rc::new(RefCell::new(display_tab));
@@ -508,22 +629,111 @@ fn build_ui(application: &gtk::Application) {
 
     let process_dialogs: Rc<RefCell<Vec<process_dialog::ProcDialog>>> =
         Rc::new(RefCell::new(Vec::new()));
-    let list_store = procs.list_store.clone();
+    let update_refreshes = create_update_refreshes(
+        &collector,
+        &stack,
+        &procs,
+        &process_dialogs,
+        &network_tab,
+        &settings,
+    );
+    // Only what is displayed is refreshed.
+    update_refreshes();
+    stack.connect_visible_child_notify(glib::clone!(
+        #[strong]
+        update_refreshes,
+        move |_| {
+            update_refreshes();
+        }
+    ));
+    procs
+        .search_bar
+        .connect_search_mode_enabled_notify(glib::clone!(
+            #[strong]
+            update_refreshes,
+            move |_| {
+                update_refreshes();
+            }
+        ));
+    for column in procs.columns.iter() {
+        column.connect_visible_notify(glib::clone!(
+            #[strong]
+            update_refreshes,
+            move |_| {
+                update_refreshes();
+            }
+        ));
+    }
 
     let rfs = RequiredForSettings {
-        process_refresh_timeout: Arc::new(Mutex::new(settings.borrow().refresh_processes_rate)),
-        network_refresh_timeout: Arc::new(Mutex::new(settings.borrow().refresh_network_rate)),
-        system_refresh_timeout: Arc::new(Mutex::new(settings.borrow().refresh_system_rate)),
+        collector: collector.clone(),
+        update_refreshes,
+        processes: Rc::new(RefCell::new(initial.processes)),
+        total_memory,
         sys: sys.clone(),
         process_dialogs: process_dialogs.clone(),
-        list_store,
+        process_model: procs.model.clone(),
+        process_view_mode: procs.view_mode.clone(),
+        users: users.clone(),
+        pins: pins.clone(),
         display_tab,
         network_tab: network_tab.clone(),
+        users_tab,
     };
 
-    setup_timeout(&rfs);
-    setup_network_timeout(&rfs, networks);
-    setup_system_timeout(&rfs, &settings, sys_components);
+    setup_updates(&rfs, &settings, snapshots);
+
+    procs.view_mode_button.connect_selected_notify(glib::clone!(
+        #[strong(rename_to = processes)]
+        rfs.processes,
+        #[weak]
+        settings,
+        #[strong(rename_to = view_mode)]
+        procs.view_mode,
+        #[strong(rename_to = columns)]
+        procs.columns,
+        #[strong(rename_to = model)]
+        procs.model,
+        #[weak]
+        users,
+        #[weak]
+        pins,
+        move |button| {
+            let Some(new_mode) = ViewMode::ALL.get(button.selected() as usize).copied() else {
+                return;
+            };
+            view_mode.set(new_mode);
+            display_procs::update_columns_visibility(
+                &columns,
+                &settings.borrow().process_columns,
+                new_mode,
+            );
+            // All rows are created again at their new place.
+            model.clear();
+            model.set_view_mode(new_mode);
+            update_window(
+                &model,
+                &processes.borrow(),
+                new_mode,
+                &mut users.borrow_mut(),
+                &pins.borrow(),
+            );
+
+            // We update the setting and save it!
+            settings.borrow_mut().process_view_mode = new_mode;
+            settings.borrow().save();
+        }
+    ));
+
+    // Some settings (like the columns' width) are only saved when the application is closed.
+    application.connect_shutdown(glib::clone!(
+        #[weak]
+        settings,
+        move |_| {
+            collector.stop();
+            settings.borrow().save();
+        }
+    ));
 
     let settings_action = gio::SimpleAction::new("settings", None);
     settings_action.connect_activate(glib::clone!(
@@ -538,41 +748,252 @@ fn build_ui(application: &gtk::Application) {
 
     info_button.connect_clicked(glib::clone!(
         #[weak]
-        current_pid,
+        selected_pids,
+        #[strong]
+        rfs,
+        move |_| {
+            for pid in selected_pids.borrow().iter() {
+                create_new_proc_diag(&rfs, *pid);
+            }
+        }
+    ));
+
+    procs.column_view.connect_activate(glib::clone!(
+        #[strong]
+        rfs,
+        move |column_view, position| {
+            let Some(item) = column_view.model().and_then(|model| model.item(position)) else {
+                return;
+            };
+            let Some(list_row) = item.downcast_ref::<gtk::TreeListRow>() else {
+                return;
+            };
+            let Some(row) = list_row.item().and_downcast::<ProcessRow>() else {
+                return;
+            };
+            // Activating an application shows or hides its processes.
+            if row.is_group() {
+                list_row.set_expanded(!list_row.is_expanded());
+                return;
+            }
+            create_new_proc_diag(&rfs, Pid::from_u32(row.pid()));
+        }
+    ));
+
+    procs.kill_button.connect_clicked(glib::clone!(
         #[weak]
-        process_dialogs,
+        selected_pids,
         #[weak]
         sys,
+        #[weak]
+        settings,
+        #[weak]
+        window,
         move |_| {
-            if let Some(pid) = current_pid.get() {
-                create_new_proc_diag(
-                    &process_dialogs,
-                    pid,
-                    &sys.lock()
-                        .expect("failed to lock to create new proc dialog"),
+            if !selected_pids.borrow().is_empty() {
+                signals::end_task(
+                    &window,
+                    &sys,
+                    &selected_pids.borrow(),
+                    settings.borrow().termination_grace_period,
                 );
             }
         }
     ));
 
-    procs.left_tree.connect_row_activated(glib::clone!(
+    let send_signal = gio::SimpleAction::new("send-signal", Some(glib::VariantTy::STRING));
+    send_signal.connect_activate(glib::clone!(
+        #[weak]
+        selected_pids,
         #[weak]
         sys,
-        move |tree_view, path, _| {
-            let model = tree_view.model().expect("couldn't get model");
-            let iter = model.iter(path).expect("couldn't get iter");
-            let pid = model
-                .get_value(&iter, 0)
-                .get::<u32>()
-                .expect("Model::get failed");
-            create_new_proc_diag(
-                &process_dialogs,
-                Pid::from_u32(pid),
-                &sys.lock()
-                    .expect("failed to lock to create new proc dialog (from tree)"),
+        move |_, parameter| {
+            let Some(name) = parameter.and_then(|p| p.str()) else {
+                return;
+            };
+            let Some(signal) = signals::signal_from_name(name) else {
+                return;
+            };
+            let mut sys = sys.borrow_mut();
+            let results = selected_pids
+                .borrow()
+                .iter()
+                .map(|pid| (*pid, signals::send_signal(&mut sys, *pid, signal)))
+                .collect::<Vec<_>>();
+            signals::show_results(&format!("Sent {name}"), &results);
+        }
+    ));
+
+    let send_signal_number = gio::SimpleAction::new("send-signal-number", None);
+    send_signal_number.connect_activate(glib::clone!(
+        #[weak]
+        selected_pids,
+        #[weak]
+        window,
+        move |_, _| {
+            let pids = selected_pids.borrow().clone();
+            if pids.is_empty() {
+                return;
+            }
+            signals::ask_signal_number(&window, move |signal| {
+                let results = pids
+                    .iter()
+                    .map(|pid| (*pid, signals::send_signal_number(*pid, signal)))
+                    .collect::<Vec<_>>();
+                signals::show_results(&format!("Sent signal {signal}"), &results);
+            });
+        }
+    ));
+
+    let signal_process_tree = gio::SimpleAction::new("signal-process-tree", None);
+    signal_process_tree.connect_activate(glib::clone!(
+        #[weak]
+        selected_pids,
+        #[weak]
+        sys,
+        #[weak]
+        window,
+        move |_, _| {
+            if !selected_pids.borrow().is_empty() {
+                signals::signal_process_trees(&window, &sys, &selected_pids.borrow());
+            }
+        }
+    ));
+
+    let change_priority = gio::SimpleAction::new("change-priority", None);
+    change_priority.connect_activate(glib::clone!(
+        #[weak]
+        selected_pids,
+        #[weak]
+        window,
+        move |_, _| {
+            priority::show_priority_dialog(&window, selected_pids.borrow().clone());
+        }
+    ));
+
+    let cpu_affinity = gio::SimpleAction::new("cpu-affinity", None);
+    cpu_affinity.connect_activate(glib::clone!(
+        #[weak]
+        selected_pids,
+        #[weak]
+        sys,
+        #[weak]
+        window,
+        move |_, _| {
+            affinity::show_affinity_dialog(&window, &sys, selected_pids.borrow().clone());
+        }
+    ));
+
+    // Used by the processes context menu.
+    let process_info = gio::SimpleAction::new("process-info", None);
+    process_info.connect_activate(glib::clone!(
+        #[weak(rename_to = info_button)]
+        procs.info_button,
+        move |_, _| info_button.emit_clicked()
+    ));
+    let end_task = gio::SimpleAction::new("end-task", None);
+    end_task.connect_activate(glib::clone!(
+        #[weak(rename_to = kill_button)]
+        procs.kill_button,
+        move |_, _| kill_button.emit_clicked()
+    ));
+
+    // Saves the pinned processes and updates the processes list accordingly.
+    let update_pins = Rc::new(glib::clone!(
+        #[weak]
+        settings,
+        #[strong(rename_to = processes)]
+        rfs.processes,
+        #[weak]
+        pins,
+        #[weak]
+        users,
+        #[strong(rename_to = model)]
+        procs.model,
+        #[strong(rename_to = view_mode)]
+        procs.view_mode,
+        move || {
+            let settings = settings.borrow();
+            settings.save();
+            *pins.borrow_mut() = Pins::new(&settings.pinned_processes);
+            update_window(
+                &model,
+                &processes.borrow(),
+                view_mode.get(),
+                &mut users.borrow_mut(),
+                &pins.borrow(),
             );
         }
     ));
+    let pin_process = gio::SimpleAction::new("pin-process", None);
+    pin_process.connect_activate(glib::clone!(
+        #[weak]
+        selected_pids,
+        #[weak]
+        settings,
+        #[strong]
+        update_pins,
+        move |_, _| {
+            pins::pin_pids(
+                &mut settings.borrow_mut().pinned_processes,
+                &selected_pids.borrow(),
+            );
+            update_pins();
+        }
+    ));
+    let pin_process_name = gio::SimpleAction::new("pin-process-name", None);
+    pin_process_name.connect_activate(glib::clone!(
+        #[weak]
+        selected_pids,
+        #[weak]
+        settings,
+        #[strong(rename_to = processes)]
+        rfs.processes,
+        #[weak]
+        window,
+        #[strong]
+        update_pins,
+        move |_, _| {
+            let name = selected_pids
+                .borrow()
+                .first()
+                .and_then(|pid| processes.borrow().get(pid).map(ProcessEntry::group_name));
+            let update_pins = update_pins.clone();
+            pins::ask_pin_pattern(&window, name.as_deref(), move |pattern| {
+                let pin = pins::PinnedProcess::Pattern(pattern);
+                {
+                    let mut settings = settings.borrow_mut();
+                    if !settings.pinned_processes.contains(&pin) {
+                        settings.pinned_processes.push(pin);
+                    }
+                }
+                update_pins();
+            });
+        }
+    ));
+    let unpin_process = gio::SimpleAction::new("unpin-process", None);
+    unpin_process.connect_activate(glib::clone!(
+        #[weak]
+        selected_pids,
+        #[weak]
+        settings,
+        #[strong(rename_to = processes)]
+        rfs.processes,
+        #[strong]
+        update_pins,
+        move |_, _| {
+            let processes = {
+                let processes = processes.borrow();
+                selected_pids
+                    .borrow()
+                    .iter()
+                    .filter_map(|pid| processes.get(pid).map(|p| (*pid, p.group_name())))
+                    .collect::<Vec<_>>()
+            };
+            pins::unpin(&mut settings.borrow_mut().pinned_processes, &processes);
+            update_pins();
+        }
+    ));
 
     let about = gio::SimpleAction::new("about", None);
     about.connect_activate(glib::clone!(
@@ -740,6 +1161,16 @@ fn build_ui(application: &gtk::Application) {
     application.add_action(&new_task);
     application.add_action(&quit);
     application.add_action(&finder);
+    application.add_action(&send_signal);
+    application.add_action(&send_signal_number);
+    application.add_action(&signal_process_tree);
+    application.add_action(&change_priority);
+    application.add_action(&cpu_affinity);
+    application.add_action(&process_info);
+    application.add_action(&end_task);
+    application.add_action(&pin_process);
+    application.add_action(&pin_process_name);
+    application.add_action(&unpin_process);
 
     window.set_widget_name(utils::MAIN_WINDOW_NAME);
 
@@ -0,0 +1,672 @@
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{gio, glib};
+use sysinfo::Pid;
+
+use crate::utils::{
+    compare_ignore_case, create_sorter, create_text_column, describe_process_error, format_number,
+    sort_again, update_store,
+};
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::io;
+use std::ops::AddAssign;
+use std::rc::Rc;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, glib::Properties)]
+    #[properties(wrapper_type = super::MemoryMapRow)]
+    pub struct MemoryMapRow {
+        /// Identifies the row in its list: the address range of a mapping, or the name of a file.
+        #[property(get, construct_only)]
+        key: RefCell<String>,
+        #[property(get, construct_only)]
+        start: RefCell<u64>,
+        #[property(get, set)]
+        perms: RefCell<String>,
+        #[property(get, set)]
+        name: RefCell<String>,
+        #[property(get, set)]
+        mappings: RefCell<u32>,
+        #[property(get, set)]
+        rss: RefCell<u64>,
+        #[property(get, set)]
+        pss: RefCell<u64>,
+        #[property(get, set)]
+        shared: RefCell<u64>,
+        #[property(get, set)]
+        private: RefCell<u64>,
+        #[property(get, set)]
+        dirty: RefCell<u64>,
+        #[property(get, set)]
+        swap: RefCell<u64>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MemoryMapRow {
+        const NAME: &'static str = "MemoryMapRow";
+        type Type = super::MemoryMapRow;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for MemoryMapRow {}
+}
+
+glib::wrapper! {
+    /// A row of the memory maps lists: either a single mapping or all the mappings of a file.
+    pub struct MemoryMapRow(ObjectSubclass<imp::MemoryMapRow>);
+}
+
+impl MemoryMapRow {
+    fn new(key: &str, map: &MemoryMap) -> Self {
+        let row: Self = glib::Object::builder()
+            .property("key", key)
+            .property("start", map.start)
+            .build();
+        row.update(map);
+        row
+    }
+
+    fn update(&self, map: &MemoryMap) {
+        if self.perms() != map.perms {
+            self.set_perms(map.perms.as_str());
+        }
+        if self.name() != map.name {
+            self.set_name(map.name.as_str());
+        }
+        if self.mappings() != map.mappings {
+            self.set_mappings(map.mappings);
+        }
+        let usage = &map.usage;
+        for (property, value) in [
+            ("rss", usage.rss),
+            ("pss", usage.pss),
+            ("shared", usage.shared),
+            ("private", usage.private),
+            ("dirty", usage.dirty),
+            ("swap", usage.swap),
+        ] {
+            if self.property::<u64>(property) != value {
+                self.set_property(property, value);
+            }
+        }
+    }
+}
+
+/// The memory used by one or more mappings, in bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MemoryUsage {
+    /// Resident set size: the memory of the mapping which is in RAM.
+    pub rss: u64,
+    /// Proportional set size: the resident memory, with the shared pages divided between the
+    /// processes sharing them.
+    pub pss: u64,
+    pub shared: u64,
+    /// The memory which isn't shared with other processes: the unique set size (USS) of a whole
+    /// process.
+    pub private: u64,
+    pub dirty: u64,
+    pub swap: u64,
+}
+
+impl AddAssign for MemoryUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.rss += other.rss;
+        self.pss += other.pss;
+        self.shared += other.shared;
+        self.private += other.private;
+        self.dirty += other.dirty;
+        self.swap += other.swap;
+    }
+}
+
+/// A memory mapping of a process, or the sum of several mappings of the same file.
+#[derive(Debug, PartialEq)]
+pub struct MemoryMap {
+    pub start: u64,
+    pub end: u64,
+    /// Empty for a sum of mappings, whose permissions differ.
+    pub perms: String,
+    /// The mapped file, or a pseudo-path like `[heap]` or `[stack]`.
+    pub name: String,
+    pub mappings: u32,
+    pub usage: MemoryUsage,
+}
+
+impl MemoryMap {
+    fn address(&self) -> String {
+        format!("{:x}-{:x}", self.start, self.end)
+    }
+}
+
+/// Returns the memory mappings of the process `pid`, in the order of their addresses.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn memory_maps(pid: Pid) -> io::Result<Vec<MemoryMap>> {
+    std::fs::read_to_string(format!("/proc/{pid}/smaps")).map(|smaps| parse_smaps(&smaps))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn memory_maps(_pid: Pid) -> io::Result<Vec<MemoryMap>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Listing the memory maps is not supported on this platform",
+    ))
+}
+
+/// Returns the memory usage of the whole process `pid`. It is read from
+/// `/proc/<pid>/smaps_rollup`, which is much cheaper than summing its memory maps. `None` if it
+/// couldn't be read, or for kernel threads, which have no memory of their own.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn memory_rollup(pid: Pid) -> Option<MemoryUsage> {
+    let rollup = std::fs::read_to_string(format!("/proc/{pid}/smaps_rollup")).ok()?;
+    // The rollup is a single mapping covering the whole address space.
+    parse_smaps(&rollup).first().map(|map| map.usage)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn memory_rollup(_pid: Pid) -> Option<MemoryUsage> {
+    None
+}
+
+/// Parses the content of a `/proc/<pid>/smaps` file: each mapping starts with the same line as
+/// in `/proc/<pid>/maps`, followed by one `Field: value kB` line per counter.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn parse_smaps(smaps: &str) -> Vec<MemoryMap> {
+    let mut maps: Vec<MemoryMap> = Vec::new();
+    for line in smaps.lines() {
+        let mut fields = line.split_whitespace();
+        let Some(first) = fields.next() else {
+            continue;
+        };
+        if let Some(field) = first.strip_suffix(':') {
+            let Some(map) = maps.last_mut() else {
+                continue;
+            };
+            // `VmFlags` and a few other fields aren't sizes, they are ignored.
+            let Some(size) = fields
+                .next()
+                .and_then(|size| size.parse::<u64>().ok())
+                .filter(|_| fields.next() == Some("kB"))
+            else {
+                continue;
+            };
+            let size = size * 1_024;
+            let usage = &mut map.usage;
+            match field {
+                "Rss" => usage.rss = size,
+                "Pss" => usage.pss = size,
+                "Shared_Clean" => usage.shared += size,
+                "Shared_Dirty" => {
+                    usage.shared += size;
+                    usage.dirty += size;
+                }
+                "Private_Clean" => usage.private += size,
+                "Private_Dirty" => {
+                    usage.private += size;
+                    usage.dirty += size;
+                }
+                "Swap" => usage.swap = size,
+                _ => {}
+            }
+            continue;
+        }
+        let Some((start, end)) = first.split_once('-').and_then(|(start, end)| {
+            Some((
+                u64::from_str_radix(start, 16).ok()?,
+                u64::from_str_radix(end, 16).ok()?,
+            ))
+        }) else {
+            continue;
+        };
+        // The fields are separated by a single space, except before the path, which is aligned
+        // and may contain spaces.
+        let mut fields = line.splitn(6, ' ').skip(1);
+        let perms = fields.next().unwrap_or_default().to_owned();
+        let name = match fields.nth(3).map(str::trim_start) {
+            Some(name) if !name.is_empty() => name.to_owned(),
+            _ => "[anonymous]".to_owned(),
+        };
+        maps.push(MemoryMap {
+            start,
+            end,
+            perms,
+            name,
+            mappings: 1,
+            usage: MemoryUsage::default(),
+        });
+    }
+    maps
+}
+
+/// Sums the `maps` of each file. The result starts at the lowest address of the file.
+fn summarize(maps: &[MemoryMap]) -> Vec<MemoryMap> {
+    let mut files: HashMap<&str, MemoryMap> = HashMap::new();
+    for map in maps {
+        let file = files.entry(&map.name).or_insert_with(|| MemoryMap {
+            start: map.start,
+            end: map.end,
+            perms: String::new(),
+            name: map.name.clone(),
+            mappings: 0,
+            usage: MemoryUsage::default(),
+        });
+        file.start = file.start.min(map.start);
+        file.end = file.end.max(map.end);
+        file.mappings += 1;
+        file.usage += map.usage;
+    }
+    files.into_values().collect()
+}
+
+/// The sums of the usage of all the mappings, next to the memory usage of the process.
+#[derive(Clone)]
+struct Totals {
+    grid: gtk::Grid,
+    /// The resident memory of the mappings, the memory usage of the process, then the other sums.
+    values: [gtk::Label; 7],
+    /// Displayed instead of the totals when the mappings can't be read.
+    error: gtk::Label,
+    memory_usage: Rc<Cell<u64>>,
+}
+
+impl Totals {
+    fn new(memory_usage: u64) -> Self {
+        let grid = gtk::Grid::builder()
+            .column_spacing(12)
+            .row_spacing(2)
+            .build();
+        let titles = [
+            "resident (RSS)",
+            "memory usage",
+            "proportional (PSS)",
+            "shared",
+            "private",
+            "dirty",
+            "swapped",
+        ];
+        let total = gtk::Label::builder().halign(gtk::Align::Start).build();
+        total.set_markup("<b>Total</b>");
+        grid.attach(&total, 0, 1, 1, 1);
+        let memory_usage_tooltip = "The memory usage of the process, as displayed in the \
+            processes list. It counts the same pages as the resident memory of the mappings, but \
+            the kernel updates these counters lazily, so they can differ slightly.";
+        for (column, title) in titles.iter().enumerate() {
+            let title = gtk::Label::builder().label(*title).xalign(1.).build();
+            grid.attach(&title, column as i32 + 1, 0, 1, 1);
+        }
+        let values = titles.map(|_| gtk::Label::builder().xalign(1.).selectable(true).build());
+        for (column, value) in values.iter().enumerate() {
+            grid.attach(value, column as i32 + 1, 1, 1, 1);
+        }
+        // The memory usage is in the second column, after the resident memory.
+        for widget in [grid.child_at(2, 0), grid.child_at(2, 1)]
+            .into_iter()
+            .flatten()
+        {
+            widget.set_tooltip_text(Some(memory_usage_tooltip));
+        }
+
+        let error = gtk::Label::builder()
+            .halign(gtk::Align::Start)
+            .wrap(true)
+            .visible(false)
+            .build();
+        Self {
+            grid,
+            values,
+            error,
+            memory_usage: Rc::new(Cell::new(memory_usage)),
+        }
+    }
+
+    fn set(&self, sum: &MemoryUsage) {
+        let sizes = [
+            sum.rss,
+            self.memory_usage.get(),
+            sum.pss,
+            sum.shared,
+            sum.private,
+            sum.dirty,
+            sum.swap,
+        ];
+        for (label, size) in self.values.iter().zip(sizes) {
+            label.set_text(&format_number(size));
+        }
+        self.grid.set_visible(true);
+        self.error.set_visible(false);
+    }
+
+    fn set_error(&self, error: &str) {
+        self.error.set_text(error);
+        self.error.set_visible(true);
+        self.grid.set_visible(false);
+    }
+}
+
+/// The memory mappings of a process, summed by file or one by one.
+pub struct MemoryMapsList {
+    pub widget: gtk::Box,
+    pid: Pid,
+    files: gio::ListStore,
+    mappings: gio::ListStore,
+    sort_models: [gtk::SortListModel; 2],
+    totals: Totals,
+}
+
+impl MemoryMapsList {
+    /// `memory_usage` is the memory usage of the process, which is compared with the resident
+    /// memory of its mappings. The maps are read again whenever the lists are displayed.
+    pub fn new(pid: Pid, memory_usage: u64) -> Self {
+        let files = gio::ListStore::new::<MemoryMapRow>();
+        let (files_view, files_sort_model) = create_view(&files, false);
+        let mappings = gio::ListStore::new::<MemoryMapRow>();
+        let (mappings_view, mappings_sort_model) = create_view(&mappings, true);
+
+        let stack = gtk::Stack::new();
+        stack.add_titled(&files_view, Some("files"), "By file");
+        stack.add_titled(&mappings_view, Some("mappings"), "All mappings");
+        let totals = Totals::new(memory_usage);
+
+        let widget = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(5)
+            .css_classes(vec!["button-with-margin".to_owned()])
+            .build();
+        widget.append(
+            &gtk::StackSwitcher::builder()
+                .stack(&stack)
+                .halign(gtk::Align::Center)
+                .build(),
+        );
+        widget.append(&stack);
+        widget.append(&totals.grid);
+        widget.append(&totals.error);
+
+        widget.connect_map(glib::clone!(
+            #[weak]
+            files,
+            #[weak]
+            mappings,
+            #[weak]
+            files_sort_model,
+            #[weak]
+            mappings_sort_model,
+            #[strong]
+            totals,
+            move |_| {
+                update_lists(
+                    pid,
+                    [&files, &mappings],
+                    [&files_sort_model, &mappings_sort_model],
+                    &totals,
+                )
+            }
+        ));
+
+        Self {
+            widget,
+            pid,
+            files,
+            mappings,
+            sort_models: [files_sort_model, mappings_sort_model],
+            totals,
+        }
+    }
+
+    /// Sets the memory usage of the process, from the last snapshot.
+    pub fn set_memory_usage(&self, memory_usage: u64) {
+        self.totals.memory_usage.set(memory_usage);
+    }
+
+    /// Reads the memory mappings of the process again.
+    pub fn update(&self) {
+        let [files_sort_model, mappings_sort_model] = &self.sort_models;
+        update_lists(
+            self.pid,
+            [&self.files, &self.mappings],
+            [files_sort_model, mappings_sort_model],
+            &self.totals,
+        );
+    }
+}
+
+/// Creates the view of the list of files, or of the list of mappings if `mappings` is `true`.
+fn create_view(
+    store: &gio::ListStore,
+    mappings: bool,
+) -> (gtk::ScrolledWindow, gtk::SortListModel) {
+    let column_view = gtk::ColumnView::new(None::<gtk::SelectionModel>);
+    let sort_model = gtk::SortListModel::new(Some(store.clone()), column_view.sorter());
+    column_view.set_model(Some(&gtk::NoSelection::new(Some(sort_model.clone()))));
+
+    let address = create_text_column("address", "key", 0., |row: &MemoryMapRow| row.key());
+    address.set_sorter(Some(&create_sorter(
+        |a: &MemoryMapRow, b: &MemoryMapRow| a.start().cmp(&b.start()),
+    )));
+    let perms = create_text_column("perms", "perms", 0., |row: &MemoryMapRow| row.perms());
+    perms.set_sorter(Some(&create_sorter(
+        |a: &MemoryMapRow, b: &MemoryMapRow| a.perms().cmp(&b.perms()),
+    )));
+    let name = create_text_column("file", "name", 0., |row: &MemoryMapRow| row.name());
+    name.set_sorter(Some(&create_sorter(
+        |a: &MemoryMapRow, b: &MemoryMapRow| compare_ignore_case(&a.name(), &b.name()),
+    )));
+    name.set_expand(true);
+    if mappings {
+        column_view.append_column(&address);
+        column_view.append_column(&perms);
+        column_view.append_column(&name);
+    } else {
+        column_view.append_column(&name);
+        let count = create_text_column("mappings", "mappings", 1., |row: &MemoryMapRow| {
+            row.mappings().to_string()
+        });
+        count.set_sorter(Some(&create_sorter(
+            |a: &MemoryMapRow, b: &MemoryMapRow| a.mappings().cmp(&b.mappings()),
+        )));
+        column_view.append_column(&count);
+    }
+
+    let mut rss = None;
+    for (title, property, value) in [
+        ("RSS", "rss", MemoryMapRow::rss as fn(&MemoryMapRow) -> u64),
+        ("PSS", "pss", MemoryMapRow::pss),
+        ("shared", "shared", MemoryMapRow::shared),
+        ("private", "private", MemoryMapRow::private),
+        ("dirty", "dirty", MemoryMapRow::dirty),
+        ("swapped", "swap", MemoryMapRow::swap),
+    ] {
+        let column = create_text_column(title, property, 1., move |row: &MemoryMapRow| {
+            format_number(value(row))
+        });
+        column.set_sorter(Some(&create_sorter(
+            move |a: &MemoryMapRow, b: &MemoryMapRow| value(a).cmp(&value(b)),
+        )));
+        column_view.append_column(&column);
+        rss.get_or_insert(column);
+    }
+
+    // The mappings are shown in the order of their addresses, like in `/proc/<pid>/maps`, and the
+    // files from the one using the most memory.
+    if mappings {
+        column_view.sort_by_column(Some(&address), gtk::SortType::Ascending);
+    } else {
+        column_view.sort_by_column(rss.as_ref(), gtk::SortType::Descending);
+    }
+
+    let scroll = gtk::ScrolledWindow::builder()
+        .child(&column_view)
+        .hexpand(true)
+        .vexpand(true)
+        .build();
+    (scroll, sort_model)
+}
+
+fn update_lists(
+    pid: Pid,
+    [files, mappings]: [&gio::ListStore; 2],
+    sort_models: [&gtk::SortListModel; 2],
+    totals: &Totals,
+) {
+    let maps = match memory_maps(pid) {
+        Ok(maps) => maps,
+        Err(error) => {
+            files.remove_all();
+            mappings.remove_all();
+            totals.set_error(&describe_process_error(&error, "read the memory maps of"));
+            return;
+        }
+    };
+
+    let mut sum = MemoryUsage::default();
+    for map in &maps {
+        sum += map.usage;
+    }
+    totals.set(&sum);
+
+    let summary = summarize(&maps);
+    // The files are identified by their name, the mappings by their address.
+    update_store(
+        files,
+        summary.iter().map(|map| (map.name.clone(), map)).collect(),
+        MemoryMapRow::key,
+        MemoryMapRow::update,
+        |(key, map)| MemoryMapRow::new(&key, map),
+    );
+    update_store(
+        mappings,
+        maps.iter().map(|map| (map.address(), map)).collect(),
+        MemoryMapRow::key,
+        MemoryMapRow::update,
+        |(key, map)| MemoryMapRow::new(&key, map),
+    );
+    for sort_model in sort_models {
+        sort_again(sort_model);
+    }
+}
+
+#[cfg(all(test, any(target_os = "linux", target_os = "android")))]
+mod tests {
+    use super::*;
+    use crate::test_support::fixture;
+
+    const KB: u64 = 1_024;
+
+    #[test]
+    fn smaps() {
+        let maps = parse_smaps(&fixture("proc_pid/smaps"));
+        assert_eq!(
+            maps.iter()
+                .map(|map| (map.address(), map.perms.as_str(), map.name.as_str()))
+                .collect::<Vec<_>>(),
+            [
+                (
+                    "55d4c3a00000-55d4c3a02000".to_owned(),
+                    "r--p",
+                    "/usr/bin/my app"
+                ),
+                (
+                    "55d4c3a02000-55d4c3a08000".to_owned(),
+                    "r-xp",
+                    "/usr/bin/my app"
+                ),
+                ("55d4c4b1e000-55d4c4b3f000".to_owned(), "rw-p", "[heap]"),
+                (
+                    "7f2a1c000000-7f2a1c021000".to_owned(),
+                    "rw-p",
+                    "[anonymous]"
+                ),
+                (
+                    "7f2a1e400000-7f2a1e428000".to_owned(),
+                    "r--p",
+                    "/usr/lib/libc.so.6"
+                ),
+                (
+                    "7f2a1e428000-7f2a1e5bd000".to_owned(),
+                    "r-xp",
+                    "/usr/lib/libc.so.6"
+                ),
+                ("7ffd5e2b1000-7ffd5e2d2000".to_owned(), "rw-p", "[stack]"),
+            ]
+        );
+        assert_eq!(
+            maps[2].usage,
+            MemoryUsage {
+                rss: 132 * KB,
+                pss: 132 * KB,
+                shared: 0,
+                private: 132 * KB,
+                dirty: 132 * KB,
+                swap: 4 * KB,
+            }
+        );
+        // The pages of the library are shared with other processes.
+        assert_eq!(
+            maps[5].usage,
+            MemoryUsage {
+                rss: 1_012 * KB,
+                pss: 101 * KB,
+                shared: 1_012 * KB,
+                private: 0,
+                dirty: 12 * KB,
+                swap: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn summarized_by_file() {
+        let mut files = summarize(&parse_smaps(&fixture("proc_pid/smaps")));
+        files.sort_by_key(|file| file.start);
+        assert_eq!(
+            files
+                .iter()
+                .map(|file| (file.name.as_str(), file.mappings))
+                .collect::<Vec<_>>(),
+            [
+                ("/usr/bin/my app", 2),
+                ("[heap]", 1),
+                ("[anonymous]", 1),
+                ("/usr/lib/libc.so.6", 2),
+                ("[stack]", 1),
+            ]
+        );
+        assert_eq!(files[0].address(), "55d4c3a00000-55d4c3a08000");
+        assert_eq!(files[3].usage.rss, (160 + 1_012) * KB);
+        assert_eq!(files[3].usage.pss, (20 + 101) * KB);
+    }
+
+    #[test]
+    fn sizes_in_other_units_are_ignored() {
+        let smaps = "Rss: 4 kB\n\
+                     7f2a1c000000-7f2a1c021000 rw-p 00000000 00:00 0 \n\
+                     Rss: four kB\n\
+                     Pss: 4 MB\n\
+                     Swap: 8 kB\n\
+                     VmFlags: rd wr\n";
+        let maps = parse_smaps(smaps);
+        assert_eq!(maps.len(), 1);
+        assert_eq!(
+            maps[0].usage,
+            MemoryUsage {
+                swap: 8 * KB,
+                ..MemoryUsage::default()
+            }
+        );
+    }
+
+    #[test]
+    fn rollup_matches_the_sum_of_the_mappings() {
+        let rollup = parse_smaps(&fixture("proc_pid/smaps_rollup"));
+        assert_eq!(rollup.len(), 1);
+        let mut sum = MemoryUsage::default();
+        for map in parse_smaps(&fixture("proc_pid/smaps")) {
+            sum += map.usage;
+        }
+        assert_eq!(rollup[0].usage, sum);
+        assert_eq!(sum.pss, 321 * KB);
+    }
+}
@@ -1,8 +1,10 @@
 use gtk::prelude::*;
 use gtk::{glib, EventControllerKey};
 
+use crate::collector::NetworkInfo;
 use crate::graph::GraphWidget;
 use crate::notebook::NoteBook;
+use crate::property_list::PropertyList;
 use crate::utils::{
     format_number, format_number_full, get_main_window, graph_label, graph_label_units, RotateVec,
 };
@@ -23,7 +25,7 @@ pub struct NetworkDialog {
     errors_on_received_peak: Rc<RefCell<u64>>,
     errors_on_transmitted_peak: Rc<RefCell<u64>>,
     to_be_removed: Rc<Cell<bool>>,
-    list_store: gtk::ListStore,
+    properties: PropertyList,
 }
 
 macro_rules! update_graph {
@@ -35,24 +37,22 @@ macro_rules! update_graph {
         let mut x = $this.$peak.borrow_mut();
         if *x < $value {
             *x = $value;
-            if let Some(iter) = $this.list_store.iter_nth_child(None, $list_pos - 1) {
-                $this.list_store.set(&iter, &[(1, &$formatter($value))]);
-            }
-        }
-        if let Some(iter) = $this.list_store.iter_nth_child(None, $list_pos - 2) {
-            $this.list_store.set(&iter, &[(1, &$formatter($value))]);
-        }
-        if let Some(iter) = $this.list_store.iter_nth_child(None, $list_pos) {
             $this
-                .list_store
-                .set(&iter, &[(1, &$formatter($total_value))]);
+                .properties
+                .set_value($list_pos - 1, &$formatter($value));
         }
+        $this
+            .properties
+            .set_value($list_pos - 2, &$formatter($value));
+        $this
+            .properties
+            .set_value($list_pos, &$formatter($total_value));
     }};
 }
 
 impl NetworkDialog {
     #[allow(clippy::cognitive_complexity)]
-    pub fn update(&self, network: &sysinfo::NetworkData) {
+    pub fn update(&self, network: &NetworkInfo) {
         if self.need_remove() {
             return;
         }
@@ -65,8 +65,8 @@ impl NetworkDialog {
             self,
             t,
             0,
-            network.packets_received(),
-            network.total_packets_received(),
+            network.packets_received,
+            network.total_packets_received,
             packets_received_peak,
             9,
             formatter
@@ -75,8 +75,8 @@ impl NetworkDialog {
             self,
             t,
             1,
-            network.packets_transmitted(),
-            network.total_packets_transmitted(),
+            network.packets_transmitted,
+            network.total_packets_transmitted,
             packets_transmitted_peak,
             12,
             formatter
@@ -85,8 +85,8 @@ impl NetworkDialog {
             self,
             t,
             2,
-            network.errors_on_received(),
-            network.total_errors_on_received(),
+            network.errors_on_received,
+            network.total_errors_on_received,
             errors_on_received_peak,
             15,
             formatter
@@ -95,8 +95,8 @@ impl NetworkDialog {
             self,
             t,
             3,
-            network.errors_on_transmitted(),
-            network.total_errors_on_transmitted(),
+            network.errors_on_transmitted,
+            network.total_errors_on_transmitted,
             errors_on_transmitted_peak,
             18,
             formatter
@@ -108,8 +108,8 @@ impl NetworkDialog {
             self,
             t,
             0,
-            network.received(),
-            network.total_received(),
+            network.received,
+            network.total_received,
             received_peak,
             3,
             format_number
@@ -118,8 +118,8 @@ impl NetworkDialog {
             self,
             t,
             1,
-            network.transmitted(),
-            network.total_transmitted(),
+            network.transmitted,
+            network.total_transmitted,
             transmitted_peak,
             6,
             format_number
@@ -136,29 +136,7 @@ impl NetworkDialog {
     }
 }
 
-fn append_text_column(tree: &gtk::TreeView, title: &str, pos: i32, right_align: bool) {
-    let column = gtk::TreeViewColumn::builder()
-        .title(title)
-        .resizable(true)
-        .build();
-    let cell = gtk::CellRendererText::new();
-
-    if right_align {
-        cell.set_xalign(1.0);
-    }
-    column.pack_start(&cell, true);
-    column.add_attribute(&cell, "text", pos);
-    if pos == 1 {
-        cell.set_wrap_mode(gtk::pango::WrapMode::Char);
-        column.set_expand(true);
-    }
-    tree.append_column(&column);
-}
-
-pub fn create_network_dialog(
-    network: &sysinfo::NetworkData,
-    interface_name: &str,
-) -> NetworkDialog {
+pub fn create_network_dialog(network: &NetworkInfo, interface_name: &str) -> NetworkDialog {
     let mut notebook = NoteBook::new();
 
     let popup = gtk::Window::new();
@@ -259,159 +237,67 @@ pub fn create_network_dialog(
     //
     // NETWORK INFO TAB
     //
-    let list_store = gtk::ListStore::new(&[glib::Type::STRING, glib::Type::STRING]);
-    let tree = gtk::TreeView::builder()
-        .headers_visible(true)
-        .model(&list_store)
-        .build();
-
-    append_text_column(&tree, "property", 0, false);
-    append_text_column(&tree, "value", 1, true);
-
-    list_store.insert_with_values(
-        None,
-        &[(0, &"MAC address"), (1, &network.mac_address().to_string())],
+    let properties = PropertyList::new(("property", "value"), true);
+    properties.append("MAC address", &network.mac_address.to_string());
+    properties.append("received", &format_number(network.received));
+    properties.append("received peak", &format_number(network.received));
+    properties.append("total received", &format_number(network.total_received));
+    properties.append("transmitted", &format_number(network.transmitted));
+    properties.append("transmitted peak", &format_number(network.transmitted));
+    properties.append(
+        "total transmitted",
+        &format_number(network.total_transmitted),
     );
-    list_store.insert_with_values(
-        None,
-        &[(0, &"received"), (1, &format_number(network.received()))],
+    properties.append(
+        "packets received",
+        &format_number_full(network.packets_received, false),
     );
-    list_store.insert_with_values(
-        None,
-        &[
-            (0, &"received peak"),
-            (1, &format_number(network.received())),
-        ],
+    properties.append(
+        "packets received peak",
+        &format_number(network.packets_received),
     );
-    list_store.insert_with_values(
-        None,
-        &[
-            (0, &"total received"),
-            (1, &format_number(network.total_received())),
-        ],
+    properties.append(
+        "total packets received",
+        &format_number_full(network.total_packets_received, false),
     );
-    list_store.insert_with_values(
-        None,
-        &[
-            (0, &"transmitted"),
-            (1, &format_number(network.transmitted())),
-        ],
+    properties.append(
+        "packets transmitted",
+        &format_number_full(network.packets_transmitted, false),
     );
-    list_store.insert_with_values(
-        None,
-        &[
-            (0, &"transmitted peak"),
-            (1, &format_number(network.transmitted())),
-        ],
+    properties.append(
+        "packets transmitted peak",
+        &format_number(network.packets_transmitted),
     );
-    list_store.insert_with_values(
-        None,
-        &[
-            (0, &"total transmitted"),
-            (1, &format_number(network.total_transmitted())),
-        ],
+    properties.append(
+        "total packets transmitted",
+        &format_number_full(network.total_packets_transmitted, false),
     );
-    list_store.insert_with_values(
-        None,
-        &[
-            (0, &"packets received"),
-            (1, &format_number_full(network.packets_received(), false)),
-        ],
-    );
-    list_store.insert_with_values(
-        None,
-        &[
-            (0, &"packets received peak"),
-            (1, &format_number(network.packets_received())),
-        ],
-    );
-    list_store.insert_with_values(
-        None,
-        &[
-            (0, &"total packets received"),
-            (
-                1,
-                &format_number_full(network.total_packets_received(), false),
-            ),
-        ],
-    );
-    list_store.insert_with_values(
-        None,
-        &[
-            (0, &"packets transmitted"),
-            (1, &format_number_full(network.packets_transmitted(), false)),
-        ],
-    );
-    list_store.insert_with_values(
-        None,
-        &[
-            (0, &"packets transmitted peak"),
-            (1, &format_number(network.packets_transmitted())),
-        ],
-    );
-    list_store.insert_with_values(
-        None,
-        &[
-            (0, &"total packets transmitted"),
-            (
-                1,
-                &format_number_full(network.total_packets_transmitted(), false),
-            ),
-        ],
+    properties.append(
+        "errors on received",
+        &format_number_full(network.errors_on_received, false),
     );
-    list_store.insert_with_values(
-        None,
-        &[
-            (0, &"errors on received"),
-            (1, &format_number_full(network.errors_on_received(), false)),
-        ],
+    properties.append(
+        "errors on received peak",
+        &format_number(network.errors_on_received),
     );
-    list_store.insert_with_values(
-        None,
-        &[
-            (0, &"errors on received peak"),
-            (1, &format_number(network.errors_on_received()).as_str()),
-        ],
-    );
-    list_store.insert_with_values(
-        None,
-        &[
-            (0, &"total errors on received"),
-            (
-                1,
-                &format_number_full(network.total_errors_on_received(), false),
-            ),
-        ],
+    properties.append(
+        "total errors on received",
+        &format_number_full(network.total_errors_on_received, false),
     );
-    list_store.insert_with_values(
-        None,
-        &[
-            (0, &"errors on transmitted"),
-            (
-                1,
-                &format_number_full(network.errors_on_transmitted(), false),
-            ),
-        ],
+    properties.append(
+        "errors on transmitted",
+        &format_number_full(network.errors_on_transmitted, false),
     );
-    list_store.insert_with_values(
-        None,
-        &[
-            (0, &"errors on transmitted peak"),
-            (1, &format_number(network.errors_on_transmitted())),
-        ],
+    properties.append(
+        "errors on transmitted peak",
+        &format_number(network.errors_on_transmitted),
     );
-    list_store.insert_with_values(
-        None,
-        &[
-            (0, &"total errors on transmitted"),
-            (
-                1,
-                &format_number_full(network.total_errors_on_transmitted(), false),
-            ),
-        ],
+    properties.append(
+        "total errors on transmitted",
+        &format_number_full(network.total_errors_on_transmitted, false),
     );
 
-    notebook.create_tab("Information", &tree);
+    notebook.create_tab("Information", &properties.view);
 
     popup.set_size_request(700, 540);
 
@@ -469,13 +355,13 @@ pub fn create_network_dialog(
         popup,
         packets_errors_history,
         in_out_history,
-        received_peak: Rc::new(RefCell::new(network.received())),
-        transmitted_peak: Rc::new(RefCell::new(network.transmitted())),
-        packets_received_peak: Rc::new(RefCell::new(network.packets_received())),
-        packets_transmitted_peak: Rc::new(RefCell::new(network.packets_transmitted())),
-        errors_on_received_peak: Rc::new(RefCell::new(network.errors_on_received())),
-        errors_on_transmitted_peak: Rc::new(RefCell::new(network.errors_on_transmitted())),
+        received_peak: Rc::new(RefCell::new(network.received)),
+        transmitted_peak: Rc::new(RefCell::new(network.transmitted)),
+        packets_received_peak: Rc::new(RefCell::new(network.packets_received)),
+        packets_transmitted_peak: Rc::new(RefCell::new(network.packets_transmitted)),
+        errors_on_received_peak: Rc::new(RefCell::new(network.errors_on_received)),
+        errors_on_transmitted_peak: Rc::new(RefCell::new(network.errors_on_transmitted)),
         to_be_removed,
-        list_store,
+        properties,
     }
 }
@@ -0,0 +1,407 @@
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{gio, glib};
+use sysinfo::Pid;
+
+use crate::filter::{Matcher, SearchOptions};
+use crate::utils::{
+    compare_ignore_case, create_search_options_button, create_sorter, create_text_column,
+    describe_process_error, sort_again, update_store,
+};
+
+use std::cell::{Cell, RefCell};
+use std::io;
+use std::rc::Rc;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, glib::Properties)]
+    #[properties(wrapper_type = super::OpenFileRow)]
+    pub struct OpenFileRow {
+        #[property(get, construct_only)]
+        fd: RefCell<u32>,
+        #[property(get, set)]
+        kind: RefCell<String>,
+        #[property(get, set)]
+        target: RefCell<String>,
+        #[property(get, set)]
+        flags: RefCell<String>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for OpenFileRow {
+        const NAME: &'static str = "OpenFileRow";
+        type Type = super::OpenFileRow;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for OpenFileRow {}
+}
+
+glib::wrapper! {
+    /// A row of the open files list.
+    pub struct OpenFileRow(ObjectSubclass<imp::OpenFileRow>);
+}
+
+impl OpenFileRow {
+    fn new(file: &OpenFile) -> Self {
+        let row: Self = glib::Object::builder().property("fd", file.fd).build();
+        row.update(file);
+        row
+    }
+
+    fn update(&self, file: &OpenFile) {
+        let kind = file.kind();
+        if self.kind() != kind {
+            self.set_kind(kind);
+        }
+        if self.target() != file.target {
+            self.set_target(file.target.as_str());
+        }
+        let flags = file.flags.map(describe_flags).unwrap_or_default();
+        if self.flags() != flags {
+            self.set_flags(flags);
+        }
+    }
+
+    fn matches(&self, matcher: &Matcher) -> bool {
+        matcher.is_match(&self.fd().to_string())
+            || matcher.is_match(&self.kind())
+            || matcher.is_match(&self.target())
+            || matcher.is_match(&self.flags())
+    }
+}
+
+/// A file descriptor opened by a process.
+pub struct OpenFile {
+    pub fd: u32,
+    /// The path of the file, or a description of what isn't one, like `socket:[1234]`.
+    pub target: String,
+    /// The flags the file was opened with, `None` if they couldn't be read.
+    pub flags: Option<u32>,
+}
+
+impl OpenFile {
+    fn kind(&self) -> &'static str {
+        if self.target.starts_with("socket:") {
+            "socket"
+        } else if self.target.starts_with("pipe:") {
+            "pipe"
+        } else if self.target.starts_with("anon_inode:") {
+            "anon inode"
+        } else if self.target.ends_with(" (deleted)") {
+            // The file was removed but it is still open, so it still takes space on the disk.
+            "deleted file"
+        } else {
+            "file"
+        }
+    }
+}
+
+/// Returns the file descriptors opened by the process `pid`, sorted by number.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn open_files(pid: Pid) -> io::Result<Vec<OpenFile>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(format!("/proc/{pid}/fd"))? {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        let Some(fd) = entry.file_name().to_str().and_then(|fd| fd.parse().ok()) else {
+            continue;
+        };
+        // The file may have been closed in the meantime.
+        let Ok(target) = std::fs::read_link(entry.path()) else {
+            continue;
+        };
+        let flags = std::fs::read_to_string(format!("/proc/{pid}/fdinfo/{fd}"))
+            .ok()
+            .and_then(|fdinfo| parse_flags(&fdinfo));
+        files.push(OpenFile {
+            fd,
+            target: target.to_string_lossy().into_owned(),
+            flags,
+        });
+    }
+    files.sort_by_key(|file| file.fd);
+    Ok(files)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn open_files(_pid: Pid) -> io::Result<Vec<OpenFile>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Listing the open files is not supported on this platform",
+    ))
+}
+
+/// Returns the flags of a `/proc/<pid>/fdinfo/<fd>` file. They are written in octal.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn parse_flags(fdinfo: &str) -> Option<u32> {
+    fdinfo
+        .lines()
+        .find_map(|line| line.strip_prefix("flags:"))
+        .and_then(|flags| u32::from_str_radix(flags.trim(), 8).ok())
+}
+
+/// Returns the names of the open `flags`, like `O_WRONLY | O_APPEND`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn describe_flags(flags: u32) -> String {
+    let mut flags = flags as libc::c_int;
+    let mut names = vec![match flags & libc::O_ACCMODE {
+        libc::O_WRONLY => "O_WRONLY",
+        libc::O_RDWR => "O_RDWR",
+        _ => "O_RDONLY",
+    }];
+    flags &= !libc::O_ACCMODE;
+    // The flags including the bits of others come first, so they are not named twice.
+    for (flag, name) in [
+        (libc::O_TMPFILE, "O_TMPFILE"),
+        (libc::O_SYNC, "O_SYNC"),
+        (libc::O_DSYNC, "O_DSYNC"),
+        (libc::O_CREAT, "O_CREAT"),
+        (libc::O_EXCL, "O_EXCL"),
+        (libc::O_NOCTTY, "O_NOCTTY"),
+        (libc::O_TRUNC, "O_TRUNC"),
+        (libc::O_APPEND, "O_APPEND"),
+        (libc::O_NONBLOCK, "O_NONBLOCK"),
+        (libc::O_ASYNC, "O_ASYNC"),
+        (libc::O_DIRECT, "O_DIRECT"),
+        (libc::O_DIRECTORY, "O_DIRECTORY"),
+        (libc::O_NOFOLLOW, "O_NOFOLLOW"),
+        (libc::O_NOATIME, "O_NOATIME"),
+        (libc::O_CLOEXEC, "O_CLOEXEC"),
+        (libc::O_PATH, "O_PATH"),
+    ] {
+        if flag != 0 && flags & flag == flag {
+            names.push(name);
+            flags &= !flag;
+        }
+    }
+    names.join(" | ")
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn describe_flags(flags: u32) -> String {
+    format!("{flags:#o}")
+}
+
+/// The list of the files opened by a process, with a filter.
+pub struct OpenFilesList {
+    pub widget: gtk::Box,
+    pid: Pid,
+    store: gio::ListStore,
+    sort_model: gtk::SortListModel,
+    status: gtk::Label,
+}
+
+impl OpenFilesList {
+    /// The list is filled whenever it is displayed, so it's up to date when switching to its tab.
+    pub fn new(pid: Pid) -> Self {
+        let store = gio::ListStore::new::<OpenFileRow>();
+        let column_view = gtk::ColumnView::new(None::<gtk::SelectionModel>);
+
+        let filter_entry = gtk::SearchEntry::builder().hexpand(true).build();
+        // `None` when there is nothing to filter or when the regular expression is invalid.
+        let matcher: Rc<RefCell<Option<Matcher>>> = Rc::new(RefCell::new(None));
+        let filter = gtk::CustomFilter::new(glib::clone!(
+            #[strong]
+            matcher,
+            move |item| {
+                let Some(ref matcher) = *matcher.borrow() else {
+                    return true;
+                };
+                item.downcast_ref::<OpenFileRow>()
+                    .is_some_and(|row| row.matches(matcher))
+            }
+        ));
+        let filter_model = gtk::FilterListModel::new(Some(store.clone()), Some(filter.clone()));
+        let sort_model = gtk::SortListModel::new(Some(filter_model), column_view.sorter());
+        column_view.set_model(Some(&gtk::NoSelection::new(Some(sort_model.clone()))));
+
+        let fd_column =
+            create_text_column("fd", "fd", 1., |row: &OpenFileRow| row.fd().to_string());
+        fd_column.set_sorter(Some(&create_sorter(|a: &OpenFileRow, b: &OpenFileRow| {
+            a.fd().cmp(&b.fd())
+        })));
+        column_view.append_column(&fd_column);
+        for (title, property, value) in [
+            (
+                "type",
+                "kind",
+                OpenFileRow::kind as fn(&OpenFileRow) -> String,
+            ),
+            ("target", "target", OpenFileRow::target),
+            ("flags", "flags", OpenFileRow::flags),
+        ] {
+            let column = create_text_column(title, property, 0., value);
+            column.set_sorter(Some(&create_sorter(
+                move |a: &OpenFileRow, b: &OpenFileRow| compare_ignore_case(&value(a), &value(b)),
+            )));
+            column.set_expand(property == "target");
+            column_view.append_column(&column);
+        }
+        column_view.sort_by_column(Some(&fd_column), gtk::SortType::Ascending);
+
+        let options = Rc::new(Cell::new(SearchOptions::default()));
+        let update_filter = Rc::new(glib::clone!(
+            #[weak]
+            filter_entry,
+            #[strong]
+            options,
+            move || {
+                let text = filter_entry.text();
+                let new_matcher = if text.is_empty() {
+                    Ok(None)
+                } else {
+                    Matcher::new(&text, options.get()).map(Some)
+                };
+                match new_matcher {
+                    Ok(new_matcher) => {
+                        *matcher.borrow_mut() = new_matcher;
+                        filter_entry.remove_css_class("error");
+                        filter_entry.set_tooltip_text(None);
+                    }
+                    Err(_) => {
+                        *matcher.borrow_mut() = None;
+                        filter_entry.add_css_class("error");
+                        filter_entry.set_tooltip_text(Some("Invalid regular expression"));
+                    }
+                }
+                filter.changed(gtk::FilterChange::Different);
+            }
+        ));
+        filter_entry.connect_search_changed(glib::clone!(
+            #[strong]
+            update_filter,
+            move |_| update_filter()
+        ));
+
+        let filter_row = gtk::Box::new(gtk::Orientation::Horizontal, 2);
+        filter_row.append(&filter_entry);
+        filter_row.append(&create_search_options_button(
+            options.get(),
+            None,
+            move |new_options, _| {
+                options.set(new_options);
+                update_filter();
+            },
+        ));
+
+        let status = gtk::Label::builder().halign(gtk::Align::Start).build();
+        let widget = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(5)
+            .css_classes(vec!["button-with-margin".to_owned()])
+            .build();
+        widget.append(&filter_row);
+        widget.append(
+            &gtk::ScrolledWindow::builder()
+                .child(&column_view)
+                .hexpand(true)
+                .vexpand(true)
+                .build(),
+        );
+        widget.append(&status);
+        widget.connect_map(glib::clone!(
+            #[weak]
+            store,
+            #[weak]
+            sort_model,
+            #[weak]
+            status,
+            move |_| update_list(&store, &sort_model, &status, pid)
+        ));
+
+        Self {
+            widget,
+            pid,
+            store,
+            sort_model,
+            status,
+        }
+    }
+
+    /// Reads the files opened by the process again.
+    pub fn update(&self) {
+        update_list(&self.store, &self.sort_model, &self.status, self.pid);
+    }
+}
+
+fn update_list(
+    store: &gio::ListStore,
+    sort_model: &gtk::SortListModel,
+    status: &gtk::Label,
+    pid: Pid,
+) {
+    let files = match open_files(pid) {
+        Ok(files) => files,
+        Err(error) => {
+            store.remove_all();
+            status.set_text(&describe_process_error(&error, "list the files opened by"));
+            return;
+        }
+    };
+    status.set_text(&format!("{} open files", files.len()));
+
+    update_store(
+        store,
+        files.iter().map(|file| (file.fd, file)).collect(),
+        OpenFileRow::fd,
+        OpenFileRow::update,
+        |(_, file)| OpenFileRow::new(file),
+    );
+    sort_again(sort_model);
+}
+
+#[cfg(all(test, any(target_os = "linux", target_os = "android")))]
+mod tests {
+    use super::*;
+    use crate::test_support::fixture;
+
+    #[test]
+    fn kinds() {
+        for (target, kind) in [
+            ("/var/log/x (deleted)", "deleted file"),
+            ("socket:[1]", "socket"),
+            ("anon_inode:[eventfd]", "anon inode"),
+            ("pipe:[48213]", "pipe"),
+            ("/home/user/notes (1).txt", "file"),
+        ] {
+            let file = OpenFile {
+                fd: 3,
+                target: target.to_owned(),
+                flags: None,
+            };
+            assert_eq!(file.kind(), kind, "{target}");
+        }
+    }
+
+    #[test]
+    fn fdinfo_flags() {
+        assert_eq!(parse_flags(&fixture("proc_pid/fdinfo")), Some(0o2102001));
+        assert_eq!(parse_flags("pos:\t0\nmnt_id:\t26\n"), None);
+        assert_eq!(parse_flags("flags:\t0800\n"), None);
+    }
+
+    #[test]
+    fn flag_names() {
+        let describe = |flags: libc::c_int| describe_flags(flags as u32);
+        assert_eq!(describe(libc::O_RDONLY), "O_RDONLY");
+        assert_eq!(
+            describe(libc::O_WRONLY | libc::O_APPEND | libc::O_CLOEXEC),
+            "O_WRONLY | O_APPEND | O_CLOEXEC"
+        );
+        // `O_SYNC` and `O_TMPFILE` include the bits of `O_DSYNC` and `O_DIRECTORY`.
+        assert_eq!(describe(libc::O_RDWR | libc::O_SYNC), "O_RDWR | O_SYNC");
+        assert_eq!(describe(libc::O_RDWR | libc::O_DSYNC), "O_RDWR | O_DSYNC");
+        assert_eq!(
+            describe(libc::O_RDWR | libc::O_TMPFILE),
+            "O_RDWR | O_TMPFILE"
+        );
+        assert_eq!(
+            describe(libc::O_RDONLY | libc::O_DIRECTORY),
+            "O_RDONLY | O_DIRECTORY"
+        );
+    }
+}
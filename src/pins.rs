@@ -0,0 +1,129 @@
+use gtk::glib;
+use gtk::prelude::*;
+use regex::Regex;
+use serde_derive::{Deserialize, Serialize};
+use sysinfo::Pid;
+
+use std::collections::HashSet;
+
+/// A process pinned at the top of the processes list.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PinnedProcess {
+    Pid(u32),
+    /// A regular expression matched against the process name. Unlike a PID, it keeps matching
+    /// when the process is restarted.
+    Pattern(String),
+}
+
+/// The pinned processes of the settings, ready to be matched against processes.
+#[derive(Default)]
+pub struct Pins {
+    pids: HashSet<Pid>,
+    patterns: Vec<Regex>,
+}
+
+impl Pins {
+    pub fn new(pinned: &[PinnedProcess]) -> Self {
+        let mut pins = Self::default();
+        for pin in pinned {
+            match pin {
+                PinnedProcess::Pid(pid) => {
+                    pins.pids.insert(Pid::from_u32(*pid));
+                }
+                // Patterns are checked before being added, so an invalid one can only come from
+                // a manually edited settings file.
+                PinnedProcess::Pattern(pattern) => pins.patterns.extend(Regex::new(pattern).ok()),
+            }
+        }
+        pins
+    }
+
+    pub fn is_pinned(&self, pid: Pid, name: &str) -> bool {
+        self.pids.contains(&pid) || self.patterns.iter().any(|p| p.is_match(name))
+    }
+}
+
+/// Pins the processes `pids`.
+pub fn pin_pids(pinned: &mut Vec<PinnedProcess>, pids: &[Pid]) {
+    for pid in pids {
+        let pin = PinnedProcess::Pid(pid.as_u32());
+        if !pinned.contains(&pin) {
+            pinned.push(pin);
+        }
+    }
+}
+
+/// Unpins the given processes (with their name), whether they are pinned by PID or by a pattern.
+pub fn unpin(pinned: &mut Vec<PinnedProcess>, processes: &[(Pid, String)]) {
+    pinned.retain(|pin| match pin {
+        PinnedProcess::Pid(pid) => !processes.iter().any(|(p, _)| p.as_u32() == *pid),
+        PinnedProcess::Pattern(pattern) => !Regex::new(pattern)
+            .is_ok_and(|regex| processes.iter().any(|(_, name)| regex.is_match(name))),
+    });
+}
+
+/// Asks for a pattern matching the names of the processes to pin. `name` is used to fill the
+/// pattern by default.
+pub fn ask_pin_pattern<T: IsA<gtk::Window>, F: Fn(String) + 'static>(
+    window: &T,
+    name: Option<&str>,
+    on_pin: F,
+) {
+    let dialog = gtk::Dialog::with_buttons(
+        Some("Pin processes by name"),
+        Some(window),
+        gtk::DialogFlags::MODAL,
+        &[
+            ("Pin", gtk::ResponseType::Other(0)),
+            ("Cancel", gtk::ResponseType::Close),
+        ],
+    );
+    let layout = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(6)
+        .css_classes(vec!["button-with-margin".to_owned()])
+        .build();
+    let input = gtk::Entry::builder()
+        .text(
+            name.map(|name| format!("^{}$", regex::escape(name)))
+                .unwrap_or_default(),
+        )
+        .hexpand(true)
+        .build();
+    layout.append(&gtk::Label::new(Some(
+        "Regular expression matching the process name:",
+    )));
+    layout.append(&input);
+
+    let update_sensitivity = glib::clone!(
+        #[weak]
+        dialog,
+        move |input: &gtk::Entry| {
+            let text = input.text();
+            let valid = Regex::new(&text).is_ok();
+            if valid {
+                input.remove_css_class("error");
+            } else {
+                input.add_css_class("error");
+            }
+            dialog.set_response_sensitive(gtk::ResponseType::Other(0), valid && !text.is_empty());
+        }
+    );
+    update_sensitivity(&input);
+    input.connect_changed(update_sensitivity);
+
+    dialog.connect_response(glib::clone!(
+        #[weak]
+        input,
+        move |dialog, response| {
+            if response == gtk::ResponseType::Other(0) {
+                on_pin(input.text().to_string());
+            }
+            dialog.close();
+        }
+    ));
+
+    dialog.content_area().append(&layout);
+    dialog.show();
+}
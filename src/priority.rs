@@ -0,0 +1,593 @@
+use gtk::glib;
+use gtk::prelude::*;
+use sysinfo::Pid;
+
+use crate::signals::show_results;
+
+use std::cell::Cell;
+use std::io;
+use std::rc::Rc;
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+unsafe fn errno_location() -> *mut libc::c_int {
+    libc::__errno_location()
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd"))]
+unsafe fn errno_location() -> *mut libc::c_int {
+    libc::__error()
+}
+
+/// Returns the nice value of the given process.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd"
+))]
+pub fn nice_value(pid: Pid) -> Option<i32> {
+    // `getpriority` can return -1 as a valid value so we need to check `errno` to know if it
+    // failed.
+    unsafe {
+        *errno_location() = 0;
+        let nice = libc::getpriority(libc::PRIO_PROCESS, pid.as_u32() as libc::id_t);
+        if nice == -1 && *errno_location() != 0 {
+            None
+        } else {
+            Some(nice)
+        }
+    }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd"
+)))]
+pub fn nice_value(_pid: Pid) -> Option<i32> {
+    None
+}
+
+/// Converts the error returned by a priority-related syscall into a message users can
+/// understand.
+pub fn explain_error(pid: Pid, what: &str, error: io::Error) -> String {
+    match error.raw_os_error() {
+        Some(libc::EPERM) | Some(libc::EACCES) => format!(
+            "You are not allowed to change the {what} of process {pid}. Raising the priority of \
+             a process or changing the priority of a process owned by another user requires \
+             administrator privileges."
+        ),
+        Some(libc::ESRCH) => format!("Process {pid} doesn't exist anymore"),
+        Some(libc::EINVAL) => format!("This {what} is not valid for process {pid}"),
+        _ => format!("Failed to change the {what} of process {pid}: {error}"),
+    }
+}
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd"
+))]
+pub fn set_nice_value(pid: Pid, nice: i32) -> Result<(), String> {
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, pid.as_u32() as libc::id_t, nice) } == 0 {
+        Ok(())
+    } else {
+        Err(explain_error(pid, "nice value", io::Error::last_os_error()))
+    }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd"
+)))]
+pub fn set_nice_value(_pid: Pid, _nice: i32) -> Result<(), String> {
+    Err("Changing the nice value is not supported on this platform".to_owned())
+}
+
+/// Linux scheduling policies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SchedulingPolicy {
+    Other,
+    Batch,
+    Idle,
+    Fifo,
+    RoundRobin,
+}
+
+impl SchedulingPolicy {
+    pub const ALL: &'static [SchedulingPolicy] = &[
+        SchedulingPolicy::Other,
+        SchedulingPolicy::Batch,
+        SchedulingPolicy::Idle,
+        SchedulingPolicy::Fifo,
+        SchedulingPolicy::RoundRobin,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Other => "SCHED_OTHER (normal)",
+            Self::Batch => "SCHED_BATCH (batch)",
+            Self::Idle => "SCHED_IDLE (idle)",
+            Self::Fifo => "SCHED_FIFO (real-time)",
+            Self::RoundRobin => "SCHED_RR (real-time)",
+        }
+    }
+
+    /// Real-time policies use a static priority instead of the nice value.
+    pub fn is_real_time(self) -> bool {
+        matches!(self, Self::Fifo | Self::RoundRobin)
+    }
+}
+
+/// Returns the scheduling policy of the process and its real-time priority.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn scheduling_policy(pid: Pid) -> Option<(SchedulingPolicy, i32)> {
+    let pid = pid.as_u32() as libc::pid_t;
+    let policy = unsafe { libc::sched_getscheduler(pid) };
+    if policy < 0 {
+        return None;
+    }
+    let policy = match policy & !libc::SCHED_RESET_ON_FORK {
+        libc::SCHED_OTHER => SchedulingPolicy::Other,
+        libc::SCHED_BATCH => SchedulingPolicy::Batch,
+        libc::SCHED_IDLE => SchedulingPolicy::Idle,
+        libc::SCHED_FIFO => SchedulingPolicy::Fifo,
+        libc::SCHED_RR => SchedulingPolicy::RoundRobin,
+        _ => return None,
+    };
+    let mut param = libc::sched_param { sched_priority: 0 };
+    if unsafe { libc::sched_getparam(pid, &mut param) } != 0 {
+        return None;
+    }
+    Some((policy, param.sched_priority))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn scheduling_policy(_pid: Pid) -> Option<(SchedulingPolicy, i32)> {
+    None
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn set_scheduling_policy(
+    pid: Pid,
+    policy: SchedulingPolicy,
+    priority: i32,
+) -> Result<(), String> {
+    let c_policy = match policy {
+        SchedulingPolicy::Other => libc::SCHED_OTHER,
+        SchedulingPolicy::Batch => libc::SCHED_BATCH,
+        SchedulingPolicy::Idle => libc::SCHED_IDLE,
+        SchedulingPolicy::Fifo => libc::SCHED_FIFO,
+        SchedulingPolicy::RoundRobin => libc::SCHED_RR,
+    };
+    let param = libc::sched_param {
+        sched_priority: if policy.is_real_time() { priority } else { 0 },
+    };
+    if unsafe { libc::sched_setscheduler(pid.as_u32() as _, c_policy, &param) } == 0 {
+        Ok(())
+    } else {
+        Err(explain_error(
+            pid,
+            "scheduling policy",
+            io::Error::last_os_error(),
+        ))
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn set_scheduling_policy(
+    _pid: Pid,
+    _policy: SchedulingPolicy,
+    _priority: i32,
+) -> Result<(), String> {
+    Err("Changing the scheduling policy is not supported on this platform".to_owned())
+}
+
+/// Linux I/O scheduling classes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IoClass {
+    RealTime,
+    BestEffort,
+    Idle,
+}
+
+impl IoClass {
+    pub const ALL: &'static [IoClass] = &[IoClass::RealTime, IoClass::BestEffort, IoClass::Idle];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::RealTime => "realtime",
+            Self::BestEffort => "best-effort",
+            Self::Idle => "idle",
+        }
+    }
+
+    /// The idle class doesn't have levels.
+    pub fn has_level(self) -> bool {
+        !matches!(self, Self::Idle)
+    }
+}
+
+/// The I/O priority of a process. For the classes with levels, 0 is the highest priority and 7
+/// the lowest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, glib::Boxed)]
+#[boxed_type(name = "IoPriority", nullable)]
+pub struct IoPriority {
+    pub class: IoClass,
+    pub level: u8,
+}
+
+impl IoPriority {
+    /// A key ordering I/O priorities from the highest to the lowest.
+    pub fn sort_key(self) -> u32 {
+        let class = match self.class {
+            IoClass::RealTime => 0,
+            IoClass::BestEffort => 1,
+            IoClass::Idle => 2,
+        };
+        class * 8 + self.level as u32
+    }
+}
+
+impl std::fmt::Display for IoPriority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.class.has_level() {
+            write!(f, "{} {}", self.class.name(), self.level)
+        } else {
+            f.write_str(self.class.name())
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod ioprio {
+    // Not provided by `libc`, see `linux/ioprio.h`.
+    pub const WHO_PROCESS: libc::c_int = 1;
+    pub const CLASS_SHIFT: libc::c_int = 13;
+    pub const CLASS_NONE: libc::c_int = 0;
+    pub const CLASS_RT: libc::c_int = 1;
+    pub const CLASS_BE: libc::c_int = 2;
+    pub const CLASS_IDLE: libc::c_int = 3;
+    pub const LEVEL_MASK: libc::c_int = (1 << CLASS_SHIFT) - 1;
+}
+
+/// Returns the I/O priority of the process.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn io_priority(pid: Pid) -> Option<IoPriority> {
+    let value = unsafe {
+        libc::syscall(
+            libc::SYS_ioprio_get,
+            ioprio::WHO_PROCESS,
+            pid.as_u32() as libc::c_int,
+        )
+    };
+    if value < 0 {
+        return None;
+    }
+    let value = value as libc::c_int;
+    let level = (value & ioprio::LEVEL_MASK) as u8;
+    match value >> ioprio::CLASS_SHIFT {
+        ioprio::CLASS_RT => Some(IoPriority {
+            class: IoClass::RealTime,
+            level,
+        }),
+        ioprio::CLASS_BE => Some(IoPriority {
+            class: IoClass::BestEffort,
+            level,
+        }),
+        ioprio::CLASS_IDLE => Some(IoPriority {
+            class: IoClass::Idle,
+            level: 0,
+        }),
+        // When no I/O priority was set, the kernel derives it from the nice value.
+        ioprio::CLASS_NONE => {
+            let nice = nice_value(pid).unwrap_or(0).clamp(-20, 19);
+            Some(IoPriority {
+                class: IoClass::BestEffort,
+                level: ((nice + 20) / 5) as u8,
+            })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn io_priority(_pid: Pid) -> Option<IoPriority> {
+    None
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn set_io_priority(pid: Pid, priority: IoPriority) -> Result<(), String> {
+    let class = match priority.class {
+        IoClass::RealTime => ioprio::CLASS_RT,
+        IoClass::BestEffort => ioprio::CLASS_BE,
+        IoClass::Idle => ioprio::CLASS_IDLE,
+    };
+    let level = if priority.class.has_level() {
+        priority.level.min(7) as libc::c_int
+    } else {
+        0
+    };
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_ioprio_set,
+            ioprio::WHO_PROCESS,
+            pid.as_u32() as libc::c_int,
+            (class << ioprio::CLASS_SHIFT) | level,
+        )
+    };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(explain_error(
+            pid,
+            "I/O priority",
+            io::Error::last_os_error(),
+        ))
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn set_io_priority(_pid: Pid, _priority: IoPriority) -> Result<(), String> {
+    Err("Changing the I/O priority is not supported on this platform".to_owned())
+}
+
+/// Returns a short description of the process' priority.
+pub fn describe_priority(pid: Pid) -> String {
+    let nice = nice_value(pid).map(|nice| format!("nice {nice}"));
+    let policy = scheduling_policy(pid).map(|(policy, priority)| {
+        if policy.is_real_time() {
+            format!("{}, priority {priority}", policy.name())
+        } else {
+            policy.name().to_owned()
+        }
+    });
+    let io = io_priority(pid).map(|io| format!("I/O {io}"));
+    let parts = [nice, policy, io].into_iter().flatten().collect::<Vec<_>>();
+    if parts.is_empty() {
+        "unknown".to_owned()
+    } else {
+        parts.join(", ")
+    }
+}
+
+/// Shows a dialog to change the nice value, the scheduling policy and the I/O priority of the
+/// processes `pids`.
+/// The current values displayed are the ones of the first process.
+pub fn show_priority_dialog<T: IsA<gtk::Window>>(window: &T, pids: Vec<Pid>) {
+    let Some(first) = pids.first().copied() else {
+        return;
+    };
+    let title = if pids.len() == 1 {
+        format!("Priority of process {first}")
+    } else {
+        format!("Priority of {} processes", pids.len())
+    };
+    let dialog = gtk::Dialog::with_buttons(
+        Some(&title),
+        Some(window),
+        gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT,
+        &[
+            ("Apply", gtk::ResponseType::Apply),
+            ("Cancel", gtk::ResponseType::Cancel),
+        ],
+    );
+    let grid = gtk::Grid::builder()
+        .column_spacing(4)
+        .row_spacing(4)
+        .css_classes(vec!["button-with-margin".to_owned()])
+        .build();
+
+    let add_row = |top: i32, label: &str, widget: &gtk::Widget| {
+        grid.attach(
+            &gtk::Label::builder()
+                .label(label)
+                .halign(gtk::Align::Start)
+                .hexpand(true)
+                .build(),
+            0,
+            top,
+            1,
+            1,
+        );
+        grid.attach(widget, 1, top, 1, 1);
+    };
+
+    let current = gtk::Label::new(Some(&describe_priority(first)));
+    add_row(0, "Current priority", current.upcast_ref());
+
+    // Lower values mean higher priority.
+    let nice = gtk::SpinButton::with_range(-20., 19., 1.);
+    nice.set_value(nice_value(first).unwrap_or(0) as _);
+    add_row(1, "Nice value", nice.upcast_ref());
+
+    let current_policy = scheduling_policy(first);
+    let policy = gtk::DropDown::from_strings(
+        &SchedulingPolicy::ALL
+            .iter()
+            .map(|p| p.name())
+            .collect::<Vec<_>>(),
+    );
+    let real_time_priority = gtk::SpinButton::with_range(1., 99., 1.);
+    if let Some((current_policy, priority)) = current_policy {
+        if let Some(pos) = SchedulingPolicy::ALL
+            .iter()
+            .position(|p| *p == current_policy)
+        {
+            policy.set_selected(pos as _);
+        }
+        real_time_priority.set_value(priority.max(1) as _);
+    }
+    // Scheduling policies are only available on Linux.
+    policy.set_sensitive(current_policy.is_some());
+    add_row(2, "Scheduling policy", policy.upcast_ref());
+    add_row(3, "Real-time priority", real_time_priority.upcast_ref());
+
+    let update_sensitivity = glib::clone!(
+        #[weak]
+        nice,
+        #[weak]
+        real_time_priority,
+        move |policy: &gtk::DropDown| {
+            let is_real_time = SchedulingPolicy::ALL
+                .get(policy.selected() as usize)
+                .is_some_and(|p| p.is_real_time());
+            // The nice value isn't used by real-time policies.
+            nice.set_sensitive(!is_real_time);
+            real_time_priority.set_sensitive(policy.is_sensitive() && is_real_time);
+        }
+    );
+    update_sensitivity(&policy);
+    policy.connect_selected_notify(update_sensitivity);
+
+    let current_io = io_priority(first);
+    let io_class =
+        gtk::DropDown::from_strings(&IoClass::ALL.iter().map(|c| c.name()).collect::<Vec<_>>());
+    let io_level = gtk::SpinButton::with_range(0., 7., 1.);
+    if let Some(current_io) = current_io {
+        if let Some(pos) = IoClass::ALL.iter().position(|c| *c == current_io.class) {
+            io_class.set_selected(pos as _);
+        }
+        io_level.set_value(current_io.level as _);
+    } else {
+        io_class.set_selected(1);
+        io_level.set_value(4.);
+    }
+    // I/O priorities are only available on Linux.
+    io_class.set_sensitive(current_io.is_some());
+    add_row(4, "I/O class", io_class.upcast_ref());
+    add_row(5, "I/O level (0 is the highest)", io_level.upcast_ref());
+
+    let update_io_sensitivity = glib::clone!(
+        #[weak]
+        io_level,
+        move |io_class: &gtk::DropDown| {
+            io_level.set_sensitive(
+                io_class.is_sensitive()
+                    && IoClass::ALL
+                        .get(io_class.selected() as usize)
+                        .is_some_and(|c| c.has_level()),
+            );
+        }
+    );
+    update_io_sensitivity(&io_class);
+    io_class.connect_selected_notify(update_io_sensitivity);
+
+    // The controls show the values of the first process, so only the ones the user changed are
+    // applied: the other processes keep their own values.
+    let nice_changed = Rc::new(Cell::new(false));
+    let policy_changed = Rc::new(Cell::new(false));
+    let io_changed = Rc::new(Cell::new(false));
+    nice.connect_value_changed(glib::clone!(
+        #[strong]
+        nice_changed,
+        move |_| nice_changed.set(true)
+    ));
+    policy.connect_selected_notify(glib::clone!(
+        #[strong]
+        policy_changed,
+        move |_| policy_changed.set(true)
+    ));
+    real_time_priority.connect_value_changed(glib::clone!(
+        #[strong]
+        policy_changed,
+        move |_| policy_changed.set(true)
+    ));
+    io_class.connect_selected_notify(glib::clone!(
+        #[strong]
+        io_changed,
+        move |_| io_changed.set(true)
+    ));
+    io_level.connect_value_changed(glib::clone!(
+        #[strong]
+        io_changed,
+        move |_| io_changed.set(true)
+    ));
+
+    dialog.connect_response(glib::clone!(
+        #[weak]
+        nice,
+        #[weak]
+        policy,
+        #[weak]
+        real_time_priority,
+        #[weak]
+        io_class,
+        #[weak]
+        io_level,
+        move |dialog, response| {
+            dialog.close();
+            if response != gtk::ResponseType::Apply {
+                return;
+            }
+            let new_policy = SchedulingPolicy::ALL
+                .get(policy.selected() as usize)
+                .copied()
+                .filter(|_| policy.is_sensitive());
+            let new_priority = real_time_priority.value_as_int();
+            let new_io = IoClass::ALL
+                .get(io_class.selected() as usize)
+                .filter(|_| io_class.is_sensitive() && io_changed.get())
+                .map(|class| IoPriority {
+                    class: *class,
+                    level: if class.has_level() {
+                        io_level.value_as_int() as u8
+                    } else {
+                        0
+                    },
+                });
+            let results = pids
+                .iter()
+                .map(|pid| {
+                    let mut result = Ok(());
+                    if let Some(new_policy) = new_policy.filter(|_| policy_changed.get()) {
+                        // The priority is only used by the real-time policies, the other ones
+                        // report 0.
+                        let unchanged =
+                            scheduling_policy(*pid).is_some_and(|(policy, priority)| {
+                                policy == new_policy
+                                    && (!policy.is_real_time() || priority == new_priority)
+                            });
+                        if !unchanged {
+                            result = set_scheduling_policy(*pid, new_policy, new_priority);
+                        }
+                    }
+                    if result.is_ok()
+                        && nice_changed.get()
+                        && !new_policy.is_some_and(|p| p.is_real_time())
+                    {
+                        result = set_nice_value(*pid, nice.value_as_int());
+                    }
+                    // The I/O priority doesn't depend on the CPU scheduling, it is changed even if
+                    // the steps above failed, and each failure is reported.
+                    let mut errors = result.err().into_iter().collect::<Vec<_>>();
+                    if let Some(new_io) = new_io {
+                        // Setting the same value again would fail for processes we cannot
+                        // change, which would be confusing.
+                        if io_priority(*pid) != Some(new_io) {
+                            errors.extend(set_io_priority(*pid, new_io).err());
+                        }
+                    }
+                    let result = if errors.is_empty() {
+                        Ok(())
+                    } else {
+                        Err(errors.join("\n"))
+                    };
+                    (*pid, result)
+                })
+                .collect::<Vec<_>>();
+            show_results("Changed priority", &results);
+        }
+    ));
+
+    dialog.content_area().append(&grid);
+    dialog.show();
+}
@@ -5,29 +5,48 @@ use sysinfo::Pid;
 use std::cell::{Cell, RefCell};
 use std::fmt;
 use std::iter;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
+use crate::collector::ProcessInfo;
 use crate::graph::GraphWidget;
+use crate::memory_maps::{memory_rollup, MemoryMapsList, MemoryUsage};
 use crate::notebook::NoteBook;
+use crate::open_files::OpenFilesList;
+use crate::priority::{describe_priority, show_priority_dialog};
+use crate::property_list::PropertyList;
+use crate::sockets::SocketsList;
+use crate::threads::ThreadsList;
 use crate::utils::{format_number, get_main_window, graph_label_units, RotateVec};
 
 #[allow(dead_code)]
 pub struct ProcDialog {
-    working_directory: gtk::Label,
     memory_usage: gtk::Label,
     disk_usage: gtk::Label,
     cpu_usage: gtk::Label,
     run_time: gtk::Label,
+    priority: gtk::Label,
+    working_directory: gtk::Label,
     pub popup: gtk::Window,
     pub pid: Pid,
+    // With the PID, identifies the process: PIDs are reused once a process ended.
+    start_time: u64,
+    priority_button: gtk::Button,
     notebook: NoteBook,
     ram_usage_history: Rc<RefCell<GraphWidget>>,
     cpu_usage_history: Rc<RefCell<GraphWidget>>,
     disk_usage_history: Rc<RefCell<GraphWidget>>,
     memory_peak: RefCell<u64>,
     memory_peak_label: gtk::Label,
+    pss: gtk::Label,
+    uss: gtk::Label,
+    swap: gtk::Label,
     disk_peak: RefCell<u64>,
     disk_peak_label: gtk::Label,
+    open_files: OpenFilesList,
+    memory_maps: MemoryMapsList,
+    threads: ThreadsList,
+    sockets: SocketsList,
     pub is_dead: bool,
     pub to_be_removed: Rc<Cell<bool>>,
 }
@@ -39,25 +58,26 @@ impl fmt::Debug for ProcDialog {
 }
 
 impl ProcDialog {
-    pub fn update(&self, process: &sysinfo::Process) {
+    pub fn update(&self, process: &ProcessInfo) {
         if self.is_dead {
             return;
         }
-        self.working_directory.set_text(
-            &process
-                .cwd()
-                .map(|path| path.display().to_string())
-                .unwrap_or_default(),
-        );
-        let memory = process.memory();
+        let memory = process.memory;
         let memory_s = format_number(memory);
         self.memory_usage.set_text(&memory_s);
         if memory > *self.memory_peak.borrow() {
             *self.memory_peak.borrow_mut() = memory;
             self.memory_peak_label.set_text(&memory_s);
         }
-        let disk_usage = process.disk_usage();
-        let disk_usage = disk_usage.written_bytes + disk_usage.read_bytes;
+        // Reading the memory details is costly, it's only done while they are displayed, unless
+        // the snapshot already has them.
+        if process.memory_details.is_some() || self.pss.is_mapped() {
+            update_memory_details(
+                [&self.pss, &self.uss, &self.swap],
+                process.memory_details.or_else(|| memory_rollup(self.pid)),
+            );
+        }
+        let disk_usage = process.disk_usage;
         let disk_usage_s = format_number(disk_usage);
         self.disk_usage.set_text(&disk_usage_s);
         if disk_usage > *self.disk_peak.borrow() {
@@ -65,8 +85,12 @@ impl ProcDialog {
             self.disk_peak_label.set_text(&disk_usage_s);
         }
         self.cpu_usage
-            .set_text(&format!("{:.1}%", process.cpu_usage()));
-        self.run_time.set_text(&format_time(process.run_time()));
+            .set_text(&format!("{:.1}%", process.cpu_usage));
+        self.run_time.set_text(&format_time(process.run_time));
+        self.priority.set_text(&describe_priority(process.pid));
+        if let Some(cwd) = read_working_directory(self.pid) {
+            self.working_directory.set_text(&display_path(Some(&cwd)));
+        }
 
         let t = self.ram_usage_history.borrow_mut();
         t.data(0, |d| {
@@ -77,7 +101,7 @@ impl ProcDialog {
         let t = self.cpu_usage_history.borrow_mut();
         t.data(0, |d| {
             d.move_start();
-            *d.get_mut(0).expect("cannot get data 0") = process.cpu_usage();
+            *d.get_mut(0).expect("cannot get data 0") = process.cpu_usage;
         });
         t.queue_draw();
         let t = self.disk_usage_history.borrow_mut();
@@ -86,6 +110,26 @@ impl ProcDialog {
             *d.get_mut(0).expect("cannot get data 0") = disk_usage as f32;
         });
         t.queue_draw();
+
+        // Reading the open files, the memory maps and the sockets is costly, it's only done when
+        // they are displayed.
+        if self.open_files.widget.is_mapped() {
+            self.open_files.update();
+        }
+        self.memory_maps.set_memory_usage(memory);
+        if self.memory_maps.widget.is_mapped() {
+            self.memory_maps.update();
+        }
+        if self.sockets.widget.is_mapped() {
+            self.sockets.update();
+        }
+        self.threads.update();
+    }
+
+    /// Returns `true` if `process` is the one displayed by this dialog and not a new process
+    /// which got the same PID.
+    pub fn is_same_process(&self, process: &ProcessInfo) -> bool {
+        process.pid == self.pid && process.start_time == self.start_time
     }
 
     pub fn need_remove(&self) -> bool {
@@ -97,7 +141,10 @@ impl ProcDialog {
             return;
         }
         self.is_dead = true;
+        // The PID might be used by another process now.
+        self.priority_button.set_sensitive(false);
         self.memory_usage.set_text("0");
+        update_memory_details([&self.pss, &self.uss, &self.swap], None);
         self.disk_usage.set_text("0");
         self.cpu_usage.set_text("0%");
         let time = self.run_time.text();
@@ -137,6 +184,58 @@ fn format_time(t: u64) -> String {
     )
 }
 
+/// Displays the proportional, unique and swapped memory of `usage`, or nothing if it is unknown.
+fn update_memory_details([pss, uss, swap]: [&gtk::Label; 3], usage: Option<MemoryUsage>) {
+    let size = |value: fn(&MemoryUsage) -> u64| {
+        usage
+            .as_ref()
+            .map(|usage| format_number(value(usage)))
+            .unwrap_or_default()
+    };
+    pss.set_text(&size(|usage| usage.pss));
+    uss.set_text(&size(|usage| usage.private));
+    swap.set_text(&size(|usage| usage.swap));
+}
+
+/// Unknown paths are displayed empty.
+fn display_path(path: Option<&Path>) -> String {
+    path.map(|path| path.display().to_string())
+        .unwrap_or_default()
+}
+
+/// Reads the current working directory of the process `pid`, which can change while the dialog
+/// is open. `None` if it couldn't be read.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn read_working_directory(pid: Pid) -> Option<PathBuf> {
+    std::fs::read_link(format!("/proc/{pid}/cwd")).ok()
+}
+
+/// The processes aren't refreshed for their working directory, so the one read when the dialog
+/// was created is kept.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn read_working_directory(_pid: Pid) -> Option<PathBuf> {
+    None
+}
+
+fn cpu_usage_labels(v: f32) -> [String; 4] {
+    if v > 100. {
+        let nb = v.ceil() as u64;
+        [
+            nb.to_string(),
+            (nb / 2).to_string(),
+            "0".to_string(),
+            "%".to_string(),
+        ]
+    } else {
+        [
+            "100".to_string(),
+            "50".to_string(),
+            "0".to_string(),
+            "%".to_string(),
+        ]
+    }
+}
+
 fn create_and_add_new_label(scroll: &gtk::Box, title: &str, text: &str) -> gtk::Label {
     let horizontal_layout = gtk::Box::new(gtk::Orientation::Horizontal, 0);
 
@@ -161,26 +260,19 @@ fn create_and_add_new_label(scroll: &gtk::Box, title: &str, text: &str) -> gtk::
     text
 }
 
-fn append_text_column(tree: &gtk::TreeView, pos: i32) {
-    let column = gtk::TreeViewColumn::new();
-    let cell = gtk::CellRendererText::new();
-
-    column.pack_start(&cell, true);
-    column.add_attribute(&cell, "text", pos);
-    if pos == 1 {
-        cell.set_wrap_width(247);
-        cell.set_wrap_mode(pango::WrapMode::Char);
-        column.set_expand(true);
-    }
-    tree.append_column(&column);
-}
-
-pub fn create_process_dialog(process: &sysinfo::Process, total_memory: u64) -> ProcDialog {
+/// The current working directory, the root directory and the environment aren't part of the
+/// snapshots, they are read from `details` when the dialog is created. The working directory is
+/// read again on each update.
+pub fn create_process_dialog(
+    process: &ProcessInfo,
+    details: &sysinfo::Process,
+    total_memory: u64,
+) -> ProcDialog {
     let mut notebook = NoteBook::new();
 
     let popup = gtk::Window::new();
 
-    let name = process.name().to_string_lossy();
+    let name = process.name.to_string_lossy();
     popup.set_title(Some(&format!("Information about {name}")));
     popup.set_transient_for(get_main_window().as_ref());
     popup.set_destroy_with_parent(true);
@@ -194,19 +286,32 @@ pub fn create_process_dialog(process: &sysinfo::Process, total_memory: u64) -> P
     let vertical_layout = gtk::Box::new(gtk::Orientation::Vertical, 0);
     scroll.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
 
-    let running_since = process.run_time();
+    let running_since = process.run_time;
 
     let labels = gtk::Box::new(gtk::Orientation::Vertical, 0);
 
     create_and_add_new_label(&labels, "name", &name);
-    create_and_add_new_label(&labels, "pid", &process.pid().to_string());
-    let memory_peak = process.memory();
+    create_and_add_new_label(&labels, "pid", &process.pid.to_string());
+    let memory_peak = process.memory;
     let memory_usage =
         create_and_add_new_label(&labels, "memory usage", &format_number(memory_peak));
     let memory_peak_label =
         create_and_add_new_label(&labels, "memory usage peak", &format_number(memory_peak));
-    let disk_peak = process.disk_usage();
-    let disk_peak = disk_peak.written_bytes + disk_peak.read_bytes;
+    // Unlike the memory usage, these don't count the pages shared with other processes, or only
+    // their share of them.
+    let pss = create_and_add_new_label(&labels, "proportional memory (PSS)", "");
+    let uss = create_and_add_new_label(&labels, "unique memory (USS)", "");
+    let swap = create_and_add_new_label(&labels, "swapped memory", "");
+    // They are read when they are displayed, to be up to date when switching to their tab.
+    let pid = process.pid;
+    pss.connect_map(glib::clone!(
+        #[weak]
+        uss,
+        #[weak]
+        swap,
+        move |pss| update_memory_details([pss, &uss, &swap], memory_rollup(pid))
+    ));
+    let disk_peak = process.disk_usage;
     let s;
     #[cfg(not(any(windows, target_os = "freebsd")))]
     {
@@ -219,19 +324,17 @@ pub fn create_process_dialog(process: &sysinfo::Process, total_memory: u64) -> P
     let disk_usage = create_and_add_new_label(&labels, s, &format_number(disk_peak));
     let disk_peak_label =
         create_and_add_new_label(&labels, &format!("{s} peak"), &format_number(disk_peak));
-    let cpu_usage = create_and_add_new_label(
-        &labels,
-        "cpu usage",
-        &format!("{:.1}%", process.cpu_usage()),
-    );
+    let cpu_usage =
+        create_and_add_new_label(&labels, "cpu usage", &format!("{:.1}%", process.cpu_usage));
     let run_time = create_and_add_new_label(&labels, "Running since", &format_time(running_since));
+    let priority = create_and_add_new_label(&labels, "priority", &describe_priority(process.pid));
     create_and_add_new_label(
         &labels,
         "command",
         &format!(
             "[{}]",
             process
-                .cmd()
+                .cmd
                 .iter()
                 .map(|x| format!("\"{}\"", x.to_string_lossy()))
                 .collect::<Vec<_>>()
@@ -242,37 +345,21 @@ pub fn create_process_dialog(process: &sysinfo::Process, total_memory: u64) -> P
         &labels,
         "executable path",
         &process
-            .exe()
+            .exe
+            .as_ref()
             .map(|path| path.display().to_string())
             .unwrap_or_default(),
     );
     let working_directory = create_and_add_new_label(
         &labels,
         "current working directory",
-        &process
-            .cwd()
-            .map(|path| path.display().to_string())
-            .unwrap_or_default(),
-    );
-    create_and_add_new_label(
-        &labels,
-        "root directory",
-        &process
-            .root()
-            .map(|path| path.display().to_string())
-            .unwrap_or_default(),
+        &display_path(details.cwd()),
     );
+    create_and_add_new_label(&labels, "root directory", &display_path(details.root()));
 
-    let env_tree = gtk::TreeView::new();
-    let list_store = gtk::ListStore::new(&[glib::Type::STRING, glib::Type::STRING]);
+    let env_list = PropertyList::new(("variable", "value"), false);
 
-    env_tree.set_headers_visible(false);
-    env_tree.set_model(Some(&list_store));
-
-    append_text_column(&env_tree, 0);
-    append_text_column(&env_tree, 1);
-
-    for env in process.environ() {
+    for env in details.environ() {
         let env = env.to_string_lossy();
         let mut parts = env.splitn(2, '=');
         let name = match parts.next() {
@@ -280,26 +367,33 @@ pub fn create_process_dialog(process: &sysinfo::Process, total_memory: u64) -> P
             None => continue,
         };
         let value = parts.next().unwrap_or("");
-        list_store.insert_with_values(None, &[(0, &name), (1, &value)]);
+        env_list.append(name, value);
     }
 
     let components = gtk::Box::new(gtk::Orientation::Vertical, 0);
     components.append(&labels);
 
-    if !process.environ().is_empty() {
+    if !details.environ().is_empty() {
         let label = gtk::Label::new(None);
         label.set_markup("<b>Environment variables</b>");
 
         components.append(&label);
-        components.append(&env_tree);
+        components.append(&env_list.view);
     }
 
     scroll.set_child(Some(&components));
     scroll.set_hexpand(true);
     scroll.set_vexpand(true);
 
+    let priority_button = gtk::Button::with_label("Change priority...");
+    priority_button.add_css_class("button-with-margin");
+    let buttons = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+    buttons.set_homogeneous(true);
+    buttons.append(&priority_button);
+    buttons.append(&close_button);
+
     vertical_layout.append(&scroll);
-    vertical_layout.append(&close_button);
+    vertical_layout.append(&buttons);
 
     notebook.create_tab("Information", &vertical_layout);
 
@@ -326,27 +420,20 @@ pub fn create_process_dialog(process: &sysinfo::Process, total_memory: u64) -> P
     disk_usage_history.set_overhead(Some(20.));
 
     cpu_usage_history.push(RotateVec::new(iter::repeat_n(0f32, 61).collect()), "", None);
-    cpu_usage_history.set_labels_callback(Some(Box::new(|v| {
-        if v > 100. {
-            let nb = v.ceil() as u64;
-            [
-                nb.to_string(),
-                (nb / 2).to_string(),
-                "0".to_string(),
-                "%".to_string(),
-            ]
-        } else {
-            [
-                "100".to_string(),
-                "50".to_string(),
-                "0".to_string(),
-                "%".to_string(),
-            ]
-        }
-    })));
+    cpu_usage_history.set_labels_callback(Some(Box::new(cpu_usage_labels)));
     vertical_layout.append(&gtk::Label::new(Some("Process usage")));
     vertical_layout.append(&cpu_usage_history);
     cpu_usage_history.queue_draw();
+
+    // The usage of the thread selected in the threads list.
+    let thread_usage_title = gtk::Label::new(None);
+    let thread_usage_history = GraphWidget::new(Some(100.), false);
+    thread_usage_history.set_display_labels(false);
+    thread_usage_history.set_minimum(Some(100.));
+    thread_usage_history.push(RotateVec::new(iter::repeat_n(0f32, 61).collect()), "", None);
+    thread_usage_history.set_labels_callback(Some(Box::new(cpu_usage_labels)));
+    vertical_layout.append(&thread_usage_title);
+    vertical_layout.append(&thread_usage_history);
     // let cpu_usage_history = connect_graph(cpu_usage_history);
     let cpu_usage_history = Rc::new(RefCell::new(cpu_usage_history));
 
@@ -391,6 +478,15 @@ pub fn create_process_dialog(process: &sysinfo::Process, total_memory: u64) -> P
     ));
     notebook.create_tab("Resources usage", &scroll);
 
+    let open_files = OpenFilesList::new(pid);
+    notebook.create_tab("Files", &open_files.widget);
+    let memory_maps = MemoryMapsList::new(pid, process.memory);
+    notebook.create_tab("Memory maps", &memory_maps.widget);
+    let threads = ThreadsList::new(pid, &thread_usage_history, &thread_usage_title);
+    notebook.create_tab("Threads", &threads.widget);
+    let sockets = SocketsList::new(pid);
+    notebook.create_tab("Sockets", &sockets.widget);
+
     popup.set_child(Some(&notebook.notebook));
     popup.set_size_request(500, 600);
 
@@ -401,6 +497,13 @@ pub fn create_process_dialog(process: &sysinfo::Process, total_memory: u64) -> P
             popup.close();
         }
     ));
+    priority_button.connect_clicked(glib::clone!(
+        #[weak]
+        popup,
+        move |_| {
+            show_priority_dialog(&popup, vec![pid]);
+        }
+    ));
     let to_be_removed = Rc::new(Cell::new(false));
     popup.connect_destroy(glib::clone!(
         #[weak]
@@ -444,21 +547,31 @@ pub fn create_process_dialog(process: &sysinfo::Process, total_memory: u64) -> P
     scroll.set_vadjustment(Some(&adjust));
 
     ProcDialog {
-        working_directory,
         memory_usage,
         disk_usage,
         cpu_usage,
         run_time,
+        priority,
+        working_directory,
         popup,
-        pid: process.pid(),
+        pid: process.pid,
+        start_time: process.start_time,
+        priority_button,
         notebook,
         ram_usage_history,
         cpu_usage_history,
         disk_usage_history,
         memory_peak: RefCell::new(memory_peak),
         memory_peak_label,
+        pss,
+        uss,
+        swap,
         disk_peak: RefCell::new(disk_peak),
         disk_peak_label,
+        open_files,
+        memory_maps,
+        threads,
+        sockets,
         is_dead: false,
         to_be_removed,
     }
@@ -0,0 +1,532 @@
+// This module doesn't depend on GTK so it can be included by the benchmarks.
+
+use serde_derive::{Deserialize, Serialize};
+use sysinfo::Pid;
+
+use std::collections::{HashMap, HashSet};
+
+/// How the processes are displayed in the list.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ViewMode {
+    #[default]
+    List,
+    /// Processes are displayed under their parent.
+    Tree,
+    /// Processes with the same executable or name are displayed under a row summing their usage.
+    Grouped,
+}
+
+impl ViewMode {
+    pub const ALL: &'static [ViewMode] = &[ViewMode::List, ViewMode::Tree, ViewMode::Grouped];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::List => "List view",
+            Self::Tree => "Tree view",
+            Self::Grouped => "Grouped by application",
+        }
+    }
+
+    /// Threads are not displayed in the grouped mode, they would be counted as instances
+    /// otherwise.
+    pub fn displays<P: ProcessEntry>(self, process: &P) -> bool {
+        self != Self::Grouped || !process.is_userland_thread()
+    }
+}
+
+/// What the processes list needs to know about a process to place its row.
+pub trait ProcessEntry {
+    fn pid(&self) -> Pid;
+    fn parent(&self) -> Option<Pid>;
+    fn start_time(&self) -> u64;
+    fn cpu_usage(&self) -> f32;
+    fn memory(&self) -> u64;
+    /// Returns `true` if it is a thread of another process. The usage of threads is already
+    /// included in the one of their process.
+    fn is_userland_thread(&self) -> bool;
+    /// Name of the application under which the process is displayed in the grouped mode.
+    fn group_name(&self) -> String;
+}
+
+/// The row under which a row is displayed.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Parent {
+    Process(Pid),
+    /// The row of an application, in the grouped mode.
+    Group(String),
+}
+
+/// Returns the process under which `process` is displayed. In the list mode, or if its parent
+/// isn't known, the process is displayed at the top level.
+pub fn displayed_parent<P: ProcessEntry>(
+    process: &P,
+    entries: &HashMap<Pid, P>,
+    tree_mode: bool,
+) -> Option<Pid> {
+    if !tree_mode {
+        return None;
+    }
+    process
+        .parent()
+        .filter(|parent| *parent != process.pid() && entries.contains_key(parent))
+}
+
+fn displayed_under<P: ProcessEntry>(
+    process: &P,
+    entries: &HashMap<Pid, P>,
+    view_mode: ViewMode,
+) -> Option<Parent> {
+    match view_mode {
+        ViewMode::List => None,
+        ViewMode::Tree => displayed_parent(process, entries, true).map(Parent::Process),
+        ViewMode::Grouped => Some(Parent::Group(process.group_name())),
+    }
+}
+
+/// Computes the CPU and memory usage of each process and all its (displayed) descendants.
+pub fn compute_subtree_totals<P: ProcessEntry>(
+    entries: &HashMap<Pid, P>,
+    tree_mode: bool,
+) -> HashMap<Pid, (f32, u64)> {
+    let mut totals: HashMap<Pid, (f32, u64)> = HashMap::with_capacity(entries.len());
+
+    for (pid, p) in entries.iter() {
+        if p.is_userland_thread() {
+            totals.entry(*pid).or_insert((p.cpu_usage(), p.memory()));
+            continue;
+        }
+        let cpu = p.cpu_usage();
+        let memory = p.memory();
+        let mut current = Some(*pid);
+        // Just in case there is a loop in the parents, we don't want to loop forever.
+        let mut depth = 0;
+        while let Some(pid) = current {
+            let total = totals.entry(pid).or_default();
+            total.0 += cpu;
+            total.1 += memory;
+            depth += 1;
+            if depth > entries.len() {
+                break;
+            }
+            current = entries
+                .get(&pid)
+                .and_then(|p| displayed_parent(p, entries, tree_mode));
+        }
+    }
+    totals
+}
+
+struct IndexedRow<R> {
+    row: R,
+    start_time: u64,
+    parent: Option<Parent>,
+    /// The last update in which the process was still displayed at the same place.
+    generation: u64,
+    /// The fingerprint of the values the row was last updated with, `None` if it wasn't updated
+    /// since it was created.
+    fingerprint: Option<u64>,
+}
+
+/// The changes needed to display a new process table.
+pub struct Changes<'a, P, R> {
+    /// The rows of the processes still displayed at the same place whose values changed, which
+    /// only need to be updated.
+    pub updated: Vec<(R, &'a P)>,
+    /// The rows to remove, with the row they are displayed under. The rows displayed under a
+    /// removed row are removed as well, and added back.
+    pub removed: Vec<(R, Option<Parent>)>,
+    /// The processes to add, with the row they are displayed under. Parents come before their
+    /// children.
+    pub added: Vec<(&'a P, Option<Parent>)>,
+    /// The processes of each application, in the grouped mode.
+    pub groups: HashMap<String, Vec<&'a P>>,
+    /// The rows of the applications which don't have processes anymore.
+    pub removed_groups: Vec<R>,
+}
+
+/// A persistent index of the rows of the processes list. It allows to only update the rows of
+/// the processes which are still displayed at the same place, and to only add and remove the
+/// rows of the processes which started, ended or moved.
+pub struct ProcessIndex<R> {
+    rows: HashMap<Pid, IndexedRow<R>>,
+    groups: HashMap<String, R>,
+    generation: u64,
+}
+
+impl<R> Default for ProcessIndex<R> {
+    fn default() -> Self {
+        Self {
+            rows: HashMap::new(),
+            groups: HashMap::new(),
+            generation: 0,
+        }
+    }
+}
+
+impl<R: Clone> ProcessIndex<R> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn row(&self, pid: Pid) -> Option<&R> {
+        self.rows.get(&pid).map(|indexed| &indexed.row)
+    }
+
+    pub fn group(&self, name: &str) -> Option<&R> {
+        self.groups.get(name)
+    }
+
+    /// Returns the row under which the row of `pid` is displayed, `None` for the top level.
+    pub fn parent_of(&self, pid: Pid) -> Option<&Parent> {
+        self.rows
+            .get(&pid)
+            .and_then(|indexed| indexed.parent.as_ref())
+    }
+
+    /// Adds the row of a process, once the list displays it.
+    pub fn insert<P: ProcessEntry>(&mut self, process: &P, parent: Option<Parent>, row: R) {
+        self.rows.insert(
+            process.pid(),
+            IndexedRow {
+                row,
+                start_time: process.start_time(),
+                parent,
+                generation: self.generation,
+                fingerprint: None,
+            },
+        );
+    }
+
+    pub fn insert_group(&mut self, name: String, row: R) {
+        self.groups.insert(name, row);
+    }
+
+    /// Forgets all the rows, when the list is emptied.
+    pub fn clear(&mut self) {
+        self.rows.clear();
+        self.groups.clear();
+    }
+
+    /// Computes the changes needed to display `entries`. The removed rows are removed from the
+    /// index right away, while the added ones need to be inserted once they are created.
+    ///
+    /// `fingerprint` summarizes the values displayed in the row of a process: a row is only
+    /// updated if it changed since its last update.
+    pub fn diff<'a, P: ProcessEntry>(
+        &mut self,
+        entries: &'a HashMap<Pid, P>,
+        view_mode: ViewMode,
+        mut fingerprint: impl FnMut(&P) -> u64,
+    ) -> Changes<'a, P, R> {
+        self.generation += 1;
+        let generation = self.generation;
+        let mut added = Vec::new();
+
+        for (pid, p) in entries.iter().filter(|(_, p)| view_mode.displays(*p)) {
+            let parent = displayed_under(p, entries, view_mode);
+            match self.rows.get_mut(pid) {
+                // The PID could have been reused by a new process.
+                Some(indexed)
+                    if indexed.start_time == p.start_time() && indexed.parent == parent =>
+                {
+                    indexed.generation = generation;
+                }
+                _ => added.push((p, parent)),
+            }
+        }
+
+        let mut removed = Vec::new();
+        let mut removed_pids = HashSet::new();
+        self.rows.retain(|pid, indexed| {
+            if indexed.generation == generation {
+                return true;
+            }
+            removed_pids.insert(*pid);
+            removed.push((indexed.row.clone(), indexed.parent.take()));
+            false
+        });
+        // The descendants of a removed row are removed with it, so they need to be added back.
+        if view_mode == ViewMode::Tree && !removed_pids.is_empty() {
+            let mut children: HashMap<Pid, Vec<Pid>> = HashMap::new();
+            for (pid, indexed) in self.rows.iter() {
+                if let Some(Parent::Process(parent)) = indexed.parent {
+                    children.entry(parent).or_default().push(*pid);
+                }
+            }
+            let mut orphans = removed_pids
+                .iter()
+                .filter_map(|pid| children.get(pid))
+                .flatten()
+                .copied()
+                .collect::<Vec<_>>();
+            while let Some(pid) = orphans.pop() {
+                let Some(indexed) = self.rows.remove(&pid) else {
+                    continue;
+                };
+                removed.push((indexed.row, indexed.parent));
+                if let Some(p) = entries.get(&pid) {
+                    added.push((p, displayed_under(p, entries, view_mode)));
+                }
+                orphans.extend(children.get(&pid).into_iter().flatten().copied());
+            }
+        }
+
+        let mut updated = Vec::new();
+        for (pid, indexed) in self.rows.iter_mut() {
+            let Some(p) = entries.get(pid) else {
+                continue;
+            };
+            let values = Some(fingerprint(p));
+            if indexed.fingerprint != values {
+                indexed.fingerprint = values;
+                updated.push((indexed.row.clone(), p));
+            }
+        }
+
+        let mut groups: HashMap<String, Vec<&P>> = HashMap::new();
+        let mut removed_groups = Vec::new();
+        if view_mode == ViewMode::Grouped {
+            for p in entries.values().filter(|p| view_mode.displays(*p)) {
+                groups.entry(p.group_name()).or_default().push(p);
+            }
+            self.groups.retain(|name, row| {
+                let keep = groups.contains_key(name);
+                if !keep {
+                    removed_groups.push(row.clone());
+                }
+                keep
+            });
+        }
+
+        Changes {
+            updated,
+            removed,
+            added: self.parents_first(added),
+            groups,
+            removed_groups,
+        }
+    }
+
+    /// Sorts the processes to add so parents come before their children. The processes whose
+    /// parent can't be added (because of a loop in the parents) are put at the top level.
+    fn parents_first<'a, P: ProcessEntry>(
+        &self,
+        mut missing: Vec<(&'a P, Option<Parent>)>,
+    ) -> Vec<(&'a P, Option<Parent>)> {
+        let mut added = Vec::with_capacity(missing.len());
+        let mut placed = HashSet::with_capacity(missing.len());
+        while !missing.is_empty() {
+            let nb_missing = missing.len();
+            missing.retain(|(p, parent)| {
+                if let Some(Parent::Process(parent)) = parent {
+                    if !self.rows.contains_key(parent) && !placed.contains(parent) {
+                        return true;
+                    }
+                }
+                placed.insert(p.pid());
+                added.push((*p, parent.clone()));
+                false
+            });
+            if missing.len() == nb_missing {
+                for (p, _) in missing.drain(..) {
+                    added.push((p, None));
+                }
+            }
+        }
+        added
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestProcess {
+        pid: Pid,
+        parent: Option<Pid>,
+        start_time: u64,
+        cpu_usage: f32,
+    }
+
+    impl ProcessEntry for TestProcess {
+        fn pid(&self) -> Pid {
+            self.pid
+        }
+
+        fn parent(&self) -> Option<Pid> {
+            self.parent
+        }
+
+        fn start_time(&self) -> u64 {
+            self.start_time
+        }
+
+        fn cpu_usage(&self) -> f32 {
+            self.cpu_usage
+        }
+
+        fn memory(&self) -> u64 {
+            0
+        }
+
+        fn is_userland_thread(&self) -> bool {
+            false
+        }
+
+        fn group_name(&self) -> String {
+            "application".to_owned()
+        }
+    }
+
+    /// Creates a process table from `(pid, parent, start_time)` tuples.
+    fn table(processes: &[(usize, Option<usize>, u64)]) -> HashMap<Pid, TestProcess> {
+        processes
+            .iter()
+            .map(|&(pid, parent, start_time)| {
+                let process = TestProcess {
+                    pid: Pid::from(pid),
+                    parent: parent.map(Pid::from),
+                    start_time,
+                    cpu_usage: 0.,
+                };
+                (process.pid, process)
+            })
+            .collect()
+    }
+
+    /// Rows with the row they are displayed under.
+    type Placements = Vec<(usize, Option<Parent>)>;
+
+    /// Applies the changes needed to display `entries`, like the processes list does. The rows
+    /// are the PIDs. Returns the updated, removed and added rows.
+    fn update(
+        index: &mut ProcessIndex<usize>,
+        entries: &HashMap<Pid, TestProcess>,
+        view_mode: ViewMode,
+    ) -> (Vec<usize>, Placements, Placements) {
+        let changes = index.diff(entries, view_mode, |p| p.cpu_usage.to_bits().into());
+        let mut updated = changes
+            .updated
+            .iter()
+            .map(|(row, _)| *row)
+            .collect::<Vec<_>>();
+        updated.sort();
+        let mut removed = changes.removed;
+        removed.sort_by_key(|(row, _)| *row);
+        let added = changes
+            .added
+            .into_iter()
+            .map(|(p, parent)| {
+                index.insert(p, parent.clone(), p.pid.as_u32() as usize);
+                (p.pid.as_u32() as usize, parent)
+            })
+            .collect();
+        (updated, removed, added)
+    }
+
+    fn parent(pid: usize) -> Option<Parent> {
+        Some(Parent::Process(Pid::from(pid)))
+    }
+
+    #[test]
+    fn only_changed_rows_are_updated() {
+        let mut entries = table(&[(1, None, 0), (2, Some(1), 0), (3, Some(1), 0)]);
+        let mut index = ProcessIndex::new();
+        update(&mut index, &entries, ViewMode::List);
+        // The rows are updated once after their creation, their values could have changed since.
+        assert_eq!(update(&mut index, &entries, ViewMode::List).0, [1, 2, 3]);
+        assert_eq!(
+            update(&mut index, &entries, ViewMode::List),
+            (Vec::new(), Vec::new(), Vec::new()),
+        );
+
+        entries.get_mut(&Pid::from(2)).unwrap().cpu_usage = 12.5;
+        assert_eq!(
+            update(&mut index, &entries, ViewMode::List),
+            (vec![2], Vec::new(), Vec::new()),
+        );
+    }
+
+    #[test]
+    fn reparented_process_is_moved() {
+        let mut entries = table(&[(1, None, 0), (2, Some(1), 0), (3, Some(1), 0)]);
+        let mut index = ProcessIndex::new();
+        update(&mut index, &entries, ViewMode::Tree);
+        update(&mut index, &entries, ViewMode::Tree);
+
+        entries.get_mut(&Pid::from(3)).unwrap().parent = Some(Pid::from(2));
+        let (updated, removed, added) = update(&mut index, &entries, ViewMode::Tree);
+        assert!(updated.is_empty());
+        assert_eq!(removed, [(3, parent(1))]);
+        assert_eq!(added, [(3, parent(2))]);
+        assert_eq!(index.parent_of(Pid::from(3)), parent(2).as_ref());
+    }
+
+    #[test]
+    fn reused_pid_is_replaced() {
+        let mut entries = table(&[(1, None, 0), (2, None, 10)]);
+        let mut index = ProcessIndex::new();
+        update(&mut index, &entries, ViewMode::List);
+        update(&mut index, &entries, ViewMode::List);
+
+        entries.get_mut(&Pid::from(2)).unwrap().start_time = 20;
+        let (updated, removed, added) = update(&mut index, &entries, ViewMode::List);
+        assert!(updated.is_empty());
+        assert_eq!(removed, [(2, None)]);
+        assert_eq!(added, [(2, None)]);
+    }
+
+    #[test]
+    fn orphans_are_added_back() {
+        let mut entries = table(&[
+            (1, None, 0),
+            (2, Some(1), 0),
+            (3, Some(2), 0),
+            (4, Some(3), 0),
+        ]);
+        let mut index = ProcessIndex::new();
+        update(&mut index, &entries, ViewMode::Tree);
+        update(&mut index, &entries, ViewMode::Tree);
+
+        entries.remove(&Pid::from(2));
+        let (updated, removed, added) = update(&mut index, &entries, ViewMode::Tree);
+        assert!(updated.is_empty());
+        assert_eq!(removed, [(2, parent(1)), (3, parent(2)), (4, parent(3))]);
+        // The parent of 3 ended, so it is displayed at the top level.
+        assert_eq!(added, [(3, None), (4, parent(3))]);
+        assert!(index.row(Pid::from(2)).is_none());
+    }
+
+    #[test]
+    fn parents_are_added_first() {
+        let entries = table(&[
+            (5, Some(4), 0),
+            (4, Some(3), 0),
+            (3, Some(2), 0),
+            (2, Some(1), 0),
+            (1, None, 0),
+        ]);
+        let mut index = ProcessIndex::new();
+        let (_, _, added) = update(&mut index, &entries, ViewMode::Tree);
+        assert_eq!(
+            added,
+            [
+                (1, None),
+                (2, parent(1)),
+                (3, parent(2)),
+                (4, parent(3)),
+                (5, parent(4)),
+            ],
+        );
+    }
+
+    #[test]
+    fn parents_loop_is_added_at_top_level() {
+        let entries = table(&[(1, None, 0), (2, Some(3), 0), (3, Some(2), 0)]);
+        let mut index = ProcessIndex::new();
+        let (_, _, mut added) = update(&mut index, &entries, ViewMode::Tree);
+        added.sort_by_key(|(row, _)| *row);
+        assert_eq!(added, [(1, None), (2, None), (3, None)]);
+    }
+}
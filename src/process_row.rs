@@ -0,0 +1,206 @@
+use gtk::gio;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+
+use crate::display_procs::StateKind;
+use crate::priority::IoPriority;
+
+/// Value of the integer properties of a [`ProcessRow`] which couldn't be retrieved.
+pub const UNKNOWN: i64 = i64::MIN;
+
+mod imp {
+    use super::*;
+
+    use std::cell::{Cell, OnceCell, RefCell};
+
+    #[derive(Default, glib::Properties)]
+    #[properties(wrapper_type = super::ProcessRow)]
+    pub struct ProcessRow {
+        #[property(get)]
+        pub(super) pid: RefCell<u32>,
+        #[property(get)]
+        pub(super) name: RefCell<String>,
+        #[property(get)]
+        pub(super) command: RefCell<String>,
+        #[property(get)]
+        pub(super) exe: RefCell<String>,
+        /// The name of the application for the rows grouping processes, empty for processes.
+        #[property(get)]
+        pub(super) group: RefCell<String>,
+        #[property(get)]
+        pub(super) user: RefCell<String>,
+        #[property(get)]
+        pub(super) state: RefCell<String>,
+        #[property(get, builder(StateKind::Other))]
+        pub(super) state_kind: RefCell<StateKind>,
+        #[property(get)]
+        pub(super) kernel_thread: RefCell<bool>,
+        #[property(get)]
+        pub(super) pinned: RefCell<bool>,
+        #[property(get)]
+        pub(super) cpu: RefCell<f32>,
+        #[property(get)]
+        pub(super) memory: RefCell<u64>,
+        #[property(get)]
+        pub(super) disk_usage: RefCell<u64>,
+        #[property(get)]
+        pub(super) subtree_cpu: RefCell<f32>,
+        #[property(get)]
+        pub(super) subtree_memory: RefCell<u64>,
+        #[property(get, default = UNKNOWN)]
+        pub(super) threads: RefCell<i64>,
+        #[property(get)]
+        pub(super) start_time: RefCell<u64>,
+        #[property(get)]
+        pub(super) virtual_memory: RefCell<u64>,
+        #[property(get, default = UNKNOWN)]
+        pub(super) nice: RefCell<i64>,
+        #[property(get, nullable)]
+        pub(super) io_priority: RefCell<Option<IoPriority>>,
+        #[property(get, default = UNKNOWN)]
+        pub(super) parent_pid: RefCell<i64>,
+        #[property(get)]
+        pub(super) instances: RefCell<u32>,
+        /// Proportional set size.
+        #[property(get, default = UNKNOWN)]
+        pub(super) pss: RefCell<i64>,
+        /// Unique set size.
+        #[property(get, default = UNKNOWN)]
+        pub(super) uss: RefCell<i64>,
+        #[property(get, default = UNKNOWN)]
+        pub(super) swap: RefCell<i64>,
+        /// The rows displayed under this one.
+        pub(super) children: OnceCell<gio::ListStore>,
+        /// Whether the row had children when it was added to the list. The list only checks
+        /// whether a row can be expanded once.
+        pub(super) expandable: Cell<bool>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ProcessRow {
+        const NAME: &'static str = "ProcessRow";
+        type Type = super::ProcessRow;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for ProcessRow {
+        fn constructed(&self) {
+            self.parent_constructed();
+            // The `default` of the properties is only used by their `ParamSpec`.
+            self.threads.replace(UNKNOWN);
+            self.nice.replace(UNKNOWN);
+            self.parent_pid.replace(UNKNOWN);
+            self.pss.replace(UNKNOWN);
+            self.uss.replace(UNKNOWN);
+            self.swap.replace(UNKNOWN);
+        }
+    }
+}
+
+glib::wrapper! {
+    /// A row of the processes list: either a process or an application grouping processes.
+    pub struct ProcessRow(ObjectSubclass<imp::ProcessRow>);
+}
+
+/// Generates the setters of the properties, which are only notified if their value changed.
+/// They return whether it did.
+macro_rules! setters {
+    ($($setter:ident($field:ident: $ty:ty) => $notify:ident,)*) => {
+        impl ProcessRow {
+            $(
+                pub fn $setter(&self, value: $ty) -> bool {
+                    let imp = self.imp();
+                    if *imp.$field.borrow() == value {
+                        return false;
+                    }
+                    imp.$field.replace(value);
+                    self.$notify();
+                    true
+                }
+            )*
+        }
+    };
+}
+
+setters! {
+    set_pid(pid: u32) => notify_pid,
+    set_name(name: String) => notify_name,
+    set_command(command: String) => notify_command,
+    set_exe(exe: String) => notify_exe,
+    set_user(user: String) => notify_user,
+    set_state(state: String) => notify_state,
+    set_state_kind(state_kind: StateKind) => notify_state_kind,
+    set_kernel_thread(kernel_thread: bool) => notify_kernel_thread,
+    set_pinned(pinned: bool) => notify_pinned,
+    set_cpu(cpu: f32) => notify_cpu,
+    set_memory(memory: u64) => notify_memory,
+    set_disk_usage(disk_usage: u64) => notify_disk_usage,
+    set_subtree_cpu(subtree_cpu: f32) => notify_subtree_cpu,
+    set_subtree_memory(subtree_memory: u64) => notify_subtree_memory,
+    set_threads(threads: i64) => notify_threads,
+    set_start_time(start_time: u64) => notify_start_time,
+    set_virtual_memory(virtual_memory: u64) => notify_virtual_memory,
+    set_nice(nice: i64) => notify_nice,
+    set_io_priority(io_priority: Option<IoPriority>) => notify_io_priority,
+    set_parent_pid(parent_pid: i64) => notify_parent_pid,
+    set_instances(instances: u32) => notify_instances,
+    set_pss(pss: i64) => notify_pss,
+    set_uss(uss: i64) => notify_uss,
+    set_swap(swap: i64) => notify_swap,
+}
+
+impl ProcessRow {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    /// Creates the row grouping the processes of the application `name`.
+    pub fn new_group(name: &str) -> Self {
+        let row = Self::new();
+        row.imp().group.replace(name.to_owned());
+        row.set_name(name.to_owned());
+        row
+    }
+
+    pub fn is_group(&self) -> bool {
+        !self.imp().group.borrow().is_empty()
+    }
+
+    /// The rows displayed under this one: the children of a process in the tree mode, or the
+    /// processes of an application in the grouped mode.
+    pub fn children(&self) -> gio::ListStore {
+        self.imp()
+            .children
+            .get_or_init(gio::ListStore::new::<ProcessRow>)
+            .clone()
+    }
+
+    pub fn has_children(&self) -> bool {
+        self.imp()
+            .children
+            .get()
+            .is_some_and(|children| children.n_items() > 0)
+    }
+
+    /// Iterates over the rows displayed under this one, without creating their list.
+    pub fn child_rows(&self) -> impl Iterator<Item = ProcessRow> {
+        let children = self.imp().children.get().cloned();
+        let nb_children = children.as_ref().map_or(0, |children| children.n_items());
+        (0..nb_children).filter_map(move |pos| children.as_ref()?.item(pos).and_downcast())
+    }
+
+    pub(crate) fn expandable(&self) -> bool {
+        self.imp().expandable.get()
+    }
+
+    pub(crate) fn set_expandable(&self, expandable: bool) {
+        self.imp().expandable.set(expandable);
+    }
+}
+
+impl Default for ProcessRow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
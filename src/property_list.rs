@@ -0,0 +1,101 @@
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{gio, glib, pango};
+
+use std::cell::RefCell;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, glib::Properties)]
+    #[properties(wrapper_type = super::PropertyRow)]
+    pub struct PropertyRow {
+        #[property(get, construct_only)]
+        name: RefCell<String>,
+        #[property(get, set)]
+        value: RefCell<String>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PropertyRow {
+        const NAME: &'static str = "PropertyRow";
+        type Type = super::PropertyRow;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for PropertyRow {}
+}
+
+glib::wrapper! {
+    /// A row of a [`PropertyList`].
+    pub struct PropertyRow(ObjectSubclass<imp::PropertyRow>);
+}
+
+impl PropertyRow {
+    fn new(name: &str, value: &str) -> Self {
+        glib::Object::builder()
+            .property("name", name)
+            .property("value", value)
+            .build()
+    }
+}
+
+fn append_column(view: &gtk::ColumnView, title: &str, property: &'static str, xalign: f32) {
+    let factory = gtk::SignalListItemFactory::new();
+    factory.connect_setup(move |_, list_item| {
+        let Some(list_item) = list_item.downcast_ref::<gtk::ListItem>() else {
+            return;
+        };
+        let label = gtk::Label::builder().xalign(xalign).build();
+        if property == "value" {
+            // Values (like environment variables) can be very long.
+            label.set_wrap(true);
+            label.set_wrap_mode(pango::WrapMode::Char);
+        }
+        list_item
+            .property_expression_weak("item")
+            .chain_property::<PropertyRow>(property)
+            .bind(&label, "label", gtk::Widget::NONE);
+        list_item.set_child(Some(&label));
+    });
+    let column = gtk::ColumnViewColumn::builder()
+        .title(title)
+        .factory(&factory)
+        .resizable(true)
+        .expand(property == "value")
+        .build();
+    view.append_column(&column);
+}
+
+/// A list of properties and their value, displayed in two columns.
+pub struct PropertyList {
+    store: gio::ListStore,
+    pub view: gtk::ColumnView,
+}
+
+impl PropertyList {
+    /// `titles` are the titles of the property and value columns.
+    pub fn new(titles: (&str, &str), right_align_values: bool) -> Self {
+        let store = gio::ListStore::new::<PropertyRow>();
+        let view = gtk::ColumnView::new(Some(gtk::NoSelection::new(Some(store.clone()))));
+        append_column(&view, titles.0, "name", 0.);
+        append_column(
+            &view,
+            titles.1,
+            "value",
+            if right_align_values { 1. } else { 0. },
+        );
+        Self { store, view }
+    }
+
+    pub fn append(&self, name: &str, value: &str) {
+        self.store.append(&PropertyRow::new(name, value));
+    }
+
+    /// Sets the value of the property at `position`.
+    pub fn set_value(&self, position: u32, value: &str) {
+        if let Some(row) = self.store.item(position).and_downcast::<PropertyRow>() {
+            row.set_value(value);
+        }
+    }
+}
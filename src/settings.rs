@@ -15,11 +15,49 @@ use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
-use crate::utils::{get_app, get_main_window};
+use crate::collector::Subsystem;
+use crate::display_procs::{ProcColumn, StateKind};
+use crate::filter::{SearchField, SearchOptions};
+use crate::pins::PinnedProcess;
+use crate::process_index::ViewMode;
+use crate::utils::{get_main_window, show_error_dialog};
 
 use crate::RequiredForSettings;
 use crate::APPLICATION_NAME;
 
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct ProcessColumnSettings {
+    pub column: ProcColumn,
+    // Width set by the user, if any.
+    pub width: Option<i32>,
+}
+
+fn default_process_columns() -> Vec<ProcessColumnSettings> {
+    [
+        ProcColumn::Pid,
+        ProcColumn::Name,
+        ProcColumn::Cpu,
+        ProcColumn::Memory,
+        ProcColumn::DiskUsage,
+        ProcColumn::SubtreeCpu,
+        ProcColumn::SubtreeMemory,
+    ]
+    .into_iter()
+    .map(|column| ProcessColumnSettings {
+        column,
+        width: None,
+    })
+    .collect()
+}
+
+fn default_process_search_fields() -> Vec<SearchField> {
+    vec![SearchField::Name, SearchField::Pid]
+}
+
+fn default_process_states() -> Vec<StateKind> {
+    StateKind::ALL.to_vec()
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Settings {
     pub display_fahrenheit: bool,
@@ -30,6 +68,38 @@ pub struct Settings {
     pub refresh_system_rate: u32,
     // Timer length in milliseconds (500 minimum!).
     pub refresh_network_rate: u32,
+    // How the processes are displayed: flat list, parent/children tree or grouped by application.
+    #[serde(default)]
+    pub process_view_mode: ViewMode,
+    // The columns displayed in the processes list, in their display order.
+    #[serde(default = "default_process_columns")]
+    pub process_columns: Vec<ProcessColumnSettings>,
+    // Time in milliseconds given to a process to end after `SIGTERM` before it is killed.
+    #[serde(default = "default_termination_grace_period")]
+    pub termination_grace_period: u32,
+    #[serde(default)]
+    pub process_search: SearchOptions,
+    // The fields searched by the plain words of the processes filter.
+    #[serde(default = "default_process_search_fields")]
+    pub process_search_fields: Vec<SearchField>,
+    #[serde(default)]
+    pub network_search: SearchOptions,
+    #[serde(default)]
+    pub hide_kernel_threads: bool,
+    // Only the processes in one of these states are displayed.
+    #[serde(default = "default_process_states")]
+    pub process_states: Vec<StateKind>,
+    // The processes kept at the top of the processes list.
+    #[serde(default)]
+    pub pinned_processes: Vec<PinnedProcess>,
+    // Keep sampling the history of the users tab while it is hidden. The usage of every process
+    // is refreshed then, even if the processes list doesn't display it.
+    #[serde(default)]
+    pub users_history_in_background: bool,
+}
+
+fn default_termination_grace_period() -> u32 {
+    5000
 }
 
 impl Default for Settings {
@@ -40,6 +110,16 @@ impl Default for Settings {
             refresh_processes_rate: 1500,
             refresh_system_rate: 2000,
             refresh_network_rate: 1500,
+            process_view_mode: ViewMode::default(),
+            process_columns: default_process_columns(),
+            termination_grace_period: default_termination_grace_period(),
+            process_search: SearchOptions::default(),
+            process_search_fields: default_process_search_fields(),
+            network_search: SearchOptions::default(),
+            hide_kernel_threads: false,
+            process_states: default_process_states(),
+            pinned_processes: Vec::new(),
+            users_history_in_background: false,
         }
     }
 }
@@ -115,36 +195,26 @@ impl Settings {
     }
 }
 
-fn show_error_dialog(fatal: bool, text: &str) {
-    let dialog = gtk::MessageDialog::new(
-        get_main_window().as_ref(),
-        gtk::DialogFlags::MODAL,
-        gtk::MessageType::Error,
-        gtk::ButtonsType::Ok,
-        text,
-    );
-
-    dialog.connect_response(move |dialog, _| {
-        dialog.close();
-
-        if fatal {
-            get_app().quit();
-        }
-    });
-
-    dialog.set_resizable(false);
-    dialog.show();
+pub fn build_spin(label: &str, grid: &gtk::Grid, top: i32, refresh: u32) -> gtk::SpinButton {
+    // We allow 0.5 to 5 seconds, in 0.1 second steps.
+    build_spin_with_range(label, grid, top, refresh, 0.5, 5.)
 }
 
-pub fn build_spin(label: &str, grid: &gtk::Grid, top: i32, refresh: u32) -> gtk::SpinButton {
+pub fn build_spin_with_range(
+    label: &str,
+    grid: &gtk::Grid,
+    top: i32,
+    refresh: u32,
+    min: f64,
+    max: f64,
+) -> gtk::SpinButton {
     // Refresh rate.
     let refresh_label = gtk::Label::builder()
         .label(label)
         .halign(gtk::Align::Start)
         .hexpand(true)
         .build();
-    // We allow 0.5 to 5 seconds, in 0.1 second steps.
-    let refresh_entry = gtk::SpinButton::with_range(0.5, 5., 0.1);
+    let refresh_entry = gtk::SpinButton::with_range(min, max, 0.1);
 
     refresh_entry.set_value(f64::from(refresh) / 1_000.);
 
@@ -188,6 +258,19 @@ pub fn show_settings_dialog(settings: &Rc<RefCell<Settings>>, rfs: &RequiredForS
         2,
         bsettings.refresh_system_rate,
     );
+    let grace_period = build_spin_with_range(
+        "Delay before killing a process which didn't end (in seconds)",
+        &grid,
+        3,
+        bsettings.termination_grace_period,
+        0.5,
+        60.,
+    );
+    let users_history = gtk::CheckButton::builder()
+        .label("Record the history of the users tab while it is hidden")
+        .active(bsettings.users_history_in_background)
+        .build();
+    grid.attach(&users_history, 0, 4, 4, 1);
 
     // Put the grid into the dialog's content area.
     let content_area = dialog.content_area();
@@ -199,46 +282,65 @@ pub fn show_settings_dialog(settings: &Rc<RefCell<Settings>>, rfs: &RequiredForS
     refresh_procs.connect_value_changed(glib::clone!(
         #[weak]
         settings,
-        #[weak(rename_to = process_refresh_timeout)]
-        rfs.process_refresh_timeout,
+        #[strong(rename_to = collector)]
+        rfs.collector,
         move |entry| {
             let mut settings = settings.borrow_mut();
             settings.refresh_processes_rate = (entry.value() * 1_000.) as _;
-            *process_refresh_timeout
-                .lock()
-                .expect("failed to lock process_refresh_timeout") = settings.refresh_processes_rate;
+            collector.set_rate(Subsystem::Processes, settings.refresh_processes_rate);
             settings.save();
         }
     ));
     refresh_network.connect_value_changed(glib::clone!(
         #[weak]
         settings,
-        #[weak(rename_to = network_refresh_timeout)]
-        rfs.network_refresh_timeout,
+        #[strong(rename_to = collector)]
+        rfs.collector,
         move |entry| {
             let mut settings = settings.borrow_mut();
             settings.refresh_network_rate = (entry.value() * 1_000.) as _;
-            *network_refresh_timeout
-                .lock()
-                .expect("failed to lock network_refresh_timeout") = settings.refresh_network_rate;
+            collector.set_rate(Subsystem::Networks, settings.refresh_network_rate);
             settings.save();
         }
     ));
     refresh_sys.connect_value_changed(glib::clone!(
         #[weak]
         settings,
-        #[weak(rename_to = system_refresh_timeout)]
-        rfs.system_refresh_timeout,
+        #[strong(rename_to = collector)]
+        rfs.collector,
         move |entry| {
             let mut settings = settings.borrow_mut();
             settings.refresh_system_rate = (entry.value() * 1_000.) as _;
-            *system_refresh_timeout
-                .lock()
-                .expect("failed to lock system_refresh_timeout") = settings.refresh_system_rate;
+            collector.set_rate(Subsystem::System, settings.refresh_system_rate);
             settings.save();
         }
     ));
 
+    grace_period.connect_value_changed(glib::clone!(
+        #[weak]
+        settings,
+        move |entry| {
+            let mut settings = settings.borrow_mut();
+            settings.termination_grace_period = (entry.value() * 1_000.) as _;
+            settings.save();
+        }
+    ));
+
+    users_history.connect_toggled(glib::clone!(
+        #[weak]
+        settings,
+        #[strong(rename_to = update_refreshes)]
+        rfs.update_refreshes,
+        move |button| {
+            {
+                let mut settings = settings.borrow_mut();
+                settings.users_history_in_background = button.is_active();
+                settings.save();
+            }
+            update_refreshes();
+        }
+    ));
+
     dialog.connect_response(move |dialog, _| {
         dialog.close();
     });
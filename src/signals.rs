@@ -0,0 +1,637 @@
+use gtk::prelude::*;
+use gtk::{gio, glib};
+use sysinfo::{
+    Pid, ProcessRefreshKind, ProcessStatus, ProcessesToUpdate, Signal, ThreadKind,
+    SUPPORTED_SIGNALS,
+};
+
+use crate::utils::{get_main_window, show_error_dialog};
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// Signals which can be sent from the processes list, with their usual name.
+pub const SIGNALS: &[(&str, Signal)] = &[
+    ("SIGTERM", Signal::Term),
+    ("SIGINT", Signal::Interrupt),
+    ("SIGHUP", Signal::Hangup),
+    ("SIGSTOP", Signal::Stop),
+    ("SIGCONT", Signal::Continue),
+    ("SIGUSR1", Signal::User1),
+    ("SIGUSR2", Signal::User2),
+    ("SIGKILL", Signal::Kill),
+];
+
+pub fn signal_from_name(name: &str) -> Option<Signal> {
+    SIGNALS
+        .iter()
+        .find(|(signal_name, _)| *signal_name == name)
+        .map(|(_, signal)| *signal)
+}
+
+fn signal_name(signal: Signal) -> String {
+    SIGNALS
+        .iter()
+        .find(|(_, s)| *s == signal)
+        .map(|(name, _)| (*name).to_owned())
+        .unwrap_or_else(|| signal.to_string())
+}
+
+/// Refreshes the processes `pids` in `sys`, which is only refreshed when it is used.
+fn refresh_processes(sys: &mut sysinfo::System, pids: ProcessesToUpdate<'_>) {
+    sys.refresh_processes_specifics(pids, true, ProcessRefreshKind::nothing());
+}
+
+/// Sends `signal` to the process `pid`.
+pub fn send_signal(sys: &mut sysinfo::System, pid: Pid, signal: Signal) -> Result<(), String> {
+    refresh_processes(sys, ProcessesToUpdate::Some(&[pid]));
+    let Some(process) = sys.process(pid) else {
+        return Err(format!("Process {pid} doesn't exist anymore"));
+    };
+    kill(process, signal)
+}
+
+/// Sends `signal` to the process `pid` if it is still the one which started at `start_time`.
+/// PIDs are reused once a process ended, so an unrelated process could have the same one.
+fn send_signal_to_same(
+    sys: &mut sysinfo::System,
+    pid: Pid,
+    start_time: u64,
+    signal: Signal,
+) -> Result<(), String> {
+    refresh_processes(sys, ProcessesToUpdate::Some(&[pid]));
+    match sys.process(pid) {
+        Some(process) if process.start_time() == start_time => kill(process, signal),
+        _ => Err(format!("Process {pid} doesn't exist anymore")),
+    }
+}
+
+fn kill(process: &sysinfo::Process, signal: Signal) -> Result<(), String> {
+    let pid = process.pid();
+    match process.kill_with(signal) {
+        Some(true) => Ok(()),
+        Some(false) => Err(format!(
+            "Failed to send {} to process {pid}: {}",
+            signal_name(signal),
+            std::io::Error::last_os_error(),
+        )),
+        None => Err(format!(
+            "{} is not supported on this platform",
+            signal_name(signal)
+        )),
+    }
+}
+
+/// Sends the signal with the given number to the process `pid`.
+#[cfg(unix)]
+pub fn send_signal_number(pid: Pid, signal: i32) -> Result<(), String> {
+    if unsafe { libc::kill(pid.as_u32() as _, signal) } == 0 {
+        Ok(())
+    } else {
+        Err(format!(
+            "Failed to send signal {signal} to process {pid}: {}",
+            std::io::Error::last_os_error(),
+        ))
+    }
+}
+
+#[cfg(not(unix))]
+pub fn send_signal_number(_pid: Pid, signal: i32) -> Result<(), String> {
+    Err(format!(
+        "Sending signal {signal} is not supported on this platform"
+    ))
+}
+
+/// Creates the menu listing all the signals which can be sent.
+pub fn create_signals_menu() -> gio::Menu {
+    let menu = gio::Menu::new();
+
+    for (name, signal) in SIGNALS {
+        let item = gio::MenuItem::new(Some(&format!("{name} ({signal})")), None);
+        item.set_action_and_target_value(Some("app.send-signal"), Some(&name.to_variant()));
+        menu.append_item(&item);
+    }
+
+    let other = gio::Menu::new();
+    other.append(Some("Other signal..."), Some("app.send-signal-number"));
+    menu.append_section(None, &other);
+
+    let tree = gio::Menu::new();
+    tree.append(
+        Some("Signal process tree..."),
+        Some("app.signal-process-tree"),
+    );
+    menu.append_section(None, &tree);
+    menu
+}
+
+/// Asks the user which signal number should be sent. `on_send` is called with the chosen number.
+pub fn ask_signal_number<T: IsA<gtk::Window>, F: Fn(i32) + 'static>(window: &T, on_send: F) {
+    let dialog = gtk::Dialog::with_buttons(
+        Some("Send signal"),
+        Some(window),
+        gtk::DialogFlags::MODAL,
+        &[
+            ("Send", gtk::ResponseType::Other(0)),
+            ("Cancel", gtk::ResponseType::Close),
+        ],
+    );
+    let layout = gtk::Box::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .spacing(6)
+        .css_classes(vec!["button-with-margin".to_owned()])
+        .build();
+    // The real-time signals go up to 64 on Linux.
+    let input = gtk::SpinButton::with_range(1., 64., 1.);
+    input.set_value(15.);
+    input.set_hexpand(true);
+    layout.append(&gtk::Label::new(Some("Signal number")));
+    layout.append(&input);
+
+    dialog.connect_response(glib::clone!(
+        #[weak]
+        input,
+        move |dialog, response| {
+            if response == gtk::ResponseType::Other(0) {
+                on_send(input.value_as_int());
+            }
+            dialog.close();
+        }
+    ));
+
+    dialog.content_area().append(&layout);
+    dialog.show();
+}
+
+/// Returns `true` if the process `pid` started at `start_time` is still running.
+fn is_still_running(sys: &mut sysinfo::System, pid: Pid, start_time: u64) -> bool {
+    sys.refresh_processes(ProcessesToUpdate::Some(&[pid]), true);
+    // If the PID has been reused, it's not the same process anymore.
+    sys.process(pid).is_some_and(|p| {
+        p.start_time() == start_time && !matches!(p.status(), ProcessStatus::Zombie)
+    })
+}
+
+enum Outcome {
+    Ended,
+    Killed,
+    Failed(String),
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ended => f.write_str("ended"),
+            Self::Killed => f.write_str("killed"),
+            Self::Failed(error) => f.write_str(error),
+        }
+    }
+}
+
+struct Target {
+    pid: Pid,
+    name: String,
+    start_time: u64,
+    // `None` while the process is still running.
+    outcome: Option<Outcome>,
+    killed: bool,
+}
+
+/// Asks for confirmation before ending the processes `pids`. They are first asked to terminate
+/// with `SIGTERM` and the ones still running after `grace_period` milliseconds are killed.
+pub fn end_task<T: IsA<gtk::Window>>(
+    window: &T,
+    sys: &Rc<RefCell<sysinfo::System>>,
+    pids: &[Pid],
+    grace_period: u32,
+) {
+    let targets = {
+        let mut sys = sys.borrow_mut();
+        refresh_processes(&mut sys, ProcessesToUpdate::Some(pids));
+        pids.iter()
+            .filter_map(|pid| sys.process(*pid))
+            .map(|p| Target {
+                pid: p.pid(),
+                name: p.name().to_string_lossy().to_string(),
+                start_time: p.start_time(),
+                outcome: None,
+                killed: false,
+            })
+            .collect::<Vec<_>>()
+    };
+    if targets.is_empty() {
+        show_error_dialog(false, "The selected processes don't exist anymore");
+        return;
+    }
+    let targets = RefCell::new(targets);
+    let dialog = gtk::Dialog::with_buttons(
+        Some("End task"),
+        Some(window),
+        gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT,
+        &[
+            ("End task", gtk::ResponseType::Accept),
+            ("Cancel", gtk::ResponseType::Cancel),
+        ],
+    );
+    let layout = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(6)
+        .css_classes(vec!["button-with-margin".to_owned()])
+        .build();
+    let question = if let [target] = targets.borrow().as_slice() {
+        format!(
+            "Do you want to end \"{}\" (PID {})?",
+            target.name, target.pid
+        )
+    } else {
+        format!(
+            "Do you want to end these {} processes?\n\n{}",
+            targets.borrow().len(),
+            targets
+                .borrow()
+                .iter()
+                .map(|t| format!("{} (PID {})", t.name, t.pid))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )
+    };
+    let question = gtk::Label::new(Some(&question));
+    let status = gtk::Label::builder().wrap(true).build();
+    layout.append(
+        &gtk::ScrolledWindow::builder()
+            .child(&question)
+            .propagate_natural_height(true)
+            .max_content_height(300)
+            .build(),
+    );
+    layout.append(&status);
+    dialog.content_area().append(&layout);
+
+    // Used to stop checking the processes' status when the dialog is closed.
+    let is_closed = Rc::new(Cell::new(false));
+    dialog.connect_destroy(glib::clone!(
+        #[weak]
+        is_closed,
+        move |_| is_closed.set(true)
+    ));
+
+    dialog.connect_response(glib::clone!(
+        #[weak]
+        sys,
+        #[weak]
+        status,
+        #[strong]
+        is_closed,
+        move |dialog, response| {
+            if response != gtk::ResponseType::Accept {
+                is_closed.set(true);
+                dialog.close();
+                return;
+            }
+            dialog.set_response_sensitive(gtk::ResponseType::Accept, false);
+
+            let mut targets = targets.take();
+            {
+                let mut sys = sys.borrow_mut();
+                for target in targets.iter_mut() {
+                    // The process may have ended while the dialog was open, and its PID may
+                    // belong to another process now.
+                    if !is_still_running(&mut sys, target.pid, target.start_time) {
+                        target.outcome = Some(Outcome::Ended);
+                        continue;
+                    }
+                    // If `SIGTERM` isn't supported, we have no choice but to kill it directly.
+                    // Other errors, like not being allowed to signal the process, would fail
+                    // the same way with `SIGKILL`.
+                    let signal = if SUPPORTED_SIGNALS.contains(&Signal::Term) {
+                        Signal::Term
+                    } else {
+                        target.killed = true;
+                        Signal::Kill
+                    };
+                    if let Err(error) =
+                        send_signal_to_same(&mut sys, target.pid, target.start_time, signal)
+                    {
+                        target.outcome = Some(Outcome::Failed(error));
+                    }
+                }
+            }
+            let start = Instant::now();
+            let grace_period = Duration::from_millis(grace_period as _);
+            glib::timeout_add_local(
+                Duration::from_millis(250),
+                glib::clone!(
+                    #[weak]
+                    sys,
+                    #[weak]
+                    status,
+                    #[weak]
+                    dialog,
+                    #[strong]
+                    is_closed,
+                    #[upgrade_or]
+                    glib::ControlFlow::Break,
+                    move || {
+                        if is_closed.get() {
+                            return glib::ControlFlow::Break;
+                        }
+                        let mut sys = sys.borrow_mut();
+                        let elapsed = start.elapsed();
+                        let escalate = elapsed >= grace_period;
+                        for target in targets.iter_mut().filter(|t| t.outcome.is_none()) {
+                            if !is_still_running(&mut sys, target.pid, target.start_time) {
+                                target.outcome = Some(if target.killed {
+                                    Outcome::Killed
+                                } else {
+                                    Outcome::Ended
+                                });
+                            } else if escalate && !target.killed {
+                                target.killed = true;
+                                if let Err(error) = send_signal_to_same(
+                                    &mut sys,
+                                    target.pid,
+                                    target.start_time,
+                                    Signal::Kill,
+                                ) {
+                                    target.outcome = Some(Outcome::Failed(error));
+                                }
+                            }
+                        }
+                        let running = targets.iter().filter(|t| t.outcome.is_none()).count();
+                        if running == 0 {
+                            status.set_text(&summarize_end_task(&targets));
+                            dialog.set_response_sensitive(gtk::ResponseType::Cancel, false);
+                            dialog.add_button("Close", gtk::ResponseType::Close);
+                            return glib::ControlFlow::Break;
+                        }
+                        if escalate {
+                            status.set_text(&format!(
+                                "Sent SIGKILL, waiting for {running} process(es) to end ({}s)...",
+                                elapsed.as_secs(),
+                            ));
+                        } else {
+                            status.set_text(&format!(
+                                "Sent SIGTERM, waiting for {running} process(es) to end \
+                                 ({}s left)...",
+                                (grace_period - elapsed).as_secs_f32().ceil(),
+                            ));
+                        }
+                        glib::ControlFlow::Continue
+                    }
+                ),
+            );
+        }
+    ));
+
+    dialog.show();
+}
+
+fn summarize_end_task(targets: &[Target]) -> String {
+    if let [target] = targets {
+        return match &target.outcome {
+            Some(Outcome::Ended) => "The process has ended.".to_owned(),
+            Some(Outcome::Killed) => "The process has been killed.".to_owned(),
+            Some(Outcome::Failed(error)) => error.clone(),
+            None => String::new(),
+        };
+    }
+    targets
+        .iter()
+        .map(|t| match &t.outcome {
+            Some(outcome) => format!("{} (PID {}): {outcome}", t.name, t.pid),
+            None => format!("{} (PID {}): still running", t.name, t.pid),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Shows the result of an action applied on multiple processes. Nothing is displayed if there
+/// was only one process and the action succeeded.
+pub fn show_results(action: &str, results: &[(Pid, Result<(), String>)]) {
+    match results {
+        [] | [(_, Ok(()))] => {}
+        [(_, Err(error))] => show_error_dialog(false, error),
+        _ => {
+            let failures = results.iter().filter(|(_, r)| r.is_err()).count();
+            let text = format!(
+                "{action}: {} succeeded, {failures} failed\n\n{}",
+                results.len() - failures,
+                results
+                    .iter()
+                    .map(|(pid, result)| match result {
+                        Ok(()) => format!("Process {pid}: done"),
+                        Err(error) => error.clone(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            );
+            let dialog = gtk::MessageDialog::new(
+                get_main_window().as_ref(),
+                gtk::DialogFlags::MODAL,
+                if failures == 0 {
+                    gtk::MessageType::Info
+                } else {
+                    gtk::MessageType::Warning
+                },
+                gtk::ButtonsType::Ok,
+                text,
+            );
+            dialog.connect_response(|dialog, _| dialog.close());
+            dialog.show();
+        }
+    }
+}
+
+/// A process of a tree, as it was when the tree was collected. The process is identified by its
+/// PID and its start time, as its PID could be reused once it ended. It stays part of the tree
+/// if it is reparented when its parent ends.
+#[derive(Clone, Copy)]
+pub struct TreeNode {
+    pub pid: Pid,
+    pub start_time: u64,
+    pub depth: usize,
+}
+
+/// Returns the given processes and all their descendants, with their depth in the tree. Parents
+/// are always listed before their children.
+pub fn collect_process_trees(sys: &sysinfo::System, pids: &[Pid]) -> Vec<TreeNode> {
+    let mut children: HashMap<Pid, Vec<Pid>> = HashMap::new();
+    for p in sys.processes().values() {
+        // Threads receive the signals sent to their process.
+        if p.thread_kind() == Some(ThreadKind::Userland) {
+            continue;
+        }
+        if let Some(parent) = p.parent() {
+            children.entry(parent).or_default().push(p.pid());
+        }
+    }
+    let mut seen = HashSet::new();
+    let mut out = Vec::new();
+    for pid in pids {
+        if !seen.insert(*pid) {
+            continue;
+        }
+        // Depth-first so children are listed right after their parent.
+        let mut stack = vec![(*pid, 0)];
+        while let Some((pid, depth)) = stack.pop() {
+            let Some(process) = sys.process(pid) else {
+                continue;
+            };
+            out.push(TreeNode {
+                pid,
+                start_time: process.start_time(),
+                depth,
+            });
+            if let Some(children) = children.get(&pid) {
+                for child in children.iter().rev() {
+                    if seen.insert(*child) {
+                        stack.push((*child, depth + 1));
+                    }
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Returns `true` if the processes can be stopped and continued on this platform.
+fn can_freeze() -> bool {
+    SUPPORTED_SIGNALS.contains(&Signal::Stop) && SUPPORTED_SIGNALS.contains(&Signal::Continue)
+}
+
+/// Asks for confirmation before sending a signal to the processes `pids` and all their
+/// descendants.
+pub fn signal_process_trees<T: IsA<gtk::Window>>(
+    window: &T,
+    sys: &Rc<RefCell<sysinfo::System>>,
+    pids: &[Pid],
+) {
+    let (tree, description) = {
+        let mut sys = sys.borrow_mut();
+        refresh_processes(&mut sys, ProcessesToUpdate::All);
+        let tree = collect_process_trees(&sys, pids);
+        let description = tree
+            .iter()
+            .map(|node| {
+                let name = sys
+                    .process(node.pid)
+                    .map(|p| p.name().to_string_lossy().to_string())
+                    .unwrap_or_default();
+                format!("{}{name} (PID {})", "    ".repeat(node.depth), node.pid)
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        (tree, description)
+    };
+    let dialog = gtk::Dialog::with_buttons(
+        Some("Signal process tree"),
+        Some(window),
+        gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT,
+        &[
+            ("Send", gtk::ResponseType::Accept),
+            ("Cancel", gtk::ResponseType::Cancel),
+        ],
+    );
+    let layout = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(6)
+        .css_classes(vec!["button-with-margin".to_owned()])
+        .build();
+    layout.append(&gtk::Label::new(Some(&format!(
+        "The signal will be sent to these {} processes:",
+        tree.len()
+    ))));
+    layout.append(
+        &gtk::ScrolledWindow::builder()
+            .child(
+                &gtk::Label::builder()
+                    .label(description)
+                    .halign(gtk::Align::Start)
+                    .selectable(true)
+                    .build(),
+            )
+            .propagate_natural_height(true)
+            .max_content_height(300)
+            .build(),
+    );
+    let signal =
+        gtk::DropDown::from_strings(&SIGNALS.iter().map(|(name, _)| *name).collect::<Vec<_>>());
+    // The signals are sent right after each other: a process may still be handling its signal
+    // when its parent receives one.
+    let leaves_first = gtk::CheckButton::builder()
+        .label("Signal the deepest processes first, without waiting for them")
+        .tooltip_text(
+            "The children are signaled before their parent, but the parent may be signaled \
+             before its children have handled their signal.",
+        )
+        .active(true)
+        .build();
+    let all_at_once = gtk::CheckButton::builder()
+        .label("Signal all processes at once")
+        .tooltip_text(
+            "The processes are stopped until all of them are signaled, so none of them can \
+             react to the signal of another one.",
+        )
+        .group(&leaves_first)
+        .sensitive(can_freeze())
+        .build();
+    layout.append(&signal);
+    layout.append(&leaves_first);
+    layout.append(&all_at_once);
+    dialog.content_area().append(&layout);
+
+    dialog.connect_response(glib::clone!(
+        #[weak]
+        sys,
+        #[weak]
+        signal,
+        #[weak]
+        leaves_first,
+        #[weak]
+        all_at_once,
+        move |dialog, response| {
+            dialog.close();
+            if response != gtk::ResponseType::Accept {
+                return;
+            }
+            let Some((name, signal)) = SIGNALS.get(signal.selected() as usize) else {
+                return;
+            };
+            let mut tree = tree.clone();
+            if leaves_first.is_active() {
+                // The deepest processes are signaled first.
+                tree.sort_by_key(|node| std::cmp::Reverse(node.depth));
+            }
+            let mut sys = sys.borrow_mut();
+            let freeze = all_at_once.is_active() && can_freeze();
+            if freeze {
+                // The parents are stopped first, so they can't start new children meanwhile.
+                for node in tree.iter() {
+                    let _ = send_signal_to_same(&mut sys, node.pid, node.start_time, Signal::Stop);
+                }
+            }
+            // The processes which ended since the dialog was opened aren't signaled, as their
+            // PID may belong to another process now.
+            let results = tree
+                .iter()
+                .map(|node| {
+                    let result = send_signal_to_same(&mut sys, node.pid, node.start_time, *signal);
+                    (node.pid, result)
+                })
+                .collect::<Vec<_>>();
+            // They handle their signal once they continue, unless it was to stop them.
+            if freeze && *signal != Signal::Stop {
+                for node in tree.iter() {
+                    let _ =
+                        send_signal_to_same(&mut sys, node.pid, node.start_time, Signal::Continue);
+                }
+            }
+            show_results(&format!("Sent {name}"), &results);
+        }
+    ));
+
+    dialog.show();
+}
@@ -0,0 +1,581 @@
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{gio, glib};
+use sysinfo::Pid;
+
+use crate::open_files::open_files;
+use crate::process_row::UNKNOWN;
+use crate::utils::{
+    compare_ignore_case, create_sorter, create_text_column, describe_process_error, sort_again,
+    update_store,
+};
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, glib::Properties)]
+    #[properties(wrapper_type = super::SocketRow)]
+    pub struct SocketRow {
+        #[property(get, construct_only)]
+        inode: RefCell<u64>,
+        #[property(get, construct_only)]
+        protocol: RefCell<String>,
+        #[property(get, set)]
+        local: RefCell<String>,
+        #[property(get, set)]
+        remote: RefCell<String>,
+        #[property(get, set)]
+        state: RefCell<String>,
+        #[property(get, set, default = UNKNOWN)]
+        send_queue: RefCell<i64>,
+        #[property(get, set, default = UNKNOWN)]
+        receive_queue: RefCell<i64>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SocketRow {
+        const NAME: &'static str = "SocketRow";
+        type Type = super::SocketRow;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for SocketRow {}
+}
+
+glib::wrapper! {
+    /// A row of the sockets list.
+    pub struct SocketRow(ObjectSubclass<imp::SocketRow>);
+}
+
+impl SocketRow {
+    fn new(socket: &Socket) -> Self {
+        let row: Self = glib::Object::builder()
+            .property("inode", socket.inode)
+            .property("protocol", socket.protocol)
+            .build();
+        row.update(socket);
+        row
+    }
+
+    fn update(&self, socket: &Socket) {
+        if self.local() != socket.local {
+            self.set_local(socket.local.as_str());
+        }
+        if self.remote() != socket.remote {
+            self.set_remote(socket.remote.as_str());
+        }
+        if self.state() != socket.state {
+            self.set_state(socket.state);
+        }
+        let (send_queue, receive_queue) = socket
+            .queues
+            .map_or((UNKNOWN, UNKNOWN), |(tx, rx)| (tx as i64, rx as i64));
+        if self.send_queue() != send_queue {
+            self.set_send_queue(send_queue);
+        }
+        if self.receive_queue() != receive_queue {
+            self.set_receive_queue(receive_queue);
+        }
+    }
+}
+
+/// A socket of a process, as listed in one of the `/proc/net` tables.
+#[derive(Debug, PartialEq)]
+pub struct Socket {
+    pub inode: u64,
+    /// The name of the table listing the socket, with the type of the UNIX sockets.
+    pub protocol: &'static str,
+    /// The local address, or the path of a UNIX socket. Abstract UNIX sockets start with `@`.
+    pub local: String,
+    /// The remote address, empty for UNIX sockets: their peer isn't listed.
+    pub remote: String,
+    pub state: &'static str,
+    /// The bytes in the send and receive queues, `None` for UNIX sockets.
+    pub queues: Option<(u64, u64)>,
+}
+
+/// The tables of the internet sockets, with the names of their protocols.
+const INET_TABLES: [&str; 4] = ["tcp", "tcp6", "udp", "udp6"];
+
+/// Returns the sockets opened by the process `pid`.
+pub fn sockets(pid: Pid) -> io::Result<Vec<Socket>> {
+    let inodes = open_files(pid)?
+        .iter()
+        .filter_map(|file| socket_inode(&file.target))
+        .collect::<HashSet<_>>();
+    // The tables of the process are the ones of its network namespace, which might not be ours.
+    Ok(find_sockets(&inodes, |table| {
+        std::fs::read_to_string(format!("/proc/{pid}/net/{table}")).ok()
+    }))
+}
+
+/// Returns the inode of a socket from the target of its file descriptor, like `socket:[1234]`.
+fn socket_inode(target: &str) -> Option<u64> {
+    target
+        .strip_prefix("socket:[")?
+        .strip_suffix(']')?
+        .parse()
+        .ok()
+}
+
+/// Returns the sockets whose inode is in `inodes`, from the tables returned by `read_table`.
+/// Missing tables, like the IPv6 ones when it is disabled, are skipped.
+fn find_sockets(inodes: &HashSet<u64>, read_table: impl Fn(&str) -> Option<String>) -> Vec<Socket> {
+    if inodes.is_empty() {
+        return Vec::new();
+    }
+    let mut sockets = Vec::new();
+    for protocol in INET_TABLES {
+        if let Some(table) = read_table(protocol) {
+            sockets.extend(parse_inet_table(&table, protocol));
+        }
+    }
+    if let Some(table) = read_table("unix") {
+        sockets.extend(parse_unix_table(&table));
+    }
+    sockets.retain(|socket| inodes.contains(&socket.inode));
+    sockets
+}
+
+/// Parses a `tcp`, `tcp6`, `udp` or `udp6` table. After the header, each line describes a
+/// socket:
+///
+/// ```text
+/// sl: local_address rem_address st tx_queue:rx_queue tr:tm->when retrnsmt uid timeout inode ...
+/// ```
+fn parse_inet_table(table: &str, protocol: &'static str) -> Vec<Socket> {
+    let is_tcp = protocol.starts_with("tcp");
+    table
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            let state = u8::from_str_radix(fields.get(3)?, 16).ok()?;
+            let (tx_queue, rx_queue) = fields.get(4)?.split_once(':')?;
+            Some(Socket {
+                inode: fields.get(9)?.parse().ok()?,
+                protocol,
+                local: parse_inet_address(fields.get(1)?)?.to_string(),
+                remote: parse_inet_address(fields.get(2)?)?.to_string(),
+                state: if is_tcp {
+                    tcp_state(state)
+                } else {
+                    udp_state(state)
+                },
+                queues: Some((
+                    u64::from_str_radix(tx_queue, 16).ok()?,
+                    u64::from_str_radix(rx_queue, 16).ok()?,
+                )),
+            })
+        })
+        .collect()
+}
+
+/// Parses an address like `0100007F:0050`. The IP address is written as 32-bit words in the
+/// byte order of the machine, and the port in big endian.
+fn parse_inet_address(address: &str) -> Option<SocketAddr> {
+    let (ip, port) = address.split_once(':')?;
+    let port = u16::from_str_radix(port, 16).ok()?;
+    let word = |word: &str| u32::from_str_radix(word, 16).ok().map(u32::to_ne_bytes);
+    let ip = match ip.len() {
+        8 => IpAddr::V4(Ipv4Addr::from(word(ip)?)),
+        32 => {
+            let mut bytes = [0; 16];
+            for (i, chunk) in bytes.chunks_mut(4).enumerate() {
+                chunk.copy_from_slice(&word(ip.get(i * 8..(i + 1) * 8)?)?);
+            }
+            IpAddr::V6(Ipv6Addr::from(bytes))
+        }
+        _ => return None,
+    };
+    Some(SocketAddr::new(ip, port))
+}
+
+/// The names of the TCP states, as in `include/net/tcp_states.h`.
+fn tcp_state(state: u8) -> &'static str {
+    match state {
+        0x01 => "ESTABLISHED",
+        0x02 => "SYN_SENT",
+        0x03 => "SYN_RECV",
+        0x04 => "FIN_WAIT1",
+        0x05 => "FIN_WAIT2",
+        0x06 => "TIME_WAIT",
+        0x07 => "CLOSE",
+        0x08 => "CLOSE_WAIT",
+        0x09 => "LAST_ACK",
+        0x0A => "LISTEN",
+        0x0B => "CLOSING",
+        0x0C => "NEW_SYN_RECV",
+        _ => "UNKNOWN",
+    }
+}
+
+/// UDP sockets reuse the TCP states, but only two of them.
+fn udp_state(state: u8) -> &'static str {
+    match state {
+        0x01 => "CONNECTED",
+        0x07 => "UNCONNECTED",
+        _ => "UNKNOWN",
+    }
+}
+
+/// Parses the `unix` table. After the header, each line describes a socket:
+///
+/// ```text
+/// Num: RefCount Protocol Flags Type St Inode [Path]
+/// ```
+fn parse_unix_table(table: &str) -> Vec<Socket> {
+    // `__SO_ACCEPTCON`, set on the listening sockets.
+    const ACCEPT_CONNECTIONS: u32 = 1 << 16;
+
+    table
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let flags = u32::from_str_radix(fields.nth(3)?, 16).ok()?;
+            let protocol = match u16::from_str_radix(fields.next()?, 16).ok()? {
+                1 => "unix stream",
+                2 => "unix dgram",
+                5 => "unix seqpacket",
+                _ => "unix",
+            };
+            let state = u8::from_str_radix(fields.next()?, 16).ok()?;
+            let state = if flags & ACCEPT_CONNECTIONS != 0 {
+                "LISTEN"
+            } else {
+                match state {
+                    1 => "UNCONNECTED",
+                    2 => "CONNECTING",
+                    3 => "CONNECTED",
+                    4 => "DISCONNECTING",
+                    _ => "UNKNOWN",
+                }
+            };
+            Some(Socket {
+                inode: fields.next()?.parse().ok()?,
+                protocol,
+                local: fields.collect::<Vec<_>>().join(" "),
+                remote: String::new(),
+                state,
+                queues: None,
+            })
+        })
+        .collect()
+}
+
+/// The sockets opened by a process.
+pub struct SocketsList {
+    pub widget: gtk::Box,
+    pid: Pid,
+    store: gio::ListStore,
+    sort_model: gtk::SortListModel,
+    status: gtk::Label,
+}
+
+impl SocketsList {
+    /// The sockets are read again whenever the list is displayed.
+    pub fn new(pid: Pid) -> Self {
+        let store = gio::ListStore::new::<SocketRow>();
+        let column_view = gtk::ColumnView::new(None::<gtk::SelectionModel>);
+        let sort_model = gtk::SortListModel::new(Some(store.clone()), column_view.sorter());
+        column_view.set_model(Some(&gtk::NoSelection::new(Some(sort_model.clone()))));
+
+        let mut protocol = None;
+        for (title, property, value) in [
+            (
+                "protocol",
+                "protocol",
+                SocketRow::protocol as fn(&SocketRow) -> String,
+            ),
+            ("local address", "local", SocketRow::local),
+            ("remote address", "remote", SocketRow::remote),
+            ("state", "state", SocketRow::state),
+        ] {
+            let column = create_text_column(title, property, 0., value);
+            column.set_sorter(Some(&create_sorter(move |a: &SocketRow, b: &SocketRow| {
+                compare_ignore_case(&value(a), &value(b))
+            })));
+            column.set_expand(property == "local");
+            column_view.append_column(&column);
+            protocol.get_or_insert(column);
+        }
+        for (title, property, value) in [
+            (
+                "send queue",
+                "send-queue",
+                SocketRow::send_queue as fn(&SocketRow) -> i64,
+            ),
+            ("receive queue", "receive-queue", SocketRow::receive_queue),
+        ] {
+            let column =
+                create_text_column(title, property, 1., move |row: &SocketRow| {
+                    match value(row) {
+                        UNKNOWN => String::new(),
+                        bytes => bytes.to_string(),
+                    }
+                });
+            column.set_sorter(Some(&create_sorter(move |a: &SocketRow, b: &SocketRow| {
+                value(a).cmp(&value(b))
+            })));
+            column_view.append_column(&column);
+        }
+        column_view.sort_by_column(protocol.as_ref(), gtk::SortType::Ascending);
+
+        let status = gtk::Label::builder().halign(gtk::Align::Start).build();
+        let widget = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(5)
+            .css_classes(vec!["button-with-margin".to_owned()])
+            .build();
+        widget.append(
+            &gtk::ScrolledWindow::builder()
+                .child(&column_view)
+                .hexpand(true)
+                .vexpand(true)
+                .build(),
+        );
+        widget.append(&status);
+        widget.connect_map(glib::clone!(
+            #[weak]
+            store,
+            #[weak]
+            sort_model,
+            #[weak]
+            status,
+            move |_| update_list(&store, &sort_model, &status, pid)
+        ));
+
+        Self {
+            widget,
+            pid,
+            store,
+            sort_model,
+            status,
+        }
+    }
+
+    /// Reads the sockets of the process again.
+    pub fn update(&self) {
+        update_list(&self.store, &self.sort_model, &self.status, self.pid);
+    }
+}
+
+fn update_list(
+    store: &gio::ListStore,
+    sort_model: &gtk::SortListModel,
+    status: &gtk::Label,
+    pid: Pid,
+) {
+    let sockets = match sockets(pid) {
+        Ok(sockets) => sockets,
+        Err(error) => {
+            store.remove_all();
+            status.set_text(&describe_process_error(&error, "list the sockets of"));
+            return;
+        }
+    };
+    status.set_text(&format!("{} sockets", sockets.len()));
+
+    update_store(
+        store,
+        sockets
+            .iter()
+            .map(|socket| (socket.inode, socket))
+            .collect(),
+        SocketRow::inode,
+        SocketRow::update,
+        |(_, socket)| SocketRow::new(socket),
+    );
+    sort_again(sort_model);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::fixture;
+
+    fn table(name: &str) -> Option<String> {
+        Some(fixture(&format!("proc_net/{name}")))
+    }
+
+    fn inet_socket(
+        inode: u64,
+        protocol: &'static str,
+        local: &str,
+        remote: &str,
+        state: &'static str,
+        queues: (u64, u64),
+    ) -> Socket {
+        Socket {
+            inode,
+            protocol,
+            local: local.to_owned(),
+            remote: remote.to_owned(),
+            state,
+            queues: Some(queues),
+        }
+    }
+
+    fn unix_socket(inode: u64, protocol: &'static str, path: &str, state: &'static str) -> Socket {
+        Socket {
+            inode,
+            protocol,
+            local: path.to_owned(),
+            remote: String::new(),
+            state,
+            queues: None,
+        }
+    }
+
+    #[test]
+    fn socket_inodes() {
+        assert_eq!(socket_inode("socket:[48213]"), Some(48213));
+        assert_eq!(socket_inode("pipe:[48213]"), None);
+        assert_eq!(socket_inode("/tmp/socket:[48213]"), None);
+        assert_eq!(socket_inode("socket:[]"), None);
+    }
+
+    // The IP addresses of the fixtures are written by a little endian machine.
+    #[cfg(target_endian = "little")]
+    #[test]
+    fn tcp() {
+        assert_eq!(
+            parse_inet_table(&fixture("proc_net/tcp"), "tcp"),
+            [
+                inet_socket(21339, "tcp", "0.0.0.0:22", "0.0.0.0:0", "LISTEN", (0, 0)),
+                inet_socket(
+                    48213,
+                    "tcp",
+                    "127.0.0.1:54321",
+                    "127.0.0.1:5432",
+                    "ESTABLISHED",
+                    (16, 32),
+                ),
+                inet_socket(99999, "tcp", "127.0.0.1:631", "0.0.0.0:0", "LISTEN", (0, 0)),
+            ]
+        );
+    }
+
+    #[cfg(target_endian = "little")]
+    #[test]
+    fn tcp6() {
+        assert_eq!(
+            parse_inet_table(&fixture("proc_net/tcp6"), "tcp6"),
+            [
+                inet_socket(30001, "tcp6", "[::]:80", "[::]:0", "LISTEN", (0, 0)),
+                inet_socket(
+                    30002,
+                    "tcp6",
+                    "[::1]:8080",
+                    "[::1]:50000",
+                    "ESTABLISHED",
+                    (0, 0),
+                ),
+            ]
+        );
+    }
+
+    #[cfg(target_endian = "little")]
+    #[test]
+    fn udp() {
+        assert_eq!(
+            parse_inet_table(&fixture("proc_net/udp"), "udp"),
+            [
+                inet_socket(
+                    40001,
+                    "udp",
+                    "0.0.0.0:53",
+                    "0.0.0.0:0",
+                    "UNCONNECTED",
+                    (0, 0)
+                ),
+                inet_socket(
+                    40002,
+                    "udp",
+                    "127.0.0.1:58097",
+                    "127.0.0.1:53",
+                    "CONNECTED",
+                    (0, 256),
+                ),
+            ]
+        );
+        assert_eq!(
+            parse_inet_table(&fixture("proc_net/udp6"), "udp6"),
+            [inet_socket(
+                50001,
+                "udp6",
+                "[::]:5353",
+                "[::]:0",
+                "UNCONNECTED",
+                (0, 0),
+            )]
+        );
+    }
+
+    #[test]
+    fn unix() {
+        assert_eq!(
+            parse_unix_table(&fixture("proc_net/unix")),
+            [
+                unix_socket(60001, "unix stream", "/run/app/app.sock", "LISTEN"),
+                unix_socket(60002, "unix stream", "", "CONNECTED"),
+                unix_socket(60003, "unix dgram", "@/tmp/.X11-unix/X0", "UNCONNECTED"),
+                unix_socket(
+                    60004,
+                    "unix stream",
+                    "/run/dbus/system_bus_socket",
+                    "CONNECTED",
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn only_the_sockets_of_the_process() {
+        let inodes = HashSet::from([48213, 30001, 40002, 60001, 60003, 12345]);
+        let sockets = find_sockets(&inodes, table);
+        assert_eq!(
+            sockets
+                .iter()
+                .map(|socket| (socket.protocol, socket.inode))
+                .collect::<Vec<_>>(),
+            [
+                ("tcp", 48213),
+                ("tcp6", 30001),
+                ("udp", 40002),
+                ("unix stream", 60001),
+                ("unix dgram", 60003),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_tables() {
+        let inodes = HashSet::from([48213, 30001]);
+        let sockets = find_sockets(&inodes, |name| {
+            (!name.ends_with('6')).then(|| table(name)).flatten()
+        });
+        assert_eq!(
+            sockets
+                .iter()
+                .map(|socket| socket.inode)
+                .collect::<Vec<_>>(),
+            [48213]
+        );
+    }
+
+    #[test]
+    fn malformed_lines() {
+        let table = "  sl  local_address rem_address   st tx_queue rx_queue\n\
+            \x20  0: 0100007F:0050\n\
+            \x20  1: nonsense 00000000:0000 0A 00000000:00000000 00:00000000 00000000 0 0 1\n";
+        assert_eq!(parse_inet_table(table, "tcp"), []);
+        assert_eq!(parse_unix_table("Num RefCount\n0000: 00000002\n"), []);
+    }
+}
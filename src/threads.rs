@@ -0,0 +1,407 @@
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{gio, glib};
+use sysinfo::Pid;
+
+use crate::graph::GraphWidget;
+use crate::utils::{
+    compare_ignore_case, create_sorter, create_text_column, describe_process_error, sort_again,
+    update_store, RotateVec,
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io;
+use std::iter;
+use std::rc::Rc;
+use std::time::Instant;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, glib::Properties)]
+    #[properties(wrapper_type = super::ThreadRow)]
+    pub struct ThreadRow {
+        #[property(get, construct_only)]
+        tid: RefCell<u32>,
+        #[property(get, set)]
+        name: RefCell<String>,
+        #[property(get, set)]
+        state: RefCell<String>,
+        #[property(get, set)]
+        cpu: RefCell<f32>,
+        /// The CPU the thread last ran on.
+        #[property(get, set)]
+        processor: RefCell<u32>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ThreadRow {
+        const NAME: &'static str = "ThreadRow";
+        type Type = super::ThreadRow;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for ThreadRow {}
+}
+
+glib::wrapper! {
+    /// A row of the threads list.
+    pub struct ThreadRow(ObjectSubclass<imp::ThreadRow>);
+}
+
+impl ThreadRow {
+    fn new(thread: &ThreadStat, cpu: f32) -> Self {
+        let row: Self = glib::Object::builder().property("tid", thread.tid).build();
+        row.update(thread, cpu);
+        row
+    }
+
+    fn update(&self, thread: &ThreadStat, cpu: f32) {
+        if self.name() != thread.name {
+            self.set_name(thread.name.as_str());
+        }
+        let state = describe_state(thread.state);
+        if self.state() != state {
+            self.set_state(state);
+        }
+        if self.cpu() != cpu {
+            self.set_cpu(cpu);
+        }
+        if self.processor() != thread.processor {
+            self.set_processor(thread.processor);
+        }
+    }
+}
+
+/// The information about a thread read from `/proc/<pid>/task/<tid>/stat`.
+pub struct ThreadStat {
+    pub tid: u32,
+    pub name: String,
+    pub state: char,
+    /// The time spent by the thread in user and kernel mode, in clock ticks.
+    pub cpu_time: u64,
+    pub processor: u32,
+}
+
+/// Returns the threads of the process `pid`, including its main one.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn threads(pid: Pid) -> io::Result<Vec<ThreadStat>> {
+    let mut threads = Vec::new();
+    for entry in std::fs::read_dir(format!("/proc/{pid}/task"))? {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        // The thread may have ended in the meantime.
+        let Ok(stat) = std::fs::read_to_string(entry.path().join("stat")) else {
+            continue;
+        };
+        threads.extend(parse_stat(&stat));
+    }
+    Ok(threads)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn threads(_pid: Pid) -> io::Result<Vec<ThreadStat>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Listing the threads is not supported on this platform",
+    ))
+}
+
+/// Parses a `stat` file. Its second field is the name of the thread in parentheses, which may
+/// contain spaces and parentheses itself, so the other fields are found from the last one.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn parse_stat(stat: &str) -> Option<ThreadStat> {
+    let (tid, rest) = stat.split_once(" (")?;
+    let (name, fields) = rest.rsplit_once(") ")?;
+    // The fields are numbered from 1 in `proc(5)`, and these ones start at the third.
+    let fields = fields.split_whitespace().collect::<Vec<_>>();
+    let field = |number: usize| fields.get(number - 3).copied();
+    let utime = field(14)?.parse::<u64>().ok()?;
+    let stime = field(15)?.parse::<u64>().ok()?;
+    Some(ThreadStat {
+        tid: tid.trim().parse().ok()?,
+        name: name.to_owned(),
+        state: field(3)?.chars().next()?,
+        cpu_time: utime + stime,
+        processor: field(39)?.parse().ok()?,
+    })
+}
+
+/// The number of clock ticks per second, the unit of the CPU times.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn clock_ticks() -> f32 {
+    match unsafe { libc::sysconf(libc::_SC_CLK_TCK) } {
+        ticks if ticks > 0 => ticks as f32,
+        _ => 100.,
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn clock_ticks() -> f32 {
+    100.
+}
+
+fn describe_state(state: char) -> &'static str {
+    match state {
+        'R' => "Running",
+        'S' => "Sleeping",
+        'D' => "Disk sleep",
+        'T' => "Stopped",
+        't' => "Tracing stop",
+        'Z' => "Zombie",
+        'X' => "Dead",
+        'I' => "Idle",
+        _ => "Unknown",
+    }
+}
+
+/// The state of the threads list, shared with its signal handlers.
+struct State {
+    pid: Pid,
+    store: gio::ListStore,
+    sort_model: gtk::SortListModel,
+    selection: gtk::SingleSelection,
+    status: gtk::Label,
+    graph: GraphWidget,
+    graph_title: gtk::Label,
+    /// The CPU time of each thread at the previous update, to compute their CPU usage.
+    previous: RefCell<Option<(Instant, HashMap<u32, u64>)>>,
+    /// The CPU usage history of each thread, displayed in `graph` for the selected one.
+    histories: RefCell<HashMap<u32, RotateVec<f32>>>,
+}
+
+impl State {
+    fn selected(&self) -> Option<ThreadRow> {
+        self.selection.selected_item().and_downcast()
+    }
+
+    fn update(&self) {
+        let threads = match threads(self.pid) {
+            Ok(threads) => threads,
+            Err(error) => {
+                self.store.remove_all();
+                self.status
+                    .set_text(&describe_process_error(&error, "list the threads of"));
+                return;
+            }
+        };
+        self.status.set_text(&format!("{} threads", threads.len()));
+
+        let now = Instant::now();
+        let cpu_times = threads
+            .iter()
+            .map(|thread| (thread.tid, thread.cpu_time))
+            .collect::<HashMap<_, _>>();
+        let previous = self.previous.replace(Some((now, cpu_times)));
+        // The usage is computed between two updates, it is 0 until then.
+        let cpu_usage = |thread: &ThreadStat| {
+            let Some((time, cpu_times)) = &previous else {
+                return 0.;
+            };
+            let Some(previous_cpu_time) = cpu_times.get(&thread.tid) else {
+                return 0.;
+            };
+            let elapsed = now.duration_since(*time).as_secs_f32();
+            if elapsed <= 0. {
+                return 0.;
+            }
+            thread.cpu_time.saturating_sub(*previous_cpu_time) as f32 / clock_ticks() / elapsed
+                * 100.
+        };
+
+        let mut histories = self.histories.borrow_mut();
+        histories.retain(|tid, _| threads.iter().any(|thread| thread.tid == *tid));
+        let threads = threads
+            .iter()
+            .map(|thread| {
+                let cpu = cpu_usage(thread);
+                let history = histories
+                    .entry(thread.tid)
+                    .or_insert_with(|| RotateVec::new(iter::repeat_n(0f32, 61).collect()));
+                history.move_start();
+                *history.get_mut(0).expect("cannot get data 0") = cpu;
+                (thread.tid, (thread, cpu))
+            })
+            .collect::<HashMap<_, _>>();
+        drop(histories);
+
+        update_store(
+            &self.store,
+            threads,
+            ThreadRow::tid,
+            |row, (thread, cpu)| row.update(thread, cpu),
+            |(_, (thread, cpu))| ThreadRow::new(thread, cpu),
+        );
+        sort_again(&self.sort_model);
+        self.show_selected_history();
+    }
+
+    /// Displays the CPU usage history of the selected thread in the graph, or hides the graph if
+    /// no thread is selected.
+    fn show_selected_history(&self) {
+        let selected = self.selected();
+        self.graph.set_visible(selected.is_some());
+        self.graph_title.set_visible(selected.is_some());
+        let Some(selected) = selected else {
+            return;
+        };
+        self.graph_title.set_text(&format!(
+            "Thread usage: {} ({})",
+            selected.name(),
+            selected.tid()
+        ));
+        let histories = self.histories.borrow();
+        let Some(history) = histories.get(&selected.tid()) else {
+            return;
+        };
+        self.graph.data(0, |d| {
+            for i in 0..d.len().min(history.len()) {
+                *d.get_mut(i).expect("cannot get data") = history[i];
+            }
+        });
+        self.graph.queue_draw();
+    }
+}
+
+/// The threads of a process, with their CPU usage. The CPU usage history of the selected thread
+/// is displayed in a graph, which is placed by the caller.
+pub struct ThreadsList {
+    pub widget: gtk::Box,
+    state: Rc<State>,
+}
+
+impl ThreadsList {
+    /// The threads are read again whenever the list is displayed. Their CPU usage is computed
+    /// between two updates.
+    pub fn new(pid: Pid, graph: &GraphWidget, graph_title: &gtk::Label) -> Self {
+        let store = gio::ListStore::new::<ThreadRow>();
+        let column_view = gtk::ColumnView::new(None::<gtk::SelectionModel>);
+        let sort_model = gtk::SortListModel::new(Some(store.clone()), column_view.sorter());
+        let selection = gtk::SingleSelection::builder()
+            .model(&sort_model)
+            .autoselect(false)
+            .can_unselect(true)
+            .build();
+        column_view.set_model(Some(&selection));
+
+        let tid = create_text_column("thread id", "tid", 1., |row: &ThreadRow| {
+            row.tid().to_string()
+        });
+        tid.set_sorter(Some(&create_sorter(|a: &ThreadRow, b: &ThreadRow| {
+            a.tid().cmp(&b.tid())
+        })));
+        let name = create_text_column("name", "name", 0., ThreadRow::name);
+        name.set_sorter(Some(&create_sorter(|a: &ThreadRow, b: &ThreadRow| {
+            compare_ignore_case(&a.name(), &b.name())
+        })));
+        name.set_expand(true);
+        let state = create_text_column("state", "state", 0., ThreadRow::state);
+        state.set_sorter(Some(&create_sorter(|a: &ThreadRow, b: &ThreadRow| {
+            a.state().cmp(&b.state())
+        })));
+        let cpu = create_text_column("cpu usage", "cpu", 1., |row: &ThreadRow| {
+            format!("{:.1}", row.cpu())
+        });
+        cpu.set_sorter(Some(&create_sorter(|a: &ThreadRow, b: &ThreadRow| {
+            a.cpu().total_cmp(&b.cpu())
+        })));
+        let processor = create_text_column("last cpu", "processor", 1., |row: &ThreadRow| {
+            row.processor().to_string()
+        });
+        processor.set_sorter(Some(&create_sorter(|a: &ThreadRow, b: &ThreadRow| {
+            a.processor().cmp(&b.processor())
+        })));
+        for column in [&tid, &name, &state, &cpu, &processor] {
+            column_view.append_column(column);
+        }
+        // The threads using the CPU the most are the ones looked for.
+        column_view.sort_by_column(Some(&cpu), gtk::SortType::Descending);
+
+        let status = gtk::Label::builder().halign(gtk::Align::Start).build();
+        let widget = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(5)
+            .css_classes(vec!["button-with-margin".to_owned()])
+            .build();
+        widget.append(
+            &gtk::ScrolledWindow::builder()
+                .child(&column_view)
+                .hexpand(true)
+                .vexpand(true)
+                .build(),
+        );
+        widget.append(&status);
+
+        let state = Rc::new(State {
+            pid,
+            store,
+            sort_model,
+            selection,
+            status,
+            graph: graph.clone(),
+            graph_title: graph_title.clone(),
+            previous: RefCell::new(None),
+            histories: RefCell::new(HashMap::new()),
+        });
+        state.show_selected_history();
+        state.selection.connect_selection_changed(glib::clone!(
+            #[weak]
+            state,
+            move |_, _, _| state.show_selected_history()
+        ));
+        widget.connect_map(glib::clone!(
+            #[weak]
+            state,
+            move |_| state.update()
+        ));
+
+        Self { widget, state }
+    }
+
+    /// Reads the threads of the process again. It's needed while they are displayed, or while the
+    /// history of one of them is.
+    pub fn update(&self) {
+        if self.widget.is_mapped() || self.state.selected().is_some() {
+            self.state.update();
+        }
+    }
+}
+
+#[cfg(all(test, any(target_os = "linux", target_os = "android")))]
+mod tests {
+    use super::*;
+
+    /// The fields after the name, from the 3rd one (the state) to the 52nd.
+    const FIELDS: &str = "S 1 4240 4240 0 -1 4194368 1543 0 12 0 120 35 0 0 20 0 24 0 1837465 \
+                          2453504000 21583 18446744073709551615 0 0 0 0 0 0 0 0 0 0 0 0 -1 7 0 0 0 \
+                          0 0 0 0 0 0 0 0 0 0";
+
+    #[test]
+    fn stat() {
+        let stat = parse_stat(&format!("4242 (tokio (worker) 1) {FIELDS}\n")).unwrap();
+        assert_eq!(stat.tid, 4242);
+        assert_eq!(stat.name, "tokio (worker) 1");
+        assert_eq!(stat.state, 'S');
+        assert_eq!(stat.cpu_time, 120 + 35);
+        assert_eq!(stat.processor, 7);
+    }
+
+    #[test]
+    fn name_with_parentheses() {
+        for (name, expected) in [("(a) (b)", "a) (b"), ("()", "")] {
+            let stat = parse_stat(&format!("4242 {name} {FIELDS}"));
+            assert_eq!(stat.map(|stat| stat.name).as_deref(), Some(expected));
+        }
+    }
+
+    #[test]
+    fn missing_fields() {
+        assert!(parse_stat("").is_none());
+        assert!(parse_stat(&format!("4242 worker {FIELDS}")).is_none());
+        assert!(parse_stat(&format!("tid (worker) {FIELDS}")).is_none());
+        // Truncated before the processor.
+        assert!(parse_stat(&format!("4242 (worker) {}", &FIELDS[..60])).is_none());
+    }
+}
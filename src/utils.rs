@@ -1,7 +1,15 @@
-use gtk::gio;
 use gtk::prelude::*;
+use gtk::{gio, glib};
+use sysinfo::{Uid, Users};
 
+use crate::filter::{SearchField, SearchOptions};
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::io;
 use std::ops::Index;
+use std::rc::Rc;
 
 pub const MAIN_WINDOW_NAME: &str = "main-window";
 
@@ -151,3 +159,244 @@ pub fn get_main_window() -> Option<gtk::Window> {
         .into_iter()
         .find(|window| window.widget_name() == MAIN_WINDOW_NAME)
 }
+
+pub fn show_error_dialog(fatal: bool, text: &str) {
+    let dialog = gtk::MessageDialog::new(
+        get_main_window().as_ref(),
+        gtk::DialogFlags::MODAL,
+        gtk::MessageType::Error,
+        gtk::ButtonsType::Ok,
+        text,
+    );
+
+    dialog.connect_response(move |dialog, _| {
+        dialog.close();
+
+        if fatal {
+            get_app().quit();
+        }
+    });
+
+    dialog.set_resizable(false);
+    dialog.show();
+}
+
+/// Keeps the list of users around so it doesn't need to be read again for every process.
+pub struct UsersCache {
+    users: Users,
+    // Users which couldn't be found even after refreshing the list.
+    unknown: HashSet<Uid>,
+}
+
+impl UsersCache {
+    pub fn new() -> UsersCache {
+        UsersCache {
+            users: Users::new_with_refreshed_list(),
+            unknown: HashSet::new(),
+        }
+    }
+
+    /// Returns the name of the user, or its ID if no user with this ID exists.
+    pub fn name(&mut self, uid: &Uid) -> String {
+        if self.users.get_user_by_id(uid).is_none() && !self.unknown.contains(uid) {
+            // A new user might have been created in the meantime.
+            self.users.refresh();
+            if self.users.get_user_by_id(uid).is_none() {
+                self.unknown.insert(uid.clone());
+            }
+        }
+        match self.users.get_user_by_id(uid) {
+            Some(user) => user.name().to_owned(),
+            None => uid.to_string(),
+        }
+    }
+}
+
+pub fn format_start_time(start_time: u64) -> String {
+    gtk::glib::DateTime::from_unix_local(start_time as _)
+        .and_then(|date| date.format("%Y-%m-%d %H:%M:%S"))
+        .map(|date| date.to_string())
+        .unwrap_or_default()
+}
+
+/// Creates a button showing the options of a search bar. If `fields` is `Some`, the user can also
+/// pick in which fields to search. `on_changed` is called whenever an option changes.
+pub fn create_search_options_button<F: Fn(SearchOptions, Vec<SearchField>) + 'static>(
+    options: SearchOptions,
+    fields: Option<&[SearchField]>,
+    on_changed: F,
+) -> gtk::MenuButton {
+    let layout = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(2)
+        .build();
+    let regex = gtk::CheckButton::builder()
+        .label("Regular expression")
+        .active(options.regex)
+        .build();
+    let case_sensitive = gtk::CheckButton::builder()
+        .label("Case sensitive")
+        .active(options.case_sensitive)
+        .build();
+    layout.append(&regex);
+    layout.append(&case_sensitive);
+
+    let mut field_checks = Vec::new();
+    if let Some(fields) = fields {
+        layout.append(&gtk::Separator::new(gtk::Orientation::Horizontal));
+        layout.append(
+            &gtk::Label::builder()
+                .label("Search in:")
+                .halign(gtk::Align::Start)
+                .build(),
+        );
+        for field in SearchField::ALL {
+            let check = gtk::CheckButton::builder()
+                .label(field.title())
+                .active(fields.contains(field))
+                .build();
+            layout.append(&check);
+            field_checks.push((*field, check));
+        }
+    }
+
+    // Weak references are used to prevent the buttons from keeping themselves alive.
+    let regex_ref = regex.downgrade();
+    let case_sensitive_ref = case_sensitive.downgrade();
+    let field_refs = field_checks
+        .iter()
+        .map(|(field, check)| (*field, check.downgrade()))
+        .collect::<Vec<_>>();
+    let on_toggled = Rc::new(move || {
+        let (Some(regex), Some(case_sensitive)) =
+            (regex_ref.upgrade(), case_sensitive_ref.upgrade())
+        else {
+            return;
+        };
+        let fields = field_refs
+            .iter()
+            .filter(|(_, check)| check.upgrade().is_some_and(|c| c.is_active()))
+            .map(|(field, _)| *field)
+            .collect();
+        on_changed(
+            SearchOptions {
+                regex: regex.is_active(),
+                case_sensitive: case_sensitive.is_active(),
+            },
+            fields,
+        );
+    });
+    for check in [&regex, &case_sensitive]
+        .into_iter()
+        .chain(field_checks.iter().map(|(_, check)| check))
+    {
+        let on_toggled = Rc::clone(&on_toggled);
+        check.connect_toggled(move |_| on_toggled());
+    }
+
+    gtk::MenuButton::builder()
+        .icon_name("emblem-system-symbolic")
+        .tooltip_text("Search options")
+        .popover(&gtk::Popover::builder().child(&layout).build())
+        .build()
+}
+
+/// Creates a column of a `gtk::ColumnView` whose items are of type `T`. Its cells display the
+/// result of `text`, which is computed again whenever the `property` of their item changes.
+pub fn create_text_column<T: IsA<glib::Object>, F: Fn(&T) -> String + 'static>(
+    title: &str,
+    property: &str,
+    xalign: f32,
+    text: F,
+) -> gtk::ColumnViewColumn {
+    let text = Rc::new(text);
+    let property = property.to_owned();
+    let factory = gtk::SignalListItemFactory::new();
+    factory.connect_setup(move |_, list_item| {
+        let Some(list_item) = list_item.downcast_ref::<gtk::ListItem>() else {
+            return;
+        };
+        let label = gtk::Label::builder().xalign(xalign).build();
+        list_item.set_child(Some(&label));
+        let item = list_item.property_expression_weak("item");
+        let value = item.chain_property::<T>(&property);
+        let text = Rc::clone(&text);
+        gtk::ClosureExpression::with_callback([&item, &value], move |values| {
+            // The first value is the `this` object of the expression, which isn't used.
+            values[1]
+                .get::<Option<glib::Object>>()
+                .ok()
+                .flatten()
+                .and_downcast::<T>()
+                .map(|item| text(&item))
+                .unwrap_or_default()
+        })
+        .bind(&label, "label", gtk::Widget::NONE);
+    });
+    gtk::ColumnViewColumn::builder()
+        .title(title)
+        .factory(&factory)
+        .resizable(true)
+        .build()
+}
+
+/// Creates a sorter comparing the items of type `T` with `compare`.
+pub fn create_sorter<T: IsA<glib::Object>, F: Fn(&T, &T) -> Ordering + 'static>(
+    compare: F,
+) -> gtk::CustomSorter {
+    gtk::CustomSorter::new(
+        move |a, b| match (a.downcast_ref::<T>(), b.downcast_ref::<T>()) {
+            (Some(a), Some(b)) => compare(a, b).into(),
+            _ => gtk::Ordering::Equal,
+        },
+    )
+}
+
+/// Updates the rows of type `R` of `store` in place with `values`, so the selected rows stay
+/// selected. `key` returns the key of the value displayed by a row: the rows whose value is
+/// missing are removed, and `new_row` creates the rows of the values which aren't displayed yet.
+pub fn update_store<K: Eq + Hash, V, R: IsA<glib::Object>>(
+    store: &gio::ListStore,
+    mut values: HashMap<K, V>,
+    key: impl Fn(&R) -> K,
+    update: impl Fn(&R, V),
+    new_row: impl FnMut((K, V)) -> R,
+) {
+    let mut pos = 0;
+    while let Some(row) = store.item(pos).and_downcast::<R>() {
+        match values.remove(&key(&row)) {
+            Some(value) => {
+                update(&row, value);
+                pos += 1;
+            }
+            None => store.remove(pos),
+        }
+    }
+    let new_rows = values.into_iter().map(new_row).collect::<Vec<_>>();
+    store.extend_from_slice(&new_rows);
+}
+
+/// Sorts `sort_model` again after the values of its items changed: list models don't follow
+/// the changes of the properties of their items.
+pub fn sort_again(sort_model: &gtk::SortListModel) {
+    if let Some(sorter) = sort_model.sorter() {
+        sorter.changed(gtk::SorterChange::Different);
+    }
+}
+
+/// Returns the message displayed when something about a process couldn't be read, like
+/// "You are not allowed to `action` this process".
+pub fn describe_process_error(error: &io::Error, action: &str) -> String {
+    match error.kind() {
+        io::ErrorKind::PermissionDenied => format!("You are not allowed to {action} this process"),
+        io::ErrorKind::NotFound => "The process doesn't exist anymore".to_owned(),
+        _ => error.to_string(),
+    }
+}
+
+/// Compares two strings without taking their case into account.
+pub fn compare_ignore_case(a: &str, b: &str) -> Ordering {
+    a.chars()
+        .flat_map(char::to_lowercase)
+        .cmp(b.chars().flat_map(char::to_lowercase))
+}